@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(McpServerLogs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(McpServerLogs::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(McpServerLogs::ServerName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(McpServerLogs::Stream).string().not_null())
+                    .col(ColumnDef::new(McpServerLogs::Line).text().not_null())
+                    .col(
+                        ColumnDef::new(McpServerLogs::Timestamp)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Logs are always read per server, newest first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_mcp_server_logs_server_name_timestamp")
+                    .table(McpServerLogs::Table)
+                    .col(McpServerLogs::ServerName)
+                    .col(McpServerLogs::Timestamp)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_mcp_server_logs_server_name_timestamp")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(McpServerLogs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum McpServerLogs {
+    Table,
+    Id,
+    ServerName,
+    Stream,
+    Line,
+    Timestamp,
+}
@@ -3,6 +3,10 @@ use sea_orm_migration::prelude::*;
 mod m20240101_000001_create_mcp_servers_table;
 mod m20240101_000002_create_external_mcp_clients_table;
 mod m20240101_000003_create_mcp_request_logs_table;
+mod m20240101_000004_create_mcp_server_logs_table;
+mod m20240101_000005_add_enabled_to_mcp_servers;
+mod m20240101_000006_add_batch_id_to_mcp_request_logs;
+mod m20240101_000007_create_secrets_table;
 
 pub struct Migrator;
 
@@ -13,6 +17,10 @@ impl MigratorTrait for Migrator {
             Box::new(m20240101_000001_create_mcp_servers_table::Migration),
             Box::new(m20240101_000002_create_external_mcp_clients_table::Migration),
             Box::new(m20240101_000003_create_mcp_request_logs_table::Migration),
+            Box::new(m20240101_000004_create_mcp_server_logs_table::Migration),
+            Box::new(m20240101_000005_add_enabled_to_mcp_servers::Migration),
+            Box::new(m20240101_000006_add_batch_id_to_mcp_request_logs::Migration),
+            Box::new(m20240101_000007_create_secrets_table::Migration),
         ]
     }
 }
@@ -20,6 +20,7 @@ pub struct LogQueryParams {
     mcp_session_id: Option<String>,
     status_code: Option<i32>,
     method: Option<String>,
+    batch_id: Option<String>,
     start_time: Option<String>,
     end_time: Option<String>,
     // Pagination
@@ -112,6 +113,7 @@ pub async fn get_mcp_request_logs(
         || params.mcp_session_id.is_some()
         || params.status_code.is_some()
         || params.method.is_some()
+        || params.batch_id.is_some()
         || params.start_time.is_some()
         || params.end_time.is_some()
     {
@@ -121,6 +123,7 @@ pub async fn get_mcp_request_logs(
             mcp_session_id: params.mcp_session_id,
             status_code: params.status_code,
             method: params.method,
+            batch_id: params.batch_id,
             start_time: params.start_time.and_then(|s| s.parse().ok()),
             end_time: params.end_time.and_then(|s| s.parse().ok()),
         })
@@ -192,6 +195,7 @@ pub async fn get_mcp_request_log_stats(
         || params.mcp_session_id.is_some()
         || params.status_code.is_some()
         || params.method.is_some()
+        || params.batch_id.is_some()
         || params.start_time.is_some()
         || params.end_time.is_some()
     {
@@ -201,6 +205,7 @@ pub async fn get_mcp_request_log_stats(
             mcp_session_id: params.mcp_session_id,
             status_code: params.status_code,
             method: params.method,
+            batch_id: params.batch_id,
             start_time: params.start_time.and_then(|s| s.parse().ok()),
             end_time: params.end_time.and_then(|s| s.parse().ok()),
         })
@@ -281,6 +286,7 @@ mod tests {
             response_body: None,
             error_message: None,
             duration_ms: Some(100),
+            batch_id: None,
         };
 
         let active_model: ActiveModel = log_request.into();
@@ -1,16 +1,35 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::Json,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Json,
+    },
     routing::{delete, get, post},
     Router,
 };
+use futures_util::stream::Stream;
 use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use utoipa::ToSchema;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::models::mcp_server::{oauth::AuthResponse, ConnectorCatalogEntry, Model as MCPServer};
+use crate::models::mcp_server::{
+    oauth::AuthResponse,
+    sandbox::{self, get_mcp_server_log_sink, get_mcp_server_statuses, ServerState, ServerStatus},
+    ConnectorCatalogEntry, Model as MCPServer,
+};
+use crate::models::mcp_server_log::{LogStream, Model as MCPServerLog};
+use crate::utils::node::NpmPackagePin;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = InstalledMCPServer)]
+pub struct InstalledServer {
+    #[serde(flatten)]
+    pub server: MCPServer,
+    pub status: ServerStatus,
+}
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[schema(as = InstallMCPServerRequest)]
@@ -24,6 +43,35 @@ pub struct StartOAuthRequest {
     mcp_connector_id: String,
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ServerLogQueryParams {
+    stream: Option<LogStream>,
+    page: Option<u64>,
+    page_size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UninstallParams {
+    /// Keep the server's data directory instead of deleting it
+    keep_data: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = PaginatedMCPServerLogResponse)]
+pub struct PaginatedServerLogResponse {
+    data: Vec<MCPServerLog>,
+    total: u64,
+    page: u64,
+    page_size: u64,
+}
+
+/// Outcome of upgrading the package of an installed server
+pub enum PackageUpgrade {
+    Upgraded(NpmPackagePin),
+    /// Not an `npx` server, or its package is pinned to an exact version in its args
+    NotUpgradable,
+}
+
 pub struct Service {
     db: Arc<DatabaseConnection>,
 }
@@ -33,10 +81,21 @@ impl Service {
         Self { db: Arc::new(db) }
     }
 
-    async fn get_installed_mcp_servers(&self) -> Result<Vec<MCPServer>, String> {
-        MCPServer::load_installed_mcp_servers(&self.db)
+    async fn get_installed_mcp_servers(&self) -> Result<Vec<InstalledServer>, String> {
+        let servers = MCPServer::load_installed_mcp_servers(&self.db)
             .await
-            .map_err(|e| format!("Failed to load installed MCP servers: {e}"))
+            .map_err(|e| format!("Failed to load installed MCP servers: {e}"))?;
+        let mut statuses = get_mcp_server_statuses().await;
+
+        Ok(servers
+            .into_iter()
+            .map(|server| {
+                let status = statuses
+                    .remove(&server.name)
+                    .unwrap_or_else(ServerStatus::stopped);
+                InstalledServer { server, status }
+            })
+            .collect())
     }
 
     async fn get_mcp_connector_catalog(&self) -> Result<Vec<ConnectorCatalogEntry>, String> {
@@ -56,8 +115,152 @@ impl Service {
         Ok(())
     }
 
-    async fn uninstall_mcp_server(&self, mcp_server_name: String) -> Result<(), String> {
-        MCPServer::uninstall_mcp_server(&self.db, &mcp_server_name)
+    /// Get a page of a server's output, from the database if the server persists its logs
+    /// and from the in-memory buffer otherwise. Returns `None` if the server isn't installed.
+    async fn get_mcp_server_logs(
+        &self,
+        mcp_server_name: &str,
+        stream: Option<LogStream>,
+        page: u64,
+        page_size: u64,
+    ) -> Result<Option<(Vec<MCPServerLog>, u64)>, String> {
+        let Some(definition) = MCPServer::find_by_name(&self.db, mcp_server_name)
+            .await
+            .map_err(|e| format!("Failed to load MCP server: {e}"))?
+        else {
+            return Ok(None);
+        };
+
+        if definition.server_config.log_retention_days.is_some() {
+            MCPServerLog::get_server_logs(&self.db, mcp_server_name, stream, page, page_size)
+                .await
+                .map(Some)
+                .map_err(|e| format!("Failed to get server logs: {e}"))
+        } else {
+            let sink = get_mcp_server_log_sink(mcp_server_name).await;
+            Ok(Some(sink.page(stream, page, page_size)))
+        }
+    }
+
+    /// Get the status and health of a server, `None` if it isn't installed
+    async fn get_mcp_server_status(
+        &self,
+        mcp_server_name: &str,
+    ) -> Result<Option<ServerStatus>, String> {
+        if !self.is_installed(mcp_server_name).await? {
+            return Ok(None);
+        }
+        let status = sandbox::get_mcp_server_status(mcp_server_name)
+            .await
+            .unwrap_or_else(ServerStatus::stopped);
+        Ok(Some(status))
+    }
+
+    /// Start a server and keep it enabled across launches, `None` if it isn't installed
+    async fn start_mcp_server(
+        &self,
+        mcp_server_name: &str,
+    ) -> Result<Option<ServerStatus>, String> {
+        let Some(definition) = MCPServer::find_by_name(&self.db, mcp_server_name)
+            .await
+            .map_err(|e| format!("Failed to load MCP server: {e}"))?
+        else {
+            return Ok(None);
+        };
+        MCPServer::set_enabled(&self.db, mcp_server_name, true)
+            .await
+            .map_err(|e| format!("Failed to enable MCP server: {e}"))?;
+
+        if !is_running(mcp_server_name).await {
+            sandbox::start_mcp_server(&definition).await?;
+        }
+        self.get_mcp_server_status(mcp_server_name).await
+    }
+
+    /// Stop a server and keep it stopped on the next launch, `None` if it isn't installed
+    async fn stop_mcp_server(&self, mcp_server_name: &str) -> Result<Option<ServerStatus>, String> {
+        if !self.is_installed(mcp_server_name).await? {
+            return Ok(None);
+        }
+        MCPServer::set_enabled(&self.db, mcp_server_name, false)
+            .await
+            .map_err(|e| format!("Failed to disable MCP server: {e}"))?;
+
+        // A crashed server is still known to the manager and has to be cleaned up too
+        if sandbox::get_mcp_server_status(mcp_server_name)
+            .await
+            .is_some()
+        {
+            sandbox::stop_mcp_server(mcp_server_name).await?;
+        }
+        self.get_mcp_server_status(mcp_server_name).await
+    }
+
+    /// Stop a server if it's running and start it again, `None` if it isn't installed
+    async fn restart_mcp_server(
+        &self,
+        mcp_server_name: &str,
+    ) -> Result<Option<ServerStatus>, String> {
+        if !self.is_installed(mcp_server_name).await? {
+            return Ok(None);
+        }
+        if sandbox::get_mcp_server_status(mcp_server_name)
+            .await
+            .is_some()
+        {
+            sandbox::stop_mcp_server(mcp_server_name).await?;
+        }
+        self.start_mcp_server(mcp_server_name).await
+    }
+
+    /// Upgrade the npm package of a server to the newest version, restarting the server if
+    /// it's running. `None` if the server isn't installed.
+    async fn upgrade_mcp_server_package(
+        &self,
+        mcp_server_name: &str,
+    ) -> Result<Option<PackageUpgrade>, String> {
+        let Some(definition) = MCPServer::find_by_name(&self.db, mcp_server_name)
+            .await
+            .map_err(|e| format!("Failed to load MCP server: {e}"))?
+        else {
+            return Ok(None);
+        };
+        let Some(spec) = sandbox::upgradable_npm_package(&definition.server_config) else {
+            return Ok(Some(PackageUpgrade::NotUpgradable));
+        };
+
+        // The package files are replaced, so the server can't keep running off them
+        let was_running = sandbox::get_mcp_server_status(mcp_server_name)
+            .await
+            .is_some();
+        if was_running {
+            sandbox::stop_mcp_server(mcp_server_name).await?;
+        }
+        let upgraded = sandbox::upgrade_mcp_server_npm_package(
+            mcp_server_name,
+            spec,
+            definition.server_config.run_install_scripts,
+        )
+        .await;
+        if was_running {
+            sandbox::activate_mcp_server(&definition).await?;
+        }
+        upgraded.map(|pin| Some(PackageUpgrade::Upgraded(pin)))
+    }
+
+    async fn is_installed(&self, mcp_server_name: &str) -> Result<bool, String> {
+        MCPServer::find_by_name(&self.db, mcp_server_name)
+            .await
+            .map(|definition| definition.is_some())
+            .map_err(|e| format!("Failed to load MCP server: {e}"))
+    }
+
+    async fn uninstall_mcp_server(
+        &self,
+        mcp_server_name: String,
+        keep_data: bool,
+    ) -> Result<(), String> {
+        MCPServer::uninstall_mcp_server(&self.db, &mcp_server_name, keep_data)
             .await
             .map_err(|e| format!("Failed to uninstall server: {e}"))?;
 
@@ -70,13 +273,13 @@ impl Service {
     path = "/api/mcp_server",
     tag = "mcp_server",
     responses(
-        (status = 200, description = "List of installed MCP servers", body = Vec<MCPServer>),
+        (status = 200, description = "List of installed MCP servers", body = Vec<InstalledServer>),
         (status = 500, description = "Internal server error")
     )
 )]
 pub async fn get_installed_mcp_servers(
     State(service): State<Arc<Service>>,
-) -> Result<Json<Vec<MCPServer>>, StatusCode> {
+) -> Result<Json<Vec<InstalledServer>>, StatusCode> {
     service
         .get_installed_mcp_servers()
         .await
@@ -154,7 +357,8 @@ pub async fn start_mcp_server_oauth(
     path = "/api/mcp_server/{mcp_server_name}",
     tag = "mcp_server",
     params(
-        ("mcp_server_name" = String, Path, description = "Name of the MCP server to uninstall")
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server to uninstall"),
+        UninstallParams
     ),
     responses(
         (status = 200, description = "MCP server uninstalled successfully"),
@@ -164,14 +368,221 @@ pub async fn start_mcp_server_oauth(
 pub async fn uninstall_mcp_server(
     State(service): State<Arc<Service>>,
     Path(mcp_server_name): Path<String>,
+    Query(params): Query<UninstallParams>,
 ) -> Result<StatusCode, StatusCode> {
     service
-        .uninstall_mcp_server(mcp_server_name)
+        .uninstall_mcp_server(mcp_server_name, params.keep_data.unwrap_or(false))
         .await
         .map(|_| StatusCode::OK)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/mcp_server/{mcp_server_name}/logs",
+    tag = "mcp_server",
+    params(
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server"),
+        ServerLogQueryParams
+    ),
+    responses(
+        (status = 200, description = "Paginated stdout/stderr lines of the MCP server, newest first", body = PaginatedServerLogResponse),
+        (status = 404, description = "MCP server not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_mcp_server_logs(
+    State(service): State<Arc<Service>>,
+    Path(mcp_server_name): Path<String>,
+    Query(params): Query<ServerLogQueryParams>,
+) -> Result<Json<PaginatedServerLogResponse>, StatusCode> {
+    let page = params.page.unwrap_or(1);
+    let page_size = params.page_size.unwrap_or(100);
+
+    match service
+        .get_mcp_server_logs(&mcp_server_name, params.stream, page, page_size)
+        .await
+    {
+        Ok(Some((data, total))) => Ok(Json(PaginatedServerLogResponse {
+            data,
+            total,
+            page,
+            page_size,
+        })),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/mcp_server/{mcp_server_name}/logs/stream",
+    tag = "mcp_server",
+    params(
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server")
+    ),
+    responses(
+        (status = 200, description = "Server-sent `log` events, one per new stdout/stderr line", content_type = "text/event-stream", body = MCPServerLog),
+        (status = 404, description = "MCP server not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn stream_mcp_server_logs(
+    State(service): State<Arc<Service>>,
+    Path(mcp_server_name): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
+    match service.is_installed(&mcp_server_name).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    let rx = get_mcp_server_log_sink(&mcp_server_name).await.subscribe();
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(line) => return Some((Event::default().event("log").json_data(line), rx)),
+                // A slow client misses lines rather than holding up the server
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Whether the manager has a live (not given up on) instance of a server
+async fn is_running(mcp_server_name: &str) -> bool {
+    sandbox::get_mcp_server_status(mcp_server_name)
+        .await
+        .is_some_and(|status| {
+            matches!(status.state, ServerState::Running | ServerState::Restarting)
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/mcp_server/{mcp_server_name}/status",
+    tag = "mcp_server",
+    params(
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server")
+    ),
+    responses(
+        (status = 200, description = "Process state and latest health check of the MCP server", body = ServerStatus),
+        (status = 404, description = "MCP server not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_mcp_server_status(
+    State(service): State<Arc<Service>>,
+    Path(mcp_server_name): Path<String>,
+) -> Result<Json<ServerStatus>, StatusCode> {
+    match service.get_mcp_server_status(&mcp_server_name).await {
+        Ok(Some(status)) => Ok(Json(status)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/mcp_server/{mcp_server_name}/start",
+    tag = "mcp_server",
+    params(
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server to start")
+    ),
+    responses(
+        (status = 200, description = "MCP server started, or already running", body = ServerStatus),
+        (status = 404, description = "MCP server not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn start_mcp_server(
+    State(service): State<Arc<Service>>,
+    Path(mcp_server_name): Path<String>,
+) -> Result<Json<ServerStatus>, StatusCode> {
+    match service.start_mcp_server(&mcp_server_name).await {
+        Ok(Some(status)) => Ok(Json(status)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/mcp_server/{mcp_server_name}/stop",
+    tag = "mcp_server",
+    params(
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server to stop")
+    ),
+    responses(
+        (status = 200, description = "MCP server stopped, it stays stopped until started again", body = ServerStatus),
+        (status = 404, description = "MCP server not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn stop_mcp_server(
+    State(service): State<Arc<Service>>,
+    Path(mcp_server_name): Path<String>,
+) -> Result<Json<ServerStatus>, StatusCode> {
+    match service.stop_mcp_server(&mcp_server_name).await {
+        Ok(Some(status)) => Ok(Json(status)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/mcp_server/{mcp_server_name}/restart",
+    tag = "mcp_server",
+    params(
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server to restart")
+    ),
+    responses(
+        (status = 200, description = "MCP server restarted", body = ServerStatus),
+        (status = 404, description = "MCP server not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn restart_mcp_server(
+    State(service): State<Arc<Service>>,
+    Path(mcp_server_name): Path<String>,
+) -> Result<Json<ServerStatus>, StatusCode> {
+    match service.restart_mcp_server(&mcp_server_name).await {
+        Ok(Some(status)) => Ok(Json(status)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/mcp_server/{mcp_server_name}/upgrade",
+    tag = "mcp_server",
+    params(
+        ("mcp_server_name" = String, Path, description = "Name of the MCP server whose npm package to upgrade")
+    ),
+    responses(
+        (status = 200, description = "Package upgraded and pinned to the new version, the server is restarted if it was running", body = NpmPackagePin),
+        (status = 400, description = "MCP server doesn't run an npm package, or pins it to an exact version"),
+        (status = 404, description = "MCP server not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn upgrade_mcp_server_package(
+    State(service): State<Arc<Service>>,
+    Path(mcp_server_name): Path<String>,
+) -> Result<Json<NpmPackagePin>, StatusCode> {
+    match service.upgrade_mcp_server_package(&mcp_server_name).await {
+        Ok(Some(PackageUpgrade::Upgraded(pin))) => Ok(Json(pin)),
+        Ok(Some(PackageUpgrade::NotUpgradable)) => Err(StatusCode::BAD_REQUEST),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
 pub fn create_router(db: DatabaseConnection) -> Router {
     let service = Arc::new(Service::new(db));
 
@@ -181,13 +592,28 @@ pub fn create_router(db: DatabaseConnection) -> Router {
         .route("/catalog/install", post(install_mcp_server_from_catalog))
         .route("/start_oauth", post(start_mcp_server_oauth))
         .route("/{mcp_server_name}", delete(uninstall_mcp_server))
+        .route("/{mcp_server_name}/logs", get(get_mcp_server_logs))
+        .route(
+            "/{mcp_server_name}/logs/stream",
+            get(stream_mcp_server_logs),
+        )
+        .route("/{mcp_server_name}/status", get(get_mcp_server_status))
+        .route("/{mcp_server_name}/start", post(start_mcp_server))
+        .route("/{mcp_server_name}/stop", post(stop_mcp_server))
+        .route("/{mcp_server_name}/restart", post(restart_mcp_server))
+        .route(
+            "/{mcp_server_name}/upgrade",
+            post(upgrade_mcp_server_package),
+        )
         .with_state(service)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::mcp_server::{ActiveModel, Column, Entity, ServerConfig};
+    use crate::models::mcp_server::{
+        sandbox::ServerState, ActiveModel, Column, Entity, ServerConfig,
+    };
     use crate::test_fixtures::database;
     use axum::{
         body::Body,
@@ -207,6 +633,7 @@ mod tests {
             command: "node".to_string(),
             args: vec!["index.js".to_string()],
             env: std::collections::HashMap::new(),
+            ..Default::default()
         };
 
         let active_model = ActiveModel {
@@ -242,7 +669,7 @@ mod tests {
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
-        let result: Vec<MCPServer> = serde_json::from_slice(&body).unwrap();
+        let result: Vec<InstalledServer> = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(result.len(), 0);
     }
@@ -274,14 +701,19 @@ mod tests {
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
-        let result: Vec<MCPServer> = serde_json::from_slice(&body).unwrap();
+        let result: Vec<InstalledServer> = serde_json::from_slice(&body).unwrap();
 
         assert_eq!(result.len(), 2);
 
         // Check that the servers have the expected names
-        let names: Vec<String> = result.iter().map(|s| s.name.clone()).collect();
+        let names: Vec<String> = result.iter().map(|s| s.server.name.clone()).collect();
         assert!(names.contains(&"test-server-1".to_string()));
         assert!(names.contains(&"test-server-2".to_string()));
+
+        // Servers that were never started report as stopped
+        assert!(result
+            .iter()
+            .all(|s| s.status.state == ServerState::Stopped && s.status.last_exit.is_none()));
     }
 
     #[rstest]
@@ -456,7 +888,7 @@ mod tests {
 
         // Test uninstall_mcp_server
         let uninstall_result = service
-            .uninstall_mcp_server("non-existent".to_string())
+            .uninstall_mcp_server("non-existent".to_string(), false)
             .await;
         // Uninstall returns Ok even for non-existent servers (no-op)
         assert!(uninstall_result.is_ok());
@@ -494,7 +926,7 @@ mod tests {
             let service_clone = service.clone();
             let handle = tokio::spawn(async move {
                 service_clone
-                    .uninstall_mcp_server(format!("concurrent-server-{i}"))
+                    .uninstall_mcp_server(format!("concurrent-server-{i}"), false)
                     .await
             });
             handles.push(handle);
@@ -509,4 +941,251 @@ mod tests {
         let servers = service.get_installed_mcp_servers().await.unwrap();
         assert_eq!(servers.len(), 0);
     }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_mcp_server_logs_from_buffer(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        create_test_mcp_server(&db, "logs-buffer-server").await;
+
+        let sink = get_mcp_server_log_sink("logs-buffer-server").await;
+        sink.push(LogStream::Stdout, "booting");
+        sink.push(LogStream::Stderr, "Error: missing API key");
+
+        let app = app(db);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/logs-buffer-server/logs?stream=stderr")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let result: PaginatedServerLogResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(result.total, 1);
+        assert_eq!(result.data.len(), 1);
+        assert_eq!(result.data[0].line, "Error: missing API key");
+        assert_eq!(result.data[0].stream, LogStream::Stderr);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_mcp_server_logs_not_found(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let app = app(db);
+
+        for uri in [
+            "/non-existent/logs",
+            "/non-existent/logs/stream",
+            "/non-existent/status",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_mcp_server_status_not_running(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        create_test_mcp_server(&db, "idle-server").await;
+        let app = app(db);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/idle-server/status")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["state"], "stopped");
+        assert_eq!(status["health"]["state"], "stopped");
+        assert_eq!(status["health"]["consecutive_failures"], 0);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_start_stop_restart_mcp_server(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        // A remote server starts without spawning anything, nothing listens on the URL
+        let server_config = ServerConfig {
+            transport: "http".to_string(),
+            args: vec!["http://127.0.0.1:9/mcp".to_string()],
+            ..Default::default()
+        };
+        ActiveModel {
+            name: Set("lifecycle-server".to_string()),
+            server_config: Set(serde_json::to_string(&server_config).unwrap()),
+            created_at: Set(chrono::Utc::now()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let app = app(db.clone());
+
+        async fn post_action(
+            app: &Router,
+            db: &DatabaseConnection,
+            action: &str,
+        ) -> (String, bool) {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/lifecycle-server/{action}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let enabled = Entity::find()
+                .filter(Column::Name.eq("lifecycle-server"))
+                .one(db)
+                .await
+                .unwrap()
+                .unwrap()
+                .enabled;
+            (status["state"].as_str().unwrap().to_string(), enabled)
+        }
+
+        assert_eq!(
+            post_action(&app, &db, "start").await,
+            ("running".to_string(), true)
+        );
+        // Starting a running server is a no-op
+        assert_eq!(
+            post_action(&app, &db, "start").await,
+            ("running".to_string(), true)
+        );
+        assert_eq!(
+            post_action(&app, &db, "stop").await,
+            ("stopped".to_string(), false)
+        );
+        assert_eq!(
+            post_action(&app, &db, "restart").await,
+            ("running".to_string(), true)
+        );
+        assert_eq!(
+            post_action(&app, &db, "stop").await,
+            ("stopped".to_string(), false)
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_lifecycle_actions_not_found(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let app = app(db);
+
+        for action in ["start", "stop", "restart", "upgrade"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/non-existent/{action}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_upgrade_needs_npm_package(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        create_test_mcp_server(&db, "node-server").await;
+        let app = app(db);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/node-server/upgrade")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_stream_mcp_server_logs(#[future] database: DatabaseConnection) {
+        use http_body_util::BodyExt;
+
+        let db = database.await;
+        create_test_mcp_server(&db, "logs-stream-server").await;
+
+        let app = app(db);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/logs-stream-server/logs/stream")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+
+        get_mcp_server_log_sink("logs-stream-server")
+            .await
+            .push(LogStream::Stderr, "live line");
+
+        let mut body = response.into_body();
+        let frame = body.frame().await.unwrap().unwrap();
+        let chunk = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+
+        assert!(chunk.starts_with("event: log\n"));
+        assert!(chunk.contains("live line"));
+    }
 }
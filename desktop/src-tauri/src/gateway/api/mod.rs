@@ -1,11 +1,17 @@
-use axum::Router;
+use axum::{middleware, Router};
 use sea_orm::DatabaseConnection;
 
+use crate::models::secret::secret_store;
+
 pub mod external_mcp_client;
 pub mod mcp_request_log;
 pub mod mcp_server;
+pub mod runtime;
+pub mod secret;
 
 pub fn create_router(db: DatabaseConnection) -> Router {
+    let secrets = secret_store();
+
     Router::new()
         .nest(
             "/external_mcp_client",
@@ -16,4 +22,11 @@ pub fn create_router(db: DatabaseConnection) -> Router {
             mcp_request_log::create_router(db.clone()),
         )
         .nest("/mcp_server", mcp_server::create_router(db))
+        .nest("/runtime", runtime::create_router())
+        .nest("/secret", secret::create_router(secrets.clone()))
+        // Whatever a response contains, e.g. a server config, secret values don't leave
+        .layer(middleware::map_response_with_state(
+            secrets,
+            secret::redact_response,
+        ))
 }
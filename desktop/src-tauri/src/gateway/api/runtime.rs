@@ -0,0 +1,113 @@
+use axum::{extract::State, http::StatusCode, response::Json, routing::get, Router};
+use std::sync::Arc;
+
+use crate::utils::runtime::{self, RuntimeInfo, RuntimeSettings};
+
+pub struct Service;
+
+impl Service {
+    async fn get_runtime_info(&self) -> Result<RuntimeInfo, String> {
+        // Detection runs the executables to get their versions
+        tokio::task::spawn_blocking(runtime::detect_runtime_info)
+            .await
+            .map_err(|e| format!("Failed to detect runtime: {e}"))
+    }
+
+    async fn update_runtime_settings(&self, settings: RuntimeSettings) -> Result<(), String> {
+        runtime::update_settings(settings)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/runtime",
+    tag = "runtime",
+    responses(
+        (status = 200, description = "Node.js, npm and npx found on the system, with their versions", body = RuntimeInfo),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_runtime_info(
+    State(service): State<Arc<Service>>,
+) -> Result<Json<RuntimeInfo>, StatusCode> {
+    service
+        .get_runtime_info()
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/runtime",
+    tag = "runtime",
+    request_body = RuntimeSettings,
+    responses(
+        (status = 200, description = "Settings saved, with what is used from now on", body = RuntimeInfo),
+        (status = 400, description = "Preferred interpreter doesn't exist"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn update_runtime_settings(
+    State(service): State<Arc<Service>>,
+    Json(settings): Json<RuntimeSettings>,
+) -> Result<Json<RuntimeInfo>, StatusCode> {
+    runtime::validate_settings(&settings).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    service
+        .update_runtime_settings(settings)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    get_runtime_info(State(service)).await
+}
+
+pub fn create_router() -> Router {
+    let service = Arc::new(Service);
+
+    Router::new()
+        .route("/", get(get_runtime_info).put(update_runtime_settings))
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_get_runtime_info() {
+        let response = create_router()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let info: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for key in ["node", "npm", "npx", "settings"] {
+            assert!(info.get(key).is_some(), "missing {key}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_preferred_node_is_rejected() {
+        let response = create_router()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({ "preferred_node_path": "/does/not/exist/node" }).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
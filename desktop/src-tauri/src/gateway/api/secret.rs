@@ -0,0 +1,306 @@
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::models::secret::{self, SecretStore};
+
+/// A stored secret, its value is never returned
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Secret)]
+pub struct SecretInfo {
+    pub name: String,
+    /// What to put in a server's env values or args to use the secret
+    pub reference: String,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTime<Utc>,
+    #[schema(value_type = String, format = DateTime)]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<secret::Model> for SecretInfo {
+    fn from(secret: secret::Model) -> Self {
+        Self {
+            reference: format!("{}{}", secret::SECRET_SCHEME, secret.name),
+            name: secret.name,
+            created_at: secret.created_at,
+            updated_at: secret.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = SetSecretRequest)]
+pub struct SetSecretRequest {
+    value: String,
+}
+
+pub struct Service {
+    store: Option<Arc<SecretStore>>,
+}
+
+impl Service {
+    pub fn new(store: Option<Arc<SecretStore>>) -> Self {
+        Self { store }
+    }
+
+    fn store(&self) -> Result<&SecretStore, String> {
+        self.store
+            .as_deref()
+            .ok_or_else(|| "Secret store is not available".to_string())
+    }
+
+    async fn get_secrets(&self) -> Result<Vec<SecretInfo>, String> {
+        let secrets = self.store()?.list().await?;
+        Ok(secrets.into_iter().map(SecretInfo::from).collect())
+    }
+
+    async fn set_secret(&self, name: &str, value: &str) -> Result<SecretInfo, String> {
+        self.store()?.set(name, value).await.map(SecretInfo::from)
+    }
+
+    async fn delete_secret(&self, name: &str) -> Result<bool, String> {
+        self.store()?.delete(name).await
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/secret",
+    tag = "secret",
+    responses(
+        (status = 200, description = "Stored secrets, without their values", body = Vec<SecretInfo>),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_secrets(
+    State(service): State<Arc<Service>>,
+) -> Result<Json<Vec<SecretInfo>>, StatusCode> {
+    service
+        .get_secrets()
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/secret/{name}",
+    tag = "secret",
+    params(
+        ("name" = String, Path, description = "Name of the secret, referenced as secret://<name>")
+    ),
+    request_body = SetSecretRequest,
+    responses(
+        (status = 200, description = "Secret created or replaced", body = SecretInfo),
+        (status = 400, description = "Invalid secret name"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn set_secret(
+    State(service): State<Arc<Service>>,
+    Path(name): Path<String>,
+    Json(payload): Json<SetSecretRequest>,
+) -> Result<Json<SecretInfo>, StatusCode> {
+    secret::validate_name(&name).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    service
+        .set_secret(&name, &payload.value)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/secret/{name}",
+    tag = "secret",
+    params(
+        ("name" = String, Path, description = "Name of the secret to delete")
+    ),
+    responses(
+        (status = 200, description = "Secret deleted"),
+        (status = 404, description = "Secret not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn delete_secret(
+    State(service): State<Arc<Service>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    match service.delete_secret(&name).await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Replace secret values in a response body with their references. Event streams are
+/// passed through, the server log lines they carry are redacted when they're recorded.
+pub async fn redact_response(
+    State(store): State<Option<Arc<SecretStore>>>,
+    response: Response,
+) -> Response {
+    let Some(store) = store else {
+        return response;
+    };
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"text/event-stream"));
+    if is_event_stream {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let body = match std::str::from_utf8(&bytes) {
+        Ok(text) => Body::from(store.redact(text)),
+        Err(_) => Body::from(bytes),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, body)
+}
+
+pub fn create_router(store: Option<Arc<SecretStore>>) -> Router {
+    let service = Arc::new(Service::new(store));
+
+    Router::new()
+        .route("/", get(get_secrets))
+        .route("/{name}", put(set_secret).delete(delete_secret))
+        .with_state(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::database;
+    use axum::{http::Request, middleware};
+    use rstest::*;
+    use sea_orm::DatabaseConnection;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    async fn open_store(db: DatabaseConnection, key_dir: &tempfile::TempDir) -> Arc<SecretStore> {
+        Arc::new(
+            SecretStore::open(db, &key_dir.path().join("secrets.key"))
+                .await
+                .unwrap(),
+        )
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_list_and_delete_secret(#[future] database: DatabaseConnection) {
+        let key_dir = tempfile::tempdir().unwrap();
+        let app = create_router(Some(open_store(database.await, &key_dir).await));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/github_token")
+                    .header("content-type", "application/json")
+                    .body(Body::from(json!({ "value": "ghp_abc123" }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(!body.contains("ghp_abc123"));
+        let secret: SecretInfo = serde_json::from_str(&body).unwrap();
+        assert_eq!(secret.reference, "secret://github_token");
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let secrets: Vec<SecretInfo> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(secrets.len(), 1);
+        assert_eq!(secrets[0].name, "github_token");
+
+        let delete = || {
+            Request::builder()
+                .method("DELETE")
+                .uri("/github_token")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(delete()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(delete()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_invalid_secret_name_is_rejected(#[future] database: DatabaseConnection) {
+        let key_dir = tempfile::tempdir().unwrap();
+        let app = create_router(Some(open_store(database.await, &key_dir).await));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/bad%20name")
+                    .header("content-type", "application/json")
+                    .body(Body::from(json!({ "value": "value" }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_responses_are_redacted(#[future] database: DatabaseConnection) {
+        let key_dir = tempfile::tempdir().unwrap();
+        let store = open_store(database.await, &key_dir).await;
+        store.set("api_key", "sk-live-123456").await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/config",
+                get(|| async { Json(json!({ "args": ["--api-key=sk-live-123456"] })) }),
+            )
+            .layer(middleware::map_response_with_state(
+                Some(store),
+                redact_response,
+            ));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/config")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let config: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(config, json!({ "args": ["--api-key=secret://api_key"] }));
+    }
+}
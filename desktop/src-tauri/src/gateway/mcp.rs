@@ -1,29 +1,54 @@
-use crate::models::mcp_server::Model as MCPServerModel;
+use crate::models::mcp_server::{
+    sandbox::{self, CachedList},
+    Model as MCPServerModel,
+};
+use crate::utils::names;
 use rmcp::{
-    handler::server::{router::tool::ToolRouter, tool::Parameters},
+    handler::server::{
+        router::tool::ToolRouter,
+        tool::{Parameters, ToolCallContext},
+    },
     model::{
-        CallToolResult, Content, GetPromptRequestParam, GetPromptResult, ListPromptsResult,
-        ListResourcesResult, PaginatedRequestParam, Prompt, PromptArgument, PromptMessage,
-        PromptMessageContent, PromptMessageRole, ProtocolVersion, RawResource,
-        ReadResourceRequestParam, ReadResourceResult, Resource, ResourceContents,
-        ServerCapabilities, ServerInfo,
+        CallToolRequestParam, CallToolResult, Content, GetPromptRequestParam, GetPromptResult,
+        ListPromptsResult, ListResourcesResult, ListToolsResult, PaginatedRequestParam, Prompt,
+        PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole, ProtocolVersion,
+        RawResource, ReadResourceRequestParam, ReadResourceResult, Resource, ResourceContents,
+        ServerCapabilities, ServerInfo, Tool,
     },
     schemars,
-    service::RequestContext,
-    tool, tool_handler, tool_router,
+    service::{NotificationContext, RequestContext},
+    tool, tool_router,
     transport::streamable_http_server::{
         session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService,
     },
     ErrorData as MCPError, RoleServer, ServerHandler,
 };
 use sea_orm::DatabaseConnection;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast::error::RecvError, Mutex};
 use uuid::Uuid;
 
+/// Separates a server's name from the names of its tools and prompts, as in
+/// `github__create_issue`
+const NAMESPACE_SEPARATOR: &str = "__";
+/// Longest tool name clients accept
+const MAX_NAME_LENGTH: usize = 64;
+const EXAMPLE_PROMPT: &str = "example_prompt";
+/// How long a lazy server whose start for listing failed is left alone before it's
+/// started again
+const LISTING_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+lazy_static::lazy_static! {
+    /// Lazily activated servers started in the background to get their lists, by when
+    /// that was last tried
+    static ref LISTING_STARTS: std::sync::Mutex<HashMap<String, Instant>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ArchestraContext {
     pub user_id: String,
@@ -184,27 +209,331 @@ impl Service {
     }
 }
 
-#[tool_handler]
+/// A server's name as it prefixes the names of its tools and prompts, limited to the
+/// characters clients accept in tool names
+fn namespace(server_name: &str) -> String {
+    names::sanitize(server_name)
+}
+
+/// A server's tool or prompt name prefixed with the server's name. Names that would be
+/// too long for clients are cut, with a hash of the full name keeping them apart.
+fn namespaced(server_name: &str, name: &str) -> String {
+    let full_name = format!("{}{NAMESPACE_SEPARATOR}{name}", namespace(server_name));
+    if full_name.len() <= MAX_NAME_LENGTH {
+        return full_name;
+    }
+
+    let hash = names::short_hash(&full_name);
+    let mut end = MAX_NAME_LENGTH - hash.len() - 1;
+    while !full_name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}_{hash}", &full_name[..end])
+}
+
+/// Find the server behind a namespaced name, and the name the server itself knows it by.
+/// If the namespace of one server starts with that of another, the longer one wins.
+/// Only works for names that weren't cut, it's for servers whose lists aren't known yet.
+fn resolve_namespaced<'a>(server_names: &'a [String], name: &str) -> Option<(&'a str, String)> {
+    server_names
+        .iter()
+        .filter_map(|server_name| {
+            let prefix = format!("{}{NAMESPACE_SEPARATOR}", namespace(server_name));
+            let original = name.strip_prefix(&prefix)?;
+            Some((prefix.len(), server_name.as_str(), original.to_string()))
+        })
+        .max_by_key(|(prefix_len, _, _)| *prefix_len)
+        .map(|(_, server_name, original)| (server_name, original))
+}
+
+/// Servers the manager knows of, whose lists are served here, sorted by name. Lazily
+/// activated servers that never ran have no lists yet, with `start_unlisted` they are
+/// started in the background to get them.
+async fn served_servers(start_unlisted: bool) -> Vec<String> {
+    let mut server_names: Vec<String> = sandbox::get_mcp_server_statuses()
+        .await
+        .into_keys()
+        .collect();
+    server_names.sort();
+
+    if start_unlisted {
+        for server_name in &server_names {
+            if sandbox::get_mcp_server_tools(server_name).await.is_none() {
+                start_for_listing(server_name);
+            }
+        }
+    }
+    server_names
+}
+
+/// Start a lazy server in the background to get its lists, unless that's already
+/// underway or failed a short while ago. Clients are sent `list_changed` once the lists
+/// are cached, so listing never waits for a server to start.
+fn start_for_listing(server_name: &str) {
+    {
+        let mut starts = LISTING_STARTS.lock().unwrap();
+        if starts
+            .get(server_name)
+            .is_some_and(|tried| tried.elapsed() < LISTING_RETRY_INTERVAL)
+        {
+            return;
+        }
+        starts.insert(server_name.to_string(), Instant::now());
+    }
+
+    let server_name = server_name.to_string();
+    tokio::spawn(async move {
+        println!("⏰ MCP [{server_name}] Starting to list it");
+        match sandbox::start_lazy_mcp_server(&server_name).await {
+            Ok(()) => {
+                LISTING_STARTS.lock().unwrap().remove(&server_name);
+            }
+            Err(e) => {
+                eprintln!("❌ MCP [{server_name}] Failed to start for listing: {e}");
+                // Starting can take minutes, wait from when it failed
+                LISTING_STARTS
+                    .lock()
+                    .unwrap()
+                    .insert(server_name, Instant::now());
+            }
+        }
+    });
+}
+
+/// A server's tool, resource or prompt with the name (or URI) it is served by here
+struct Served<T> {
+    name: String,
+    server_name: String,
+    item: T,
+}
+
+/// Put the servers' lists together, given in the order of the servers' names. A name
+/// that is already taken stays with whoever came first, the item of the later server
+/// is left out and reported.
+fn merge_server_lists<T>(
+    kind: &str,
+    lists: Vec<(String, Vec<T>)>,
+    mut taken: HashSet<String>,
+    served_name: impl Fn(&str, &T) -> String,
+) -> Vec<Served<T>> {
+    let mut served = Vec::new();
+    for (server_name, items) in lists {
+        for item in items {
+            let name = served_name(&server_name, &item);
+            if !taken.insert(name.clone()) {
+                eprintln!(
+                    "⚠️ MCP [{server_name}] Leaving out {kind} '{name}', it's already served for another server"
+                );
+                continue;
+            }
+            served.push(Served {
+                name,
+                server_name: server_name.clone(),
+                item,
+            });
+        }
+    }
+    served
+}
+
+/// Send a request to a server through the manager, which starts the server if it's
+/// activated lazily, and return the server's result
+async fn forward_request<P: Serialize, R: DeserializeOwned>(
+    server_name: &str,
+    method: &str,
+    params: P,
+) -> Result<R, MCPError> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": Uuid::new_v4().to_string(),
+        "method": method,
+        "params": params,
+    });
+    let response = sandbox::forward_raw_request(server_name, None, request.to_string(), None)
+        .await
+        .map_err(|e| MCPError::internal_error(format!("MCP server '{server_name}': {e}"), None))?;
+    let mut response: serde_json::Value = serde_json::from_str(&response).map_err(|e| {
+        MCPError::internal_error(
+            format!("Invalid response from MCP server '{server_name}': {e}"),
+            None,
+        )
+    })?;
+
+    if let Some(error) = response.get_mut("error").map(serde_json::Value::take) {
+        return Err(serde_json::from_value(error.clone())
+            .unwrap_or_else(|_| MCPError::internal_error(error.to_string(), None)));
+    }
+    serde_json::from_value(response["result"].take()).map_err(|e| {
+        MCPError::internal_error(
+            format!("Invalid {method} result from MCP server '{server_name}': {e}"),
+            None,
+        )
+    })
+}
+
+impl Service {
+    /// The servers' tools under the names they are served by here
+    async fn server_tools(&self, start_unlisted: bool) -> Vec<Served<Tool>> {
+        let mut lists = Vec::new();
+        for server_name in served_servers(start_unlisted).await {
+            let tools = sandbox::get_mcp_server_tools(&server_name)
+                .await
+                .unwrap_or_default();
+            lists.push((server_name, tools));
+        }
+        let own_names = self
+            .tool_router
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        merge_server_lists("tool", lists, own_names, |server_name, tool| {
+            namespaced(server_name, &tool.name)
+        })
+    }
+
+    /// The servers' resources, which keep their URIs
+    async fn server_resources(&self, start_unlisted: bool) -> Vec<Served<Resource>> {
+        let mut lists = Vec::new();
+        for server_name in served_servers(start_unlisted).await {
+            let resources = sandbox::get_mcp_server_resources(&server_name)
+                .await
+                .unwrap_or_default();
+            lists.push((server_name, resources));
+        }
+        let own_uris = self
+            .resources
+            .lock()
+            .await
+            .keys()
+            .map(|id| format!("archestra://{id}"))
+            .collect();
+        merge_server_lists("resource", lists, own_uris, |_, resource| {
+            resource.raw.uri.clone()
+        })
+    }
+
+    /// The servers' prompts under the names they are served by here
+    async fn server_prompts(&self, start_unlisted: bool) -> Vec<Served<Prompt>> {
+        let mut lists = Vec::new();
+        for server_name in served_servers(start_unlisted).await {
+            let prompts = sandbox::get_mcp_server_prompts(&server_name)
+                .await
+                .unwrap_or_default();
+            lists.push((server_name, prompts));
+        }
+        let own_names = HashSet::from([EXAMPLE_PROMPT.to_string()]);
+        merge_server_lists("prompt", lists, own_names, |server_name, prompt| {
+            namespaced(server_name, &prompt.name)
+        })
+    }
+
+    /// Call a server's tool by the name it is served by here
+    async fn call_server_tool(
+        &self,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, MCPError> {
+        let served = self.server_tools(false).await;
+        let route = match served.into_iter().find(|tool| tool.name == request.name) {
+            Some(tool) => Some((tool.server_name, tool.item.name.to_string())),
+            // The tools of lazy servers that never ran aren't known yet
+            None => resolve_namespaced(&served_servers(false).await, &request.name)
+                .map(|(server_name, tool_name)| (server_name.to_string(), tool_name)),
+        };
+        let Some((server_name, tool_name)) = route else {
+            return Err(MCPError::invalid_params(
+                format!("Tool not found: {}", request.name),
+                None,
+            ));
+        };
+        let request = CallToolRequestParam {
+            name: tool_name.into(),
+            arguments: request.arguments,
+        };
+        forward_request(&server_name, "tools/call", request).await
+    }
+}
+
+/// Besides its own tools, resources and prompts, the service serves those of every
+/// server the manager knows of, with the names of tools and prompts prefixed by the
+/// server's name
 impl ServerHandler for Service {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2025_03_26,
             capabilities: ServerCapabilities::builder()
-                .enable_tools()
+                .enable_prompts()
                 .enable_resources()
+                .enable_tools()
+                .enable_prompts_list_changed()
+                .enable_resources_list_changed()
+                .enable_tool_list_changed()
                 .build(),
             instructions: None,
             ..Default::default()
         }
     }
 
+    /// Pass the changes of the servers' lists on to the client, until its session ends
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        let peer = context.peer;
+        let mut changes = sandbox::subscribe_to_list_changes();
+        tokio::spawn(async move {
+            loop {
+                let lists = match changes.recv().await {
+                    Ok(list) => vec![list],
+                    // Missed some, so any list may have changed
+                    Err(RecvError::Lagged(_)) => CachedList::ALL.to_vec(),
+                    Err(RecvError::Closed) => return,
+                };
+                for list in lists {
+                    let sent = match list {
+                        CachedList::Tools => peer.notify_tool_list_changed().await,
+                        CachedList::Resources => peer.notify_resource_list_changed().await,
+                        CachedList::Prompts => peer.notify_prompt_list_changed().await,
+                    };
+                    if sent.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, MCPError> {
+        let mut tools = self.tool_router.list_all();
+        tools.extend(self.server_tools(true).await.into_iter().map(|served| {
+            let mut tool = served.item;
+            tool.name = served.name.into();
+            tool
+        }));
+        Ok(ListToolsResult::with_all_items(tools))
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, MCPError> {
+        if self.tool_router.has_route(&request.name) {
+            let tool_call_context = ToolCallContext::new(self, request, context);
+            return self.tool_router.call(tool_call_context).await;
+        }
+
+        self.call_server_tool(request).await
+    }
+
     async fn list_resources(
         &self,
         _request: Option<PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListResourcesResult, MCPError> {
         let resources = self.resources.lock().await;
-        let resource_list: Vec<Resource> = resources
+        let mut resource_list: Vec<Resource> = resources
             .values()
             .map(|r| Resource {
                 raw: RawResource {
@@ -217,6 +546,14 @@ impl ServerHandler for Service {
                 annotations: None,
             })
             .collect();
+        drop(resources);
+
+        // Resources keep their URIs, only their names get the server's prefix
+        resource_list.extend(self.server_resources(true).await.into_iter().map(|served| {
+            let mut resource = served.item;
+            resource.raw.name = namespaced(&served.server_name, &resource.raw.name);
+            resource
+        }));
 
         Ok(ListResourcesResult {
             resources: resource_list,
@@ -246,7 +583,20 @@ impl ServerHandler for Service {
                 ))
             }
         } else {
-            Err(MCPError::invalid_params("Invalid resource URI", None))
+            // From the server the resource is listed for, the same one listing picks
+            let served = self.server_resources(false).await;
+            match served
+                .into_iter()
+                .find(|resource| resource.name == request.uri)
+            {
+                Some(resource) => {
+                    forward_request(&resource.server_name, "resources/read", request).await
+                }
+                None => Err(MCPError::invalid_params(
+                    format!("Resource not found: {}", request.uri),
+                    None,
+                )),
+            }
         }
     }
 
@@ -255,17 +605,24 @@ impl ServerHandler for Service {
         _request: Option<PaginatedRequestParam>,
         _: RequestContext<RoleServer>,
     ) -> Result<ListPromptsResult, MCPError> {
+        let mut prompts = vec![Prompt::new(
+            EXAMPLE_PROMPT,
+            Some("This is an example prompt that takes one required argument, message"),
+            Some(vec![PromptArgument {
+                name: "message".to_string(),
+                description: Some("A message to put in the prompt".to_string()),
+                required: Some(true),
+            }]),
+        )];
+        prompts.extend(self.server_prompts(true).await.into_iter().map(|served| {
+            let mut prompt = served.item;
+            prompt.name = served.name;
+            prompt
+        }));
+
         Ok(ListPromptsResult {
             next_cursor: None,
-            prompts: vec![Prompt::new(
-                "example_prompt",
-                Some("This is an example prompt that takes one required argument, message"),
-                Some(vec![PromptArgument {
-                    name: "message".to_string(),
-                    description: Some("A message to put in the prompt".to_string()),
-                    required: Some(true),
-                }]),
-            )],
+            prompts,
         })
     }
 
@@ -275,7 +632,7 @@ impl ServerHandler for Service {
         _: RequestContext<RoleServer>,
     ) -> Result<GetPromptResult, MCPError> {
         match name.as_str() {
-            "example_prompt" => {
+            EXAMPLE_PROMPT => {
                 let message = arguments
                     .and_then(|json| json.get("message")?.as_str().map(|s| s.to_string()))
                     .ok_or_else(|| {
@@ -292,7 +649,23 @@ impl ServerHandler for Service {
                     }],
                 })
             }
-            _ => Err(MCPError::invalid_params("prompt not found", None)),
+            _ => {
+                let served = self.server_prompts(false).await;
+                let route = match served.into_iter().find(|prompt| prompt.name == name) {
+                    Some(prompt) => Some((prompt.server_name, prompt.item.name)),
+                    // The prompts of lazy servers that never ran aren't known yet
+                    None => resolve_namespaced(&served_servers(false).await, &name)
+                        .map(|(server_name, prompt_name)| (server_name.to_string(), prompt_name)),
+                };
+                let Some((server_name, prompt_name)) = route else {
+                    return Err(MCPError::invalid_params("prompt not found", None));
+                };
+                let request = GetPromptRequestParam {
+                    name: prompt_name,
+                    arguments,
+                };
+                forward_request(&server_name, "prompts/get", request).await
+            }
         }
     }
 }
@@ -306,7 +679,7 @@ pub async fn create_streamable_http_service(
         move || Ok(Service::new(user_id.clone(), (*db_for_closure).clone())),
         Arc::new(LocalSessionManager::default()),
         StreamableHttpServerConfig {
-            sse_keep_alive: Some(std::time::Duration::from_secs(30)),
+            sse_keep_alive: Some(Duration::from_secs(30)),
             stateful_mode: true, // Enable stateful mode for session management
         },
     )
@@ -315,6 +688,7 @@ pub async fn create_streamable_http_service(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::mcp_server::{Activation, MCPServerDefinition, ServerConfig};
     use crate::test_fixtures::database;
     use rstest::*;
 
@@ -337,7 +711,162 @@ mod tests {
         assert!(info.capabilities.tools.is_some());
         assert!(info.capabilities.resources.is_some());
         assert!(info.capabilities.logging.is_none());
-        assert!(info.capabilities.prompts.is_none());
+        // The servers' lists change as servers are installed and removed
+        let capabilities = info.capabilities;
+        assert_eq!(capabilities.tools.unwrap().list_changed, Some(true));
+        assert_eq!(capabilities.resources.unwrap().list_changed, Some(true));
+        assert_eq!(capabilities.prompts.unwrap().list_changed, Some(true));
+    }
+
+    #[test]
+    fn test_namespaced_names() {
+        assert_eq!(namespaced("github", "create_issue"), "github__create_issue");
+        assert_eq!(namespaced("My Server", "search"), "My_Server__search");
+
+        let server_names = vec![
+            "github".to_string(),
+            "github__enterprise".to_string(),
+            "My Server".to_string(),
+        ];
+        assert_eq!(
+            resolve_namespaced(&server_names, "github__create_issue"),
+            Some(("github", "create_issue".to_string()))
+        );
+        assert_eq!(
+            resolve_namespaced(&server_names, "github__enterprise__create_issue"),
+            Some(("github__enterprise", "create_issue".to_string()))
+        );
+        assert_eq!(
+            resolve_namespaced(&server_names, "My_Server__search"),
+            Some(("My Server", "search".to_string()))
+        );
+        assert_eq!(resolve_namespaced(&server_names, "slack__post"), None);
+    }
+
+    #[test]
+    fn test_long_namespaced_names_are_cut() {
+        let server_name = "a-server-with-quite-a-long-name";
+        let first = namespaced(server_name, "create_or_update_file_in_the_repository");
+        let second = namespaced(server_name, "create_or_update_file_in_the_repository_fork");
+        assert!(first.len() <= MAX_NAME_LENGTH, "{first}");
+        assert!(second.len() <= MAX_NAME_LENGTH, "{second}");
+        assert!(first.starts_with("a-server-with-quite-a-long-name__create_or"));
+        assert_ne!(first, second);
+        assert_eq!(
+            first,
+            namespaced(server_name, "create_or_update_file_in_the_repository")
+        );
+
+        // Cut where a character starts
+        let cut = namespaced(server_name, &"é".repeat(40));
+        assert!(cut.len() <= MAX_NAME_LENGTH, "{cut}");
+    }
+
+    #[test]
+    fn test_first_server_keeps_a_name_that_is_taken() {
+        let lists = vec![
+            ("My Server".to_string(), vec!["search", "get_context"]),
+            ("My_Server".to_string(), vec!["search", "fetch"]),
+        ];
+        let taken = HashSet::from(["My_Server__get_context".to_string()]);
+        let served = merge_server_lists("tool", lists, taken, |server_name, tool| {
+            namespaced(server_name, tool)
+        });
+        let served: Vec<_> = served
+            .iter()
+            .map(|served| {
+                (
+                    served.name.as_str(),
+                    served.server_name.as_str(),
+                    served.item,
+                )
+            })
+            .collect();
+        assert_eq!(
+            served,
+            [
+                ("My_Server__search", "My Server", "search"),
+                ("My_Server__fetch", "My_Server", "fetch"),
+            ]
+        );
+    }
+
+    /// Answer like a remote MCP server with an `echo` tool
+    async fn fake_remote_post(body: String) -> axum::response::Response {
+        use axum::response::IntoResponse;
+
+        let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let result = match request["method"].as_str() {
+            Some("initialize") => serde_json::json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "fake", "version": "1.0.0" }
+            }),
+            Some("tools/list") => serde_json::json!({
+                "tools": [{ "name": "echo", "inputSchema": { "type": "object" } }]
+            }),
+            Some("tools/call") => serde_json::json!({
+                "content": [{ "type": "text", "text": request["params"]["arguments"]["text"] }]
+            }),
+            Some("ping") => serde_json::json!({}),
+            _ => return axum::http::StatusCode::ACCEPTED.into_response(),
+        };
+        axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            .into_response()
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_lazy_server_is_listed_and_callable(
+        #[future] service_and_db: (Service, DatabaseConnection),
+    ) {
+        let (service, _) = service_and_db.await;
+        let fake = axum::Router::new().route("/mcp", axum::routing::post(fake_remote_post));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, fake).await.unwrap() });
+
+        sandbox::activate_mcp_server(&MCPServerDefinition {
+            name: "lazy-echo".to_string(),
+            server_config: ServerConfig {
+                transport: "http".to_string(),
+                args: vec![url],
+                activation: Activation::Lazy,
+                ..Default::default()
+            },
+            meta: None,
+        })
+        .await
+        .unwrap();
+        assert!(sandbox::get_mcp_server_tools("lazy-echo").await.is_none());
+
+        // Listing starts it in the background, its tools are served once they are known
+        let listed = async {
+            loop {
+                let tools = service.server_tools(true).await;
+                if tools.iter().any(|tool| tool.name == "lazy-echo__echo") {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), listed)
+            .await
+            .expect("the lazy server's tools were never listed");
+
+        let result = service
+            .call_server_tool(CallToolRequestParam {
+                name: "lazy-echo__echo".into(),
+                arguments: serde_json::json!({ "text": "hello" }).as_object().cloned(),
+            })
+            .await
+            .unwrap();
+        match &result.content[0].raw {
+            rmcp::model::RawContent::Text(text) => assert_eq!(text.text, "hello"),
+            _ => panic!("Expected text content"),
+        }
+
+        sandbox::stop_mcp_server("lazy-echo").await.unwrap();
     }
 
     #[rstest]
@@ -1,17 +1,27 @@
 use crate::models::mcp_request_log::ClientInfo;
-use crate::models::mcp_request_log::{CreateLogRequest, Model as MCPRequestLog};
-use crate::models::mcp_server::sandbox::forward_raw_request;
+use crate::models::mcp_request_log::{
+    CreateLogRequest, Model as MCPRequestLog, CANCELLED_STATUS_CODE,
+};
+use crate::models::mcp_server::sandbox::{
+    close_session, forward_raw_request, open_session_stream, REQUEST_CANCELLED,
+};
 use axum::{
     body::Body,
     extract::{Path, State},
-    http::{HeaderMap, Request, Response},
-    response::IntoResponse,
+    http::{HeaderMap, Request, Response, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     routing::{post, Router},
 };
+use futures_util::future::join_all;
+use futures_util::stream::Stream;
 use sea_orm::DatabaseConnection;
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 pub struct Service {
@@ -60,6 +70,22 @@ impl Service {
         (session_id, mcp_session_id)
     }
 
+    // Extract the time limit a client asks for, in milliseconds
+    fn extract_timeout(headers: &HeaderMap) -> Option<Duration> {
+        let value = headers.get("x-request-timeout-ms")?;
+        match value
+            .to_str()
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+        {
+            Some(ms) => Some(Duration::from_millis(ms)),
+            None => {
+                println!("⚠️ Ignoring invalid x-request-timeout-ms header: {value:?}");
+                None
+            }
+        }
+    }
+
     // Convert HeaderMap to HashMap for JSON serialization
     fn headers_to_hashmap(headers: &HeaderMap) -> HashMap<String, String> {
         headers
@@ -94,6 +120,7 @@ impl Service {
         let headers = req.headers().clone();
         let (session_id, mcp_session_id) = Self::extract_session_ids(&headers);
         let client_info = Self::extract_client_info(&headers);
+        let timeout = Self::extract_timeout(&headers);
         let request_headers = Self::headers_to_hashmap(&headers);
 
         // Generate session_id if not provided
@@ -123,6 +150,7 @@ impl Service {
                     status_code: 400,
                     error_message: Some(format!("Failed to read request body: {e}")),
                     duration_ms: Some(start_time.elapsed().as_millis() as i32),
+                    batch_id: None,
                 };
 
                 // Log asynchronously (don't block on database errors)
@@ -165,6 +193,7 @@ impl Service {
                     status_code: 400,
                     error_message: Some(format!("Invalid UTF-8 in request body: {e}")),
                     duration_ms: Some(start_time.elapsed().as_millis() as i32),
+                    batch_id: None,
                 };
 
                 // Log asynchronously
@@ -183,103 +212,307 @@ impl Service {
             }
         };
 
+        // The elements of a batch are forwarded one by one
+        if let Ok(serde_json::Value::Array(elements)) = serde_json::from_str(&request_body) {
+            let context = RequestContext {
+                session_id,
+                mcp_session_id,
+                client_info,
+                request_headers,
+                timeout,
+                start_time,
+            };
+            return self.call_batch(&server_name, &context, elements).await;
+        }
+
         // Extract method from request body
         let method = Self::extract_method_from_request(&request_body);
 
+        // Hand out an MCP session ID on initialize so that the client's requests can be
+        // told apart from other clients sharing the same server
+        let mcp_session_id = mcp_session_id.or_else(|| {
+            (method.as_deref() == Some("initialize")).then(|| Uuid::new_v4().to_string())
+        });
+
+        let context = RequestContext {
+            session_id,
+            mcp_session_id,
+            client_info,
+            request_headers,
+            timeout,
+            start_time,
+        };
+        let (status, body) = self
+            .forward_and_log(
+                &server_name,
+                &context,
+                request_id,
+                request_body,
+                None,
+                serde_json::Value::Null,
+            )
+            .await;
+
+        let mut response = axum::http::Response::builder().status(status);
+        for (key, value) in &Self::response_headers(&context, status) {
+            response = response.header(key, value);
+        }
+        response.body(Body::from(body)).unwrap()
+    }
+
+    /// Forward the elements of a JSON-RPC batch side by side, each logged on its own under
+    /// a shared batch id, and answer with an array of the responses that came back, along
+    /// with an error for each element that isn't a JSON-RPC message
+    async fn call_batch(
+        &self,
+        server_name: &str,
+        context: &RequestContext,
+        elements: Vec<serde_json::Value>,
+    ) -> Response<Body> {
+        let invalid = |message: &str| {
+            axum::http::Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("Content-Type", "application/json")
+                .body(Body::from(invalid_request(message).to_string()))
+                .unwrap()
+        };
+        if elements.is_empty() {
+            return invalid("empty batch");
+        }
+        // The session it starts would have no Mcp-Session-Id to go with the batch's answer
+        if elements
+            .iter()
+            .any(|element| element.get("method").and_then(|m| m.as_str()) == Some("initialize"))
+        {
+            return invalid("initialize must not be part of a batch");
+        }
+
+        let batch_id = Uuid::new_v4().to_string();
+        println!(
+            "📦 MCP Proxy: Forwarding batch of {} messages to '{server_name}' (batch: {batch_id})",
+            elements.len()
+        );
+
+        let responses = join_all(elements.into_iter().map(|element| {
+            let batch_id = batch_id.clone();
+            async move {
+                // Answered right here instead of being forwarded
+                if !is_message(&element) {
+                    println!("❌ MCP Proxy: Invalid batch element {element}");
+                    return Some(invalid_request("not a JSON-RPC message"));
+                }
+
+                // Only requests get a response, not even a failed notification does
+                let expects_response =
+                    element.get("method").is_some() && element.get("id").is_some();
+                let error_id = element.get("id").cloned().unwrap_or_default();
+                let (_, body) = self
+                    .forward_and_log(
+                        server_name,
+                        context,
+                        Uuid::new_v4().to_string(),
+                        element.to_string(),
+                        Some(batch_id),
+                        error_id,
+                    )
+                    .await;
+                expects_response
+                    .then(|| serde_json::from_str(&body).ok())
+                    .flatten()
+            }
+        }))
+        .await;
+        let responses: Vec<serde_json::Value> = responses.into_iter().flatten().collect();
+        // A batch of notifications and responses only is just accepted
+        let (status, body) = if responses.is_empty() {
+            (StatusCode::ACCEPTED, String::new())
+        } else {
+            (StatusCode::OK, serde_json::to_string(&responses).unwrap())
+        };
+
+        let mut response = axum::http::Response::builder().status(status);
+        for (key, value) in &Self::response_headers(context, status) {
+            response = response.header(key, value);
+        }
+        response.body(Body::from(body)).unwrap()
+    }
+
+    /// Forward a single JSON-RPC message and log it, returns the status and body to answer
+    /// it with. Failures are answered with a JSON-RPC error carrying `error_id`.
+    async fn forward_and_log(
+        &self,
+        server_name: &str,
+        context: &RequestContext,
+        request_id: String,
+        request_body: String,
+        batch_id: Option<String>,
+        error_id: serde_json::Value,
+    ) -> (StatusCode, String) {
+        // Until the server answers, the request counts as cancelled, as the handler
+        // is dropped right here when the client disconnects
+        let pending_log = PendingLog {
+            db: Arc::clone(&self.db),
+            start_time: context.start_time,
+            log_data: Some(CreateLogRequest {
+                request_id,
+                session_id: Some(context.session_id.clone()),
+                mcp_session_id: context.mcp_session_id.clone(),
+                server_name: server_name.to_string(),
+                client_info: Some(context.client_info.clone()),
+                method: Self::extract_method_from_request(&request_body),
+                request_headers: Some(context.request_headers.clone()),
+                request_body: Some(request_body.clone()),
+                response_body: None,
+                response_headers: None,
+                status_code: CANCELLED_STATUS_CODE,
+                error_message: Some("Client disconnected".to_string()),
+                duration_ms: None,
+                batch_id,
+            }),
+        };
+
         println!("🔄 Forwarding request to forward_raw_request function...");
         // Forward the raw JSON-RPC request to the MCPServerManager
-        match forward_raw_request(&server_name, request_body.clone()).await {
+        let result = forward_raw_request(
+            server_name,
+            context.mcp_session_id.as_deref(),
+            request_body,
+            context.timeout,
+        )
+        .await;
+        let mut log_data = pending_log.finish();
+
+        let (status, body) = match result {
             Ok(raw_response) => {
                 println!("✅ Successfully received response from server '{server_name}'");
                 println!("📤 Response: {raw_response}");
-
-                let duration_ms = start_time.elapsed().as_millis() as i32;
-
-                // Log successful request
-                let mut response_headers = HashMap::new();
-                response_headers.insert("Content-Type".to_string(), "application/json".to_string());
-
-                let log_data = CreateLogRequest {
-                    request_id,
-                    session_id: Some(session_id),
-                    mcp_session_id,
-                    server_name,
-                    client_info: Some(client_info),
-                    method,
-                    request_headers: Some(request_headers),
-                    request_body: Some(request_body),
-                    response_body: Some(raw_response.clone()),
-                    response_headers: Some(response_headers),
-                    status_code: 200,
-                    error_message: None,
-                    duration_ms: Some(duration_ms),
+                log_data.error_message = None;
+                // Notifications and answers to the server's requests have no response
+                let status = if raw_response.is_empty() {
+                    StatusCode::ACCEPTED
+                } else {
+                    StatusCode::OK
                 };
-
-                // Log asynchronously
-                let db_clone = Arc::clone(&self.db);
-                tokio::spawn(async move {
-                    if let Err(e) = MCPRequestLog::create_request_log(&db_clone, log_data).await {
-                        eprintln!("Failed to log request: {e}");
-                    }
-                });
-
-                axum::http::Response::builder()
-                    .status(axum::http::StatusCode::OK)
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(raw_response))
-                    .unwrap()
+                (status, raw_response)
             }
             Err(e) => {
                 println!("❌ MCP Proxy: Failed to forward request to '{server_name}': {e}");
 
-                let duration_ms = start_time.elapsed().as_millis() as i32;
-
                 // Return a JSON-RPC error response
                 let error_response = serde_json::json!({
                     "jsonrpc": "2.0",
-                    "id": null,
+                    "id": error_id,
                     "error": {
                         "code": -32603,
                         "message": format!("MCP Proxy error: {}", e)
                     }
                 });
 
-                let error_response_str = serde_json::to_string(&error_response).unwrap();
+                // A request the client cancelled itself isn't a failure
+                let status = if e == REQUEST_CANCELLED {
+                    StatusCode::from_u16(CANCELLED_STATUS_CODE as u16).unwrap()
+                } else {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                };
+                log_data.error_message = Some(e);
+                (status, serde_json::to_string(&error_response).unwrap())
+            }
+        };
 
-                // Log failed request
-                let mut response_headers = HashMap::new();
-                response_headers.insert("Content-Type".to_string(), "application/json".to_string());
+        log_data.response_body = Some(body.clone());
+        log_data.response_headers = Some(Self::response_headers(context, status));
+        log_data.status_code = status.as_u16() as i32;
+        self.log_request(log_data);
 
-                let log_data = CreateLogRequest {
-                    request_id,
-                    session_id: Some(session_id),
-                    mcp_session_id,
-                    server_name,
-                    client_info: Some(client_info),
-                    method,
-                    request_headers: Some(request_headers),
-                    request_body: Some(request_body),
-                    response_body: Some(error_response_str.clone()),
-                    response_headers: Some(response_headers),
-                    status_code: 500,
-                    error_message: Some(e),
-                    duration_ms: Some(duration_ms),
-                };
+        (status, body)
+    }
 
-                // Log asynchronously
-                let db_clone = Arc::clone(&self.db);
-                tokio::spawn(async move {
-                    if let Err(e) = MCPRequestLog::create_request_log(&db_clone, log_data).await {
-                        eprintln!("Failed to log request: {e}");
-                    }
-                });
+    // Headers of the response to a forwarded request
+    fn response_headers(context: &RequestContext, status: StatusCode) -> HashMap<String, String> {
+        let mut response_headers = HashMap::new();
+        response_headers.insert("Content-Type".to_string(), "application/json".to_string());
+        if let (true, Some(mcp_session_id)) = (status.is_success(), &context.mcp_session_id) {
+            response_headers.insert("Mcp-Session-Id".to_string(), mcp_session_id.clone());
+        }
+        response_headers
+    }
 
-                axum::http::Response::builder()
-                    .status(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(error_response_str))
-                    .unwrap()
+    // Log asynchronously (don't block on database errors)
+    fn log_request(&self, log_data: CreateLogRequest) {
+        let db_clone = Arc::clone(&self.db);
+        tokio::spawn(async move {
+            if let Err(e) = MCPRequestLog::create_request_log(&db_clone, log_data).await {
+                eprintln!("Failed to log request: {e}");
+            }
+        });
+    }
+}
+
+/// What the messages of one HTTP request to the proxy have in common
+struct RequestContext {
+    session_id: String,
+    mcp_session_id: Option<String>,
+    client_info: ClientInfo,
+    request_headers: HashMap<String, String>,
+    timeout: Option<Duration>,
+    start_time: Instant,
+}
+
+/// The log entry of a forwarded request, written as cancelled if it is dropped
+/// before the request finished
+struct PendingLog {
+    db: Arc<DatabaseConnection>,
+    start_time: Instant,
+    log_data: Option<CreateLogRequest>,
+}
+
+impl PendingLog {
+    /// The request finished, take over the entry with its duration filled in
+    fn finish(mut self) -> CreateLogRequest {
+        let mut log_data = self.log_data.take().expect("log entry is only taken once");
+        log_data.duration_ms = Some(self.start_time.elapsed().as_millis() as i32);
+        log_data
+    }
+}
+
+impl Drop for PendingLog {
+    fn drop(&mut self) {
+        let Some(mut log_data) = self.log_data.take() else {
+            return;
+        };
+        println!(
+            "🚫 MCP Proxy: Client disconnected from request {}",
+            log_data.request_id
+        );
+        log_data.duration_ms = Some(self.start_time.elapsed().as_millis() as i32);
+        let db = Arc::clone(&self.db);
+        tokio::spawn(async move {
+            if let Err(e) = MCPRequestLog::create_request_log(&db, log_data).await {
+                eprintln!("Failed to log request: {e}");
             }
+        });
+    }
+}
+
+/// A JSON-RPC error for a message that isn't a valid request
+fn invalid_request(message: &str) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": -32600, "message": format!("Invalid Request: {message}") }
+    })
+}
+
+/// Whether a batch element is a request, a notification or a response
+fn is_message(element: &serde_json::Value) -> bool {
+    let Some(element) = element.as_object() else {
+        return false;
+    };
+    match element.get("method") {
+        Some(method) => method.is_string(),
+        None => {
+            element.contains_key("id")
+                && (element.contains_key("result") || element.contains_key("error"))
         }
     }
 }
@@ -292,15 +525,68 @@ async fn handler(
     service.call(server_name, request).await
 }
 
+/// Streams the notifications and requests a server sends on its own to a client session,
+/// the client posts its answers to those requests like any other message
+async fn stream_handler(
+    Path(server_name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let (_, mcp_session_id) = Service::extract_session_ids(&headers);
+    let Some(mcp_session_id) = mcp_session_id else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Missing Mcp-Session-Id header".to_string(),
+        ));
+    };
+
+    let rx = open_session_stream(&server_name, &mcp_session_id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    println!("📡 MCP Proxy: Session {mcp_session_id} listening to server '{server_name}'");
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        let message = rx.recv().await?;
+        Some((Ok(Event::default().event("message").data(message)), rx))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Ends a client session, the server's messages are no longer kept for it
+async fn close_session_handler(
+    Path(server_name): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let (_, mcp_session_id) = Service::extract_session_ids(&headers);
+    let Some(mcp_session_id) = mcp_session_id else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Missing Mcp-Session-Id header".to_string(),
+        ));
+    };
+
+    close_session(&server_name, &mcp_session_id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    println!("👋 MCP Proxy: Session {mcp_session_id} of server '{server_name}' closed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub fn create_router(db: DatabaseConnection) -> Router {
     Router::new()
-        .route("/{server_name}", post(handler))
+        .route(
+            "/{server_name}",
+            post(handler)
+                .get(stream_handler)
+                .delete(close_session_handler),
+        )
         .with_state(Arc::new(Service::new(db)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::mcp_server::{MCPServerDefinition, ServerConfig};
     use crate::test_fixtures::database;
     use axum::{
         body::Body,
@@ -313,6 +599,71 @@ mod tests {
         create_router(db)
     }
 
+    /// Answer like a remote MCP server without tools, accepting notifications
+    async fn fake_remote_post(body: String) -> axum::response::Response {
+        use axum::response::IntoResponse;
+
+        let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let result = match request["method"].as_str() {
+            Some("initialize") => serde_json::json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "fake", "version": "1.0.0" }
+            }),
+            Some("tools/list") => serde_json::json!({ "tools": [] }),
+            Some("ping") => serde_json::json!({}),
+            _ => return StatusCode::ACCEPTED.into_response(),
+        };
+        axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            .into_response()
+    }
+
+    /// Start a server in the global manager that is a fake remote server
+    async fn start_fake_server(name: &str) {
+        let fake = Router::new().route("/mcp", post(fake_remote_post));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, fake).await.unwrap() });
+
+        crate::models::mcp_server::sandbox::start_mcp_server(&MCPServerDefinition {
+            name: name.to_string(),
+            server_config: ServerConfig {
+                transport: "http".to_string(),
+                args: vec![url],
+                ..Default::default()
+            },
+            meta: None,
+        })
+        .await
+        .unwrap();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_notification_is_accepted(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        start_fake_server("accepting-server").await;
+
+        let response = app(db)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/accepting-server")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        r#"{"jsonrpc":"2.0","method":"notifications/roots/list_changed"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_extract_client_info(#[future] database: DatabaseConnection) {
@@ -349,6 +700,21 @@ mod tests {
         assert_eq!(mcp_session_id, Some("mcp-456".to_string()));
     }
 
+    #[test]
+    fn test_extract_timeout() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(Service::extract_timeout(&headers), None);
+
+        headers.insert("x-request-timeout-ms", "1500".parse().unwrap());
+        assert_eq!(
+            Service::extract_timeout(&headers),
+            Some(Duration::from_millis(1500))
+        );
+
+        headers.insert("x-request-timeout-ms", "soon".parse().unwrap());
+        assert_eq!(Service::extract_timeout(&headers), None);
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_headers_to_hashmap(#[future] database: DatabaseConnection) {
@@ -532,4 +898,314 @@ mod tests {
         assert_eq!(log.method, Some("tools/list".to_string()));
         assert_eq!(log.status_code, 500); // Failed since forward_raw_request isn't mocked
     }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_batch_elements_are_answered_and_logged_separately(
+        #[future] database: DatabaseConnection,
+    ) {
+        let db = database.await;
+        let app = app(db.clone());
+
+        let batch = r#"[
+            {"jsonrpc":"2.0","id":1,"method":"tools/list"},
+            {"jsonrpc":"2.0","method":"notifications/roots/list_changed"},
+            {"jsonrpc":"2.0","id":"b","method":"ping"}
+        ]"#;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/batch-server")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(batch))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Both requests fail as the server doesn't exist, the notification gets no answer
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let responses: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<&serde_json::Value> = responses.iter().map(|r| &r["id"]).collect();
+        assert_eq!(ids, [&serde_json::json!(1), &serde_json::json!("b")]);
+        assert!(responses.iter().all(|r| r["error"]["code"] == -32603));
+
+        // Give time for async logging to complete
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        use crate::models::mcp_request_log::{Column, Entity};
+        use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+        let logs = Entity::find()
+            .filter(Column::ServerName.eq("batch-server"))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 3);
+        assert!(logs[0].batch_id.is_some());
+        assert!(logs.iter().all(|log| log.batch_id == logs[0].batch_id));
+        assert_ne!(logs[0].request_id, logs[1].request_id);
+        let mut methods: Vec<_> = logs.iter().filter_map(|log| log.method.clone()).collect();
+        methods.sort();
+        assert_eq!(
+            methods,
+            ["notifications/roots/list_changed", "ping", "tools/list"]
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_empty_batch_is_rejected(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let app = app(db);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/batch-server")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from("[]"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_batch_without_requests_is_accepted(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        start_fake_server("accepting-batch-server").await;
+
+        let response = app(db)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/accepting-batch-server")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        r#"[{"jsonrpc":"2.0","method":"notifications/roots/list_changed"},
+                            {"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":7}}]"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_invalid_batch_elements_get_errors(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        start_fake_server("invalid-batch-server").await;
+        let post = |body: &'static str| {
+            app(db.clone()).oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/invalid-batch-server")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        let response = post(
+            r#"[1, {"id":1}, {"jsonrpc":"2.0","id":2,"method":"ping"},
+                {"jsonrpc":"2.0","method":"notifications/roots/list_changed"}]"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let body = body.as_array().unwrap();
+        assert_eq!(body.len(), 3);
+        for error in &body[..2] {
+            assert_eq!(error["id"], serde_json::Value::Null);
+            assert_eq!(error["error"]["code"], -32600);
+        }
+        assert_eq!(body[2]["id"], 2);
+        assert!(body[2].get("result").is_some());
+
+        // Nothing but invalid elements still gets an error for each of them
+        let response = post("[1, 2, 3]").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 3);
+        assert_eq!(body[0]["error"]["code"], -32600);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_initialize_in_batch_is_rejected(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        start_fake_server("initialize-batch-server").await;
+
+        let response = app(db)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/initialize-batch-server")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        r#"[{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}},
+                            {"jsonrpc":"2.0","id":2,"method":"ping"}]"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.headers().get("Mcp-Session-Id").is_none());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], -32600);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_abandoned_request_is_logged_as_cancelled(#[future] database: DatabaseConnection) {
+        let db = database.await;
+
+        // What's left of a handler dropped while the server was working on the request
+        drop(PendingLog {
+            db: Arc::new(db.clone()),
+            start_time: Instant::now(),
+            log_data: Some(CreateLogRequest {
+                request_id: "abandoned".to_string(),
+                session_id: None,
+                mcp_session_id: None,
+                server_name: "test-server".to_string(),
+                client_info: None,
+                method: Some("tools/call".to_string()),
+                request_headers: None,
+                request_body: None,
+                response_body: None,
+                response_headers: None,
+                status_code: CANCELLED_STATUS_CODE,
+                error_message: Some("Client disconnected".to_string()),
+                duration_ms: None,
+                batch_id: None,
+            }),
+        });
+
+        // Give time for async logging to complete
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        use crate::models::mcp_request_log::{Column, Entity};
+        use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+        let log = Entity::find()
+            .filter(Column::RequestId.eq("abandoned"))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.status_code, CANCELLED_STATUS_CODE);
+        assert!(log.duration_ms.is_some());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_stream_requires_mcp_session_id(#[future] database: DatabaseConnection) {
+        let db = database.await;
+
+        let response = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/test-server")
+                    .header("Accept", "text/event-stream")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app(db)
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/test-server")
+                    .header("Accept", "text/event-stream")
+                    .header("mcp-session-id", "mcp-456")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_close_session(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        start_fake_server("proxy-close-session").await;
+
+        let close = |session_id: Option<&str>| {
+            let mut request = Request::builder()
+                .method("DELETE")
+                .uri("/proxy-close-session");
+            if let Some(session_id) = session_id {
+                request = request.header("mcp-session-id", session_id);
+            }
+            app(db.clone()).oneshot(request.body(Body::empty()).unwrap())
+        };
+        assert_eq!(close(None).await.unwrap().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            close(Some("mcp-456")).await.unwrap().status(),
+            StatusCode::NO_CONTENT
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_initialize_assigns_mcp_session_id(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let service = Service::new(db.clone());
+
+        let request_body = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let req = Request::builder()
+            .method("POST")
+            .uri("/init-server")
+            .header("Content-Type", "application/json")
+            .body(Body::from(request_body))
+            .unwrap();
+
+        let _response = service.call("init-server".to_string(), req).await;
+
+        // Give time for async logging to complete
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        use crate::models::mcp_request_log::{Column, Entity};
+        use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+        let log = Entity::find()
+            .filter(Column::ServerName.eq("init-server"))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(log.mcp_session_id.is_some());
+    }
 }
@@ -29,7 +29,7 @@ pub async fn start_gateway(
     let listener = TcpListener::bind(addr).await?;
 
     println!("Gateway started successfully on http://{addr}");
-    println!("  - Archestra MCP endpoint (streamable HTTP): http://{addr}/mcp");
+    println!("  - Archestra MCP endpoint, serving all installed servers (streamable HTTP): http://{addr}/mcp");
     println!("  - Proxy endpoints: http://{addr}/mcp_proxy/<server_name>");
     println!("  - LLM endpoints: http://{addr}/llm/<provider>");
     println!("  - API endpoints: http://{addr}/api");
@@ -58,6 +58,20 @@ pub fn run() {
                     .map_err(|e| format!("Database error: {e}"))
             })?;
 
+            // Open the secret store before anything that resolves or redacts secrets
+            let app_handle = app.handle().clone();
+            if let Err(e) = tauri::async_runtime::block_on(models::secret::init_secret_store(
+                &app_handle,
+                db.clone(),
+            )) {
+                eprintln!("Failed to open secret store: {e}");
+            }
+
+            // Servers may be configured to run with a node of the user's choosing
+            if let Err(e) = utils::runtime::init_runtime_settings(app.handle()) {
+                eprintln!("Failed to load runtime settings: {e}");
+            }
+
             // Start all persisted MCP servers
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -142,7 +156,9 @@ pub fn run() {
                     eprintln!("Failed to shutdown Ollama: {e}");
                 }
 
-                // Note: MCP servers will be stopped automatically via kill_on_drop, no need to explicitly stop them here
+                // Give MCP servers a chance to exit cleanly, the manager is never dropped so
+                // kill_on_drop alone would leave them running
+                models::mcp_server::sandbox::shutdown_all_mcp_servers().await;
             });
 
             println!("Cleanup completed");
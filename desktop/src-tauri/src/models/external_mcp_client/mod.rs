@@ -1,4 +1,3 @@
-use crate::models::mcp_server::Model as MCPServer;
 use sea_orm::entity::prelude::*;
 use sea_orm::Set;
 use serde::{Deserialize, Serialize};
@@ -164,10 +163,8 @@ impl Model {
         }
     }
 
-    pub async fn update_external_mcp_client_config(
-        db: &DatabaseConnection,
-        client_name: &str,
-    ) -> Result<(), String> {
+    /// Point a client's config at the archestra.ai server
+    pub fn update_external_mcp_client_config(client_name: &str) -> Result<(), String> {
         let config_path = Self::get_config_path_for_external_mcp_client(client_name)?;
 
         println!("🔌 Connecting {client_name} client...");
@@ -198,50 +195,12 @@ impl Model {
             );
         }
 
-        // Now add each installed MCP server with archestra.ai suffix
-        let installed_mcp_servers = MCPServer::load_installed_mcp_servers(db)
-            .await
-            .map_err(|e| e.to_string())?;
-        println!("🔧 Available MCP servers: {installed_mcp_servers:?}");
-
-        println!(
-            "➕ Adding {} MCP servers to {} config",
-            installed_mcp_servers.len(),
-            client_name
-        );
-        for installed_mcp_server in &installed_mcp_servers {
-            let server_name = installed_mcp_server.name.clone();
-            let server_key = format!("{server_name} {INSTALLED_MCP_SERVER_KEY_SUFFIX}");
-            let server_config = MCPServerConfig {
-                url: format!("{ARCHESTRA_SERVER_BASE_URL}/proxy/{server_name}"),
-            };
-
-            if !external_client_mcp_servers_config.contains_key(&server_key) {
-                external_client_mcp_servers_config.insert(
-                    server_key.clone(),
-                    serde_json::to_value(server_config).unwrap(),
-                );
-            }
-            println!("  ✅ Added MCP server: {server_key}");
-        }
-
-        // remove all entries with that're suffixed with "(archestra.ai)" that aren't in the installed_mcp_servers list
-        let installed_names: std::collections::HashSet<_> = installed_mcp_servers
-            .iter()
-            .map(|s| s.name.as_str())
-            .collect();
+        // The archestra.ai server serves the tools of every installed server, so entries
+        // added for single servers by earlier versions are no longer needed
         let keys_to_remove: Vec<String> = external_client_mcp_servers_config
             .keys()
-            .filter_map(|key| {
-                if let Some(stripped) =
-                    key.strip_suffix(&format!(" {INSTALLED_MCP_SERVER_KEY_SUFFIX}"))
-                {
-                    if !installed_names.contains(stripped) {
-                        return Some(key.clone());
-                    }
-                }
-                None
-            })
+            .filter(|key| key.ends_with(&format!(" {INSTALLED_MCP_SERVER_KEY_SUFFIX}")))
+            .cloned()
             .collect();
 
         for key in keys_to_remove {
@@ -266,8 +225,8 @@ impl Model {
         db: &DatabaseConnection,
         client_name: &str,
     ) -> Result<(), String> {
-        // Update the externalMCP client's config with the installed Archestra MCP servers
-        Self::update_external_mcp_client_config(db, client_name).await?;
+        // Update the external MCP client's config with the Archestra MCP server
+        Self::update_external_mcp_client_config(client_name)?;
 
         // Save external MCP client to database
         let definition = ExternalMCPClientDefinition {
@@ -391,7 +350,7 @@ impl Model {
             .await
             .map_err(|e| e.to_string())?;
         for client in connected_clients {
-            Self::update_external_mcp_client_config(db, &client.client_name).await?;
+            Self::update_external_mcp_client_config(&client.client_name)?;
         }
         Ok(())
     }
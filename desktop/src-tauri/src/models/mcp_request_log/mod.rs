@@ -1,9 +1,13 @@
+use crate::models::secret;
 use sea_orm::entity::prelude::*;
 use sea_orm::{PaginatorTrait, QueryOrder, Set};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use utoipa::ToSchema;
 
+/// Status code of requests cancelled by their client, like nginx's "client closed request"
+pub const CANCELLED_STATUS_CODE: i32 = 499;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "mcp_request_logs")]
 #[schema(as = MCPRequestLog)]
@@ -26,6 +30,8 @@ pub struct Model {
     pub duration_ms: Option<i32>,
     #[schema(value_type = String, format = DateTime)]
     pub timestamp: DateTimeUtc,
+    /// Shared by the requests that were sent together in one JSON-RPC batch
+    pub batch_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -50,6 +56,7 @@ pub struct LogFilters {
     pub mcp_session_id: Option<String>,
     pub status_code: Option<i32>,
     pub method: Option<String>,
+    pub batch_id: Option<String>,
     #[schema(value_type = Option<String>, format = DateTime)]
     pub start_time: Option<DateTimeUtc>,
     #[schema(value_type = Option<String>, format = DateTime)]
@@ -62,6 +69,7 @@ pub struct LogStats {
     pub total_requests: u64,
     pub success_count: u64,
     pub error_count: u64,
+    pub cancelled_count: u64,
     pub avg_duration_ms: f64,
     pub requests_per_server: HashMap<String, u64>,
 }
@@ -82,6 +90,7 @@ pub struct CreateLogRequest {
     pub status_code: i32,
     pub error_message: Option<String>,
     pub duration_ms: Option<i32>,
+    pub batch_id: Option<String>,
 }
 
 impl From<CreateLogRequest> for ActiveModel {
@@ -91,6 +100,9 @@ impl From<CreateLogRequest> for ActiveModel {
             .client_info
             .and_then(|info| serde_json::to_string(&info).ok());
 
+        // Secret values the server or client passed along are never stored
+        let redact = |text: Option<String>| text.map(|text| secret::redact(&text));
+
         let request_headers_json = log_data
             .request_headers
             .and_then(|headers| serde_json::to_string(&headers).ok());
@@ -106,14 +118,15 @@ impl From<CreateLogRequest> for ActiveModel {
             server_name: Set(log_data.server_name),
             client_info: Set(client_info_json),
             method: Set(log_data.method),
-            request_headers: Set(request_headers_json),
-            request_body: Set(log_data.request_body),
-            response_body: Set(log_data.response_body),
-            response_headers: Set(response_headers_json),
+            request_headers: Set(redact(request_headers_json)),
+            request_body: Set(redact(log_data.request_body)),
+            response_body: Set(redact(log_data.response_body)),
+            response_headers: Set(redact(response_headers_json)),
             status_code: Set(log_data.status_code),
-            error_message: Set(log_data.error_message),
+            error_message: Set(redact(log_data.error_message)),
             duration_ms: Set(log_data.duration_ms),
             timestamp: Set(chrono::Utc::now()),
+            batch_id: Set(log_data.batch_id),
             ..Default::default()
         }
     }
@@ -155,6 +168,9 @@ impl Model {
             if let Some(method) = filters.method {
                 query = query.filter(Column::Method.eq(method));
             }
+            if let Some(batch_id) = filters.batch_id {
+                query = query.filter(Column::BatchId.eq(batch_id));
+            }
             if let Some(start_time) = filters.start_time {
                 query = query.filter(Column::Timestamp.gte(start_time));
             }
@@ -204,6 +220,9 @@ impl Model {
             if let Some(method) = filters.method {
                 base_query = base_query.filter(Column::Method.eq(method));
             }
+            if let Some(batch_id) = filters.batch_id {
+                base_query = base_query.filter(Column::BatchId.eq(batch_id));
+            }
             if let Some(start_time) = filters.start_time {
                 base_query = base_query.filter(Column::Timestamp.gte(start_time));
             }
@@ -219,7 +238,11 @@ impl Model {
             .iter()
             .filter(|log| log.status_code >= 200 && log.status_code < 300)
             .count() as u64;
-        let error_count = total_requests - success_count;
+        let cancelled_count = all_logs
+            .iter()
+            .filter(|log| log.status_code == CANCELLED_STATUS_CODE)
+            .count() as u64;
+        let error_count = total_requests - success_count - cancelled_count;
 
         let avg_duration_ms = if total_requests > 0 {
             let total_duration: i32 = all_logs.iter().filter_map(|log| log.duration_ms).sum();
@@ -243,6 +266,7 @@ impl Model {
             total_requests,
             success_count,
             error_count,
+            cancelled_count,
             avg_duration_ms,
             requests_per_server,
         })
@@ -326,6 +350,7 @@ mod tests {
             status_code: 200,
             error_message: None,
             duration_ms: Some(150),
+            batch_id: None,
         };
 
         let result = Model::create_request_log(&db, log_data).await;
@@ -358,6 +383,7 @@ mod tests {
                 status_code: 200,
                 error_message: None,
                 duration_ms: Some(100 + i),
+                batch_id: None,
             };
             Model::create_request_log(&db, log_data).await.unwrap();
         }
@@ -396,6 +422,7 @@ mod tests {
                 status_code: *status,
                 error_message: None,
                 duration_ms: Some(*duration),
+                batch_id: None,
             };
             Model::create_request_log(&db, log_data).await.unwrap();
         }
@@ -408,4 +435,37 @@ mod tests {
         assert!(stats.avg_duration_ms > 0.0);
         assert_eq!(stats.requests_per_server.len(), 2);
     }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_cancelled_requests_are_not_errors(#[future] database: DatabaseConnection) {
+        let db = database.await;
+
+        for (i, status) in [200, 500, CANCELLED_STATUS_CODE].iter().enumerate() {
+            let log_data = CreateLogRequest {
+                request_id: format!("test-{i}"),
+                session_id: None,
+                mcp_session_id: None,
+                server_name: "server1".to_string(),
+                client_info: None,
+                method: Some("tools/call".to_string()),
+                request_headers: None,
+                request_body: None,
+                response_body: None,
+                response_headers: None,
+                status_code: *status,
+                error_message: None,
+                duration_ms: Some(10),
+                batch_id: None,
+            };
+            Model::create_request_log(&db, log_data).await.unwrap();
+        }
+
+        let stats = Model::get_request_log_stats(&db, None).await.unwrap();
+
+        assert_eq!(stats.total_requests, 3);
+        assert_eq!(stats.success_count, 1);
+        assert_eq!(stats.error_count, 1);
+        assert_eq!(stats.cancelled_count, 1);
+    }
 }
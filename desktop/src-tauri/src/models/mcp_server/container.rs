@@ -0,0 +1,320 @@
+//! Servers with the "docker" transport run in a container. The manager spawns
+//! `docker run -i` (or podman) and talks stdio to it like to any other server process.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::data_dir::ServerDataDir;
+use super::{ContainerConfig, ResourceLimits};
+use crate::utils::names;
+use crate::utils::node::find_in_path;
+
+const CONTAINER_NAME_PREFIX: &str = "archestra-mcp-";
+/// Labels carrying the server name and which of its processes a container runs, so
+/// the containers of a server can be told apart
+const SERVER_LABEL: &str = "archestra.mcp-server";
+const REPLICA_LABEL: &str = "archestra.mcp-replica";
+
+/// Find the runtime to run a server's containers with: podman if that's the server's
+/// command, otherwise docker, or podman if docker isn't installed
+pub fn detect_runtime(command: &str) -> Result<String, String> {
+    let candidates: &[&str] = if command == "podman" {
+        &["podman"]
+    } else {
+        &["docker", "podman"]
+    };
+    candidates
+        .iter()
+        .find_map(|candidate| find_in_path(candidate))
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or_else(get_container_runtime_installation_instructions)
+}
+
+/// Name for a new container running one of a server's processes. Every process gets
+/// a name of its own, so removing the container of one that exited can't hit the
+/// container of the process that replaced it.
+pub fn container_name(server_name: &str, replica: usize) -> String {
+    let process_id = uuid::Uuid::new_v4().simple().to_string();
+    format!(
+        "{CONTAINER_NAME_PREFIX}{}-{replica}-{}",
+        names::unique(server_name),
+        &process_id[..8]
+    )
+}
+
+/// Start of the `run` command line: stdin kept open for the stdio transport, the
+/// container removed once it exits, and named and labelled so it can be removed when
+/// the server is stopped
+pub fn run_prefix(server_name: &str, replica: usize, container_name: &str) -> Vec<String> {
+    vec![
+        "run".to_string(),
+        "-i".to_string(),
+        "--rm".to_string(),
+        "--name".to_string(),
+        container_name.to_string(),
+        "--label".to_string(),
+        format!("{SERVER_LABEL}={server_name}"),
+        "--label".to_string(),
+        format!("{REPLICA_LABEL}={replica}"),
+    ]
+}
+
+/// The rest of the `run` command line: the server's settings, the image and its args.
+/// Env values are passed through the runtime's own environment, so they don't show up
+/// in the command line.
+pub fn run_args(
+    config: &ContainerConfig,
+    env: &HashMap<String, String>,
+    args: Vec<String>,
+    resource_limits: Option<&ResourceLimits>,
+    data_dir: Option<&ServerDataDir>,
+) -> Result<Vec<String>, String> {
+    if config.image.is_empty() {
+        return Err("No image specified for the container".to_string());
+    }
+
+    let mut run_args = Vec::new();
+
+    let mut env_keys: Vec<&String> = env.keys().collect();
+    env_keys.sort();
+    for key in env_keys {
+        run_args.push("-e".to_string());
+        run_args.push(key.clone());
+    }
+
+    for mount in &config.mounts {
+        let source = if Path::new(&mount.source).is_absolute() {
+            Path::new(&mount.source).to_path_buf()
+        } else {
+            let Some(data_dir) = data_dir else {
+                return Err(format!(
+                    "Mount source '{}' is relative, but the server has no data directory",
+                    mount.source
+                ));
+            };
+            // Unlike docker, podman won't create a missing source directory
+            let source = data_dir.home().join(&mount.source);
+            std::fs::create_dir_all(&source)
+                .map_err(|e| format!("Failed to create {}: {e}", source.display()))?;
+            source
+        };
+        let read_only = if mount.read_only { ":ro" } else { "" };
+        run_args.push("-v".to_string());
+        run_args.push(format!("{}:{}{read_only}", source.display(), mount.target));
+    }
+
+    if let Some(network) = &config.network {
+        run_args.push("--network".to_string());
+        run_args.push(network.clone());
+    }
+
+    // The limits apply to the container rather than to the runtime's client process
+    if let Some(limits) = resource_limits {
+        if let Some(max_memory_mb) = limits.max_memory_mb {
+            run_args.push("--memory".to_string());
+            run_args.push(format!("{max_memory_mb}m"));
+        }
+        if let Some(max_processes) = limits.max_processes {
+            run_args.push("--pids-limit".to_string());
+            run_args.push(max_processes.to_string());
+        }
+        if let Some(max_open_files) = limits.max_open_files {
+            run_args.push("--ulimit".to_string());
+            run_args.push(format!("nofile={max_open_files}:{max_open_files}"));
+        }
+        if let Some(max_cpu_seconds) = limits.max_cpu_seconds {
+            run_args.push("--ulimit".to_string());
+            run_args.push(format!("cpu={max_cpu_seconds}:{max_cpu_seconds}"));
+        }
+    }
+
+    run_args.push(config.image.clone());
+    run_args.extend(args);
+    Ok(run_args)
+}
+
+/// Remove the containers left over from a server process that was killed along with
+/// the app, and wait until they are gone
+pub async fn remove_leftover_containers(runtime: &str, server_name: &str, replica: usize) {
+    let Ok(output) = tokio::process::Command::new(runtime)
+        .args(["ps", "-a", "-q", "--filter"])
+        .arg(format!("label={SERVER_LABEL}={server_name}"))
+        .arg("--filter")
+        .arg(format!("label={REPLICA_LABEL}={replica}"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+    else {
+        return;
+    };
+    let ids = String::from_utf8_lossy(&output.stdout);
+    let ids: Vec<&str> = ids.split_whitespace().collect();
+    if ids.is_empty() {
+        return;
+    }
+
+    let _ = tokio::process::Command::new(runtime)
+        .args(["rm", "-f"])
+        .args(ids)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+}
+
+/// Removes a server process's container when dropped, including when its supervisor is
+/// aborted: killing the runtime's client process leaves the container running
+pub struct ContainerGuard {
+    runtime: String,
+    name: String,
+}
+
+impl ContainerGuard {
+    pub fn new(runtime: &str, container_name: &str) -> Self {
+        Self {
+            runtime: runtime.to_string(),
+            name: container_name.to_string(),
+        }
+    }
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        // Spawned rather than waited for, the removal finishes even if the app is exiting
+        let removal = Command::new(&self.runtime)
+            .args(["rm", "-f", &self.name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match removal {
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => eprintln!("⚠️ Failed to remove container {}: {e}", self.name),
+        }
+    }
+}
+
+/// Get a user-friendly error message for a missing container runtime
+pub fn get_container_runtime_installation_instructions() -> String {
+    let os = std::env::consts::OS;
+
+    match os {
+        "macos" => {
+            "Neither Docker nor Podman is installed on your system. Please install one of them:\n\n\
+             1. Docker Desktop: https://www.docker.com/products/docker-desktop/\n\
+             2. Using Homebrew: brew install podman"
+                .to_string()
+        }
+        "linux" => {
+            "Neither Docker nor Podman is installed on your system. Please install one of them \
+             using your package manager:\n\n\
+             Ubuntu/Debian: sudo apt-get install docker.io (or podman)\n\
+             Fedora: sudo dnf install podman\n\
+             Arch: sudo pacman -S docker (or podman)"
+                .to_string()
+        }
+        "windows" => "Neither Docker nor Podman is installed on your system. Please:\n\n\
+             1. Download Docker Desktop from: https://www.docker.com/products/docker-desktop/\n\
+             2. Or Podman Desktop from: https://podman-desktop.io/\n\
+             3. Restart this application after installation"
+            .to_string(),
+        _ => "Neither Docker nor Podman is installed on your system. Please install Docker from \
+             https://docs.docker.com/get-docker/ or Podman from https://podman.io/"
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mcp_server::ContainerMount;
+
+    #[test]
+    fn test_container_name_is_safe() {
+        let name = container_name("GitHub (work)", 1);
+        let prefix = format!("archestra-mcp-{}-1-", names::unique("GitHub (work)"));
+        assert!(name.starts_with(&prefix), "{name}");
+        assert!(name.starts_with("archestra-mcp-GitHub__work_-"), "{name}");
+
+        // Neither servers with similar names nor two processes of a server share one
+        let without_process_id = |name: String| name.rsplit_once('-').unwrap().0.to_string();
+        assert_ne!(
+            without_process_id(container_name("My Server", 0)),
+            without_process_id(container_name("My_Server", 0))
+        );
+        assert_ne!(container_name("github", 0), container_name("github", 0));
+    }
+
+    #[test]
+    fn test_run_args() {
+        let data_root = tempfile::tempdir().unwrap();
+        let data_dir = ServerDataDir::new(data_root.path(), "github");
+        let absolute = data_root.path().join("shared");
+        let config = ContainerConfig {
+            image: "ghcr.io/github/github-mcp-server".to_string(),
+            mounts: vec![
+                ContainerMount {
+                    source: "workspace".to_string(),
+                    target: "/workspace".to_string(),
+                    read_only: false,
+                },
+                ContainerMount {
+                    source: absolute.to_string_lossy().to_string(),
+                    target: "/shared".to_string(),
+                    read_only: true,
+                },
+            ],
+            network: Some("none".to_string()),
+        };
+        let env = HashMap::from([
+            ("GITHUB_TOKEN".to_string(), "ghp_abc123".to_string()),
+            ("DEBUG".to_string(), "1".to_string()),
+        ]);
+        let limits = ResourceLimits {
+            max_memory_mb: Some(512),
+            ..Default::default()
+        };
+
+        let args = run_args(
+            &config,
+            &env,
+            vec!["stdio".to_string()],
+            Some(&limits),
+            Some(&data_dir),
+        )
+        .unwrap();
+        let workspace = data_dir.home().join("workspace");
+        assert!(workspace.is_dir());
+        assert_eq!(
+            args,
+            vec![
+                "-e".to_string(),
+                "DEBUG".to_string(),
+                "-e".to_string(),
+                "GITHUB_TOKEN".to_string(),
+                "-v".to_string(),
+                format!("{}:/workspace", workspace.display()),
+                "-v".to_string(),
+                format!("{}:/shared:ro", absolute.display()),
+                "--network".to_string(),
+                "none".to_string(),
+                "--memory".to_string(),
+                "512m".to_string(),
+                "ghcr.io/github/github-mcp-server".to_string(),
+                "stdio".to_string(),
+            ]
+        );
+        // Values stay out of the command line
+        assert!(!args.iter().any(|arg| arg.contains("ghp_abc123")));
+
+        // A relative mount needs the data directory
+        assert!(run_args(&config, &env, vec![], None, None).is_err());
+        let no_image = ContainerConfig::default();
+        assert!(run_args(&no_image, &env, vec![], None, None).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Every server process gets a directory of its own under the app data dir, used as its
+//! HOME, temp directory and npm cache, so that servers don't write into the user's home
+//! or see each other's files.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::utils::names;
+
+/// The directory a server keeps its files in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerDataDir {
+    root: PathBuf,
+}
+
+impl ServerDataDir {
+    pub fn new(data_root: &Path, server_name: &str) -> Self {
+        // Server names come from users and catalogs, keep them from escaping the directory
+        Self {
+            root: data_root
+                .join("mcp_servers")
+                .join(names::unique(server_name)),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The server's HOME, which is also where it runs unless it has a `cwd`
+    pub fn home(&self) -> PathBuf {
+        self.root.join("home")
+    }
+
+    pub fn tmp(&self) -> PathBuf {
+        self.root.join("tmp")
+    }
+
+    pub fn npm_cache(&self) -> PathBuf {
+        self.root.join("npm-cache")
+    }
+
+    /// npm prefix the package of an `npx` server is installed into
+    pub fn npm_prefix(&self) -> PathBuf {
+        self.root.join("npm")
+    }
+
+    /// Virtual environment of a Python server, created when it's first needed
+    pub fn venv(&self) -> PathBuf {
+        self.root.join("venv")
+    }
+
+    pub fn create(&self) -> Result<(), String> {
+        for dir in [self.home(), self.tmp(), self.npm_cache()] {
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Delete the directory and everything the server put in it
+    pub fn remove(&self) -> Result<(), String> {
+        match std::fs::remove_dir_all(&self.root) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove {}: {e}", self.root.display())),
+        }
+    }
+
+    /// Environment pointing the server's HOME, temp directory and npm cache here,
+    /// under the names both Unix and Windows tools look at
+    pub fn env(&self) -> HashMap<String, String> {
+        let home = self.home().to_string_lossy().to_string();
+        let tmp = self.tmp().to_string_lossy().to_string();
+        HashMap::from([
+            ("HOME".to_string(), home.clone()),
+            ("USERPROFILE".to_string(), home),
+            ("TMPDIR".to_string(), tmp.clone()),
+            ("TEMP".to_string(), tmp.clone()),
+            ("TMP".to_string(), tmp),
+            (
+                "npm_config_cache".to_string(),
+                self.npm_cache().to_string_lossy().to_string(),
+            ),
+        ])
+    }
+
+    /// Where the server runs: its configured directory, taken relative to its HOME if
+    /// it isn't absolute, or else its HOME. A relative directory can't climb out of HOME
+    /// with `..`, as it's also where a sandboxed server is allowed to read.
+    pub fn working_dir(&self, cwd: Option<&str>) -> Result<PathBuf, String> {
+        let Some(cwd) = cwd else {
+            return Ok(self.home());
+        };
+        let path = Path::new(cwd);
+        if !path.is_absolute()
+            && path
+                .components()
+                .any(|component| matches!(component, Component::ParentDir))
+        {
+            return Err(format!(
+                "Working directory {cwd} must not leave the server's home directory"
+            ));
+        }
+        Ok(self.home().join(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_name_cannot_escape_data_root() {
+        let data_dir = ServerDataDir::new(Path::new("/data"), "../../etc");
+        assert_eq!(
+            data_dir.root().parent().unwrap(),
+            Path::new("/data/mcp_servers")
+        );
+        let dir_name = data_dir.root().file_name().unwrap().to_string_lossy();
+        assert!(dir_name.starts_with("______etc-"), "{dir_name}");
+    }
+
+    #[test]
+    fn test_similar_server_names_get_different_directories() {
+        let data_root = Path::new("/data");
+        assert_ne!(
+            ServerDataDir::new(data_root, "My Server"),
+            ServerDataDir::new(data_root, "My_Server")
+        );
+        assert_eq!(
+            ServerDataDir::new(data_root, "My Server"),
+            ServerDataDir::new(data_root, "My Server")
+        );
+    }
+
+    #[test]
+    fn test_create_env_and_remove() {
+        let data_root = tempfile::tempdir().unwrap();
+        let data_dir = ServerDataDir::new(data_root.path(), "filesystem");
+        data_dir.create().unwrap();
+        assert!(data_dir.home().is_dir());
+        assert!(data_dir.tmp().is_dir());
+        assert!(data_dir.npm_cache().is_dir());
+
+        let env = data_dir.env();
+        assert_eq!(env["HOME"], data_dir.home().to_string_lossy());
+        assert_eq!(env["TMPDIR"], data_dir.tmp().to_string_lossy());
+        assert_eq!(
+            env["npm_config_cache"],
+            data_dir.npm_cache().to_string_lossy()
+        );
+
+        // A relative working directory stays inside, an absolute one is taken as is
+        assert_eq!(data_dir.working_dir(None).unwrap(), data_dir.home());
+        assert_eq!(
+            data_dir.working_dir(Some("project")).unwrap(),
+            data_dir.home().join("project")
+        );
+        let elsewhere = data_root.path().join("elsewhere");
+        assert_eq!(
+            data_dir
+                .working_dir(Some(elsewhere.to_str().unwrap()))
+                .unwrap(),
+            elsewhere
+        );
+        assert!(data_dir.working_dir(Some("../../..")).is_err());
+        assert!(data_dir.working_dir(Some("project/../../tmp")).is_err());
+
+        data_dir.remove().unwrap();
+        assert!(!data_dir.root().exists());
+        // Removing twice is fine
+        data_dir.remove().unwrap();
+    }
+}
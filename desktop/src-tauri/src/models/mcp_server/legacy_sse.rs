@@ -0,0 +1,363 @@
+//! Client side of the HTTP+SSE transport from the 2024-11-05 protocol revision.
+//!
+//! The server keeps a GET event stream open, announces in an `endpoint` event where
+//! messages are to be POSTed, and sends everything back as `message` events on that
+//! stream. A new stream means a new session, so the handshake has to be run again.
+
+use super::sandbox::{dispatch_stdout_line, ServerMessage, SharedRequestRouter};
+use super::streamable_http::{SseEvent, SseParser};
+use crate::models::mcp_server_log::{LogStream, ServerLogSink};
+use futures_util::StreamExt;
+use reqwest::{header, Url};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Notify};
+use tokio_util::sync::CancellationToken;
+
+const CHANNEL_CAPACITY: usize = 100;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Connection to a remote MCP server speaking the legacy HTTP+SSE transport
+pub struct SseTransport {
+    server_name: String,
+    url: String,
+    headers: HashMap<String, String>,
+    http_client: reqwest::Client,
+    request_router: SharedRequestRouter,
+    logs: Arc<ServerLogSink>,
+    messages_tx: mpsc::UnboundedSender<ServerMessage>,
+    // Where to POST messages for the current stream, unknown while (re)connecting
+    endpoint: watch::Sender<Option<Url>>,
+    connections: AtomicU64,
+    session_expired: Notify,
+    shutdown: CancellationToken,
+}
+
+impl SseTransport {
+    /// Start the transport, returning the channel to write messages to and the
+    /// notifications and requests the server sends on its own
+    pub fn start(
+        server_name: &str,
+        url: String,
+        headers: HashMap<String, String>,
+        http_client: reqwest::Client,
+        request_router: SharedRequestRouter,
+        logs: Arc<ServerLogSink>,
+        shutdown: CancellationToken,
+    ) -> (
+        Arc<Self>,
+        mpsc::Sender<String>,
+        mpsc::UnboundedReceiver<ServerMessage>,
+    ) {
+        let (messages_tx, messages) = mpsc::unbounded_channel();
+        let transport = Arc::new(Self {
+            server_name: server_name.to_string(),
+            url,
+            headers,
+            http_client,
+            request_router,
+            logs,
+            messages_tx,
+            endpoint: watch::Sender::new(None),
+            connections: AtomicU64::new(0),
+            session_expired: Notify::new(),
+            shutdown,
+        });
+
+        tokio::spawn(transport.clone().listen());
+
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<String>(CHANNEL_CAPACITY);
+        let writer = transport.clone();
+        tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    message = outgoing_rx.recv() => match message {
+                        Some(message) => message,
+                        None => return,
+                    },
+                    _ = writer.shutdown.cancelled() => return,
+                };
+                writer.post(message).await;
+            }
+        });
+
+        (transport, outgoing_tx, messages)
+    }
+
+    /// Resolves when the stream was reopened and a new handshake is needed
+    pub async fn session_expired(&self) {
+        self.session_expired.notified().await;
+    }
+
+    fn with_headers(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        request
+    }
+
+    /// Keep the event stream open, reconnecting whenever it drops
+    async fn listen(self: Arc<Self>) {
+        let mut delay = RECONNECT_DELAY;
+
+        loop {
+            match self.read_stream().await {
+                // Got as far as an endpoint, so the server is reachable
+                Ok(true) => delay = RECONNECT_DELAY,
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("⚠️ MCP [{}] Event stream failed: {e}", self.server_name);
+                    self.logs
+                        .push(LogStream::System, &format!("Event stream failed: {e}"));
+                }
+            }
+            if self.shutdown.is_cancelled() {
+                return;
+            }
+
+            // Nothing sent to the old session is going to be answered
+            self.endpoint.send_replace(None);
+            self.request_router.lock().await.clear();
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.shutdown.cancelled() => return,
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Read one event stream until it ends, returns whether it announced an endpoint
+    async fn read_stream(&self) -> Result<bool, String> {
+        let response = self
+            .with_headers(self.http_client.get(&self.url))
+            .header(header::ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| format!("HTTP request failed: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+
+        let mut connected = false;
+        let mut parser = SseParser::default();
+        let mut stream = response.bytes_stream();
+
+        loop {
+            let chunk = tokio::select! {
+                chunk = stream.next() => chunk,
+                _ = self.shutdown.cancelled() => return Ok(connected),
+            };
+            let chunk = match chunk {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => return Err(format!("Stream broke: {e}")),
+                None => {
+                    eprintln!("⚠️ MCP [{}] Event stream closed", self.server_name);
+                    return Ok(connected);
+                }
+            };
+
+            for event in parser.feed(&chunk) {
+                if event.event.as_deref() == Some("endpoint") {
+                    self.set_endpoint(&event)?;
+                    connected = true;
+                } else if !event.data.is_empty() {
+                    self.dispatch(&event.data).await;
+                }
+            }
+        }
+    }
+
+    fn set_endpoint(&self, event: &SseEvent) -> Result<(), String> {
+        // The endpoint is usually relative to the stream's URL
+        let endpoint = Url::parse(&self.url)
+            .and_then(|base| base.join(event.data.trim()))
+            .map_err(|e| format!("Invalid endpoint '{}': {e}", event.data))?;
+        println!(
+            "🔗 MCP [{}] Posting messages to {endpoint}",
+            self.server_name
+        );
+        self.endpoint.send_replace(Some(endpoint));
+
+        // Every stream after the first one is a fresh session
+        if self.connections.fetch_add(1, Ordering::SeqCst) > 0 {
+            self.session_expired.notify_one();
+        }
+        Ok(())
+    }
+
+    /// POST a message to the current endpoint, its response arrives on the event stream
+    async fn post(&self, message: String) {
+        let request_id = serde_json::from_str::<serde_json::Value>(&message)
+            .ok()
+            .filter(|json| json.get("method").is_some())
+            .and_then(|json| json.get("id").cloned());
+
+        let mut endpoint = self.endpoint.subscribe();
+        let endpoint = tokio::select! {
+            endpoint = endpoint.wait_for(Option::is_some) => {
+                endpoint.ok().and_then(|endpoint| endpoint.clone())
+            }
+            _ = self.shutdown.cancelled() => None,
+        };
+        let Some(endpoint) = endpoint else {
+            return;
+        };
+
+        let response = self
+            .with_headers(self.http_client.post(endpoint))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(message)
+            .send()
+            .await;
+        let error = match response {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => format!("HTTP {}", response.status()),
+            Err(e) => format!("HTTP request failed: {e}"),
+        };
+
+        eprintln!("❌ MCP [{}] {error}", self.server_name);
+        self.logs.push(LogStream::System, &error);
+        if let Some(id) = request_id {
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32603, "message": error },
+            });
+            self.dispatch(&response.to_string()).await;
+        }
+    }
+
+    async fn dispatch(&self, message: &str) {
+        if let Some(message) =
+            dispatch_stdout_line(&self.server_name, message, &self.request_router, &self.logs).await
+        {
+            let _ = self.messages_tx.send(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::mcp_server::sandbox::MCPServerManager;
+    use crate::models::mcp_server::ServerConfig;
+    use axum::{
+        extract::{Query, State},
+        http::StatusCode,
+        response::sse::{Event, Sse},
+        routing::{get, post},
+        Router,
+    };
+    use futures_util::stream::Stream;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, Mutex as TokioMutex};
+
+    /// The open event stream of a fake legacy server
+    type EventStream = Arc<TokioMutex<Option<mpsc::UnboundedSender<String>>>>;
+
+    async fn fake_sse(
+        State(stream): State<EventStream>,
+    ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *stream.lock().await = Some(tx);
+
+        let endpoint = futures_util::stream::once(async {
+            Ok(Event::default()
+                .event("endpoint")
+                .data("/messages?session_id=abc"))
+        });
+        let messages = futures_util::stream::unfold(rx, |mut rx| async move {
+            let message = rx.recv().await?;
+            Some((Ok(Event::default().event("message").data(message)), rx))
+        });
+        Sse::new(futures_util::StreamExt::chain(endpoint, messages))
+    }
+
+    async fn fake_messages(
+        State(stream): State<EventStream>,
+        Query(query): Query<HashMap<String, String>>,
+        body: String,
+    ) -> StatusCode {
+        if query.get("session_id").map(String::as_str) != Some("abc") {
+            return StatusCode::NOT_FOUND;
+        }
+        let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let result = match request["method"].as_str() {
+            Some("initialize") => json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "legacy", "version": "0.1.0" }
+            }),
+            Some("tools/list") => json!({
+                "tools": [{ "name": "lookup", "inputSchema": { "type": "object" } }]
+            }),
+            Some("tools/call") => json!({ "content": [{ "type": "text", "text": "found" }] }),
+            _ => return StatusCode::ACCEPTED,
+        };
+
+        let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+        if let Some(stream) = stream.lock().await.as_ref() {
+            let _ = stream.send(response.to_string());
+        }
+        StatusCode::ACCEPTED
+    }
+
+    #[tokio::test]
+    async fn test_legacy_server_responses_arrive_on_event_stream() {
+        let app = Router::new()
+            .route("/sse", get(fake_sse))
+            .route("/messages", post(fake_messages))
+            .with_state(EventStream::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sse", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let manager = MCPServerManager::new();
+        manager
+            .start_server(
+                "legacy".to_string(),
+                ServerConfig {
+                    transport: "sse".to_string(),
+                    args: vec![url],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while manager
+                .get_cached_tools("legacy")
+                .await
+                .is_none_or(|tools| tools.is_empty())
+            {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let response = manager
+            .forward_raw_request(
+                "legacy",
+                Some("session-a"),
+                r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"lookup"}}"#
+                    .to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["content"][0]["text"], "found");
+
+        manager.stop_server("legacy").await.unwrap();
+    }
+}
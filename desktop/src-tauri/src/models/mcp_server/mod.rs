@@ -5,8 +5,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use utoipa::ToSchema;
 
+pub mod container;
+pub mod data_dir;
+pub mod legacy_sse;
 pub mod oauth;
 pub mod sandbox;
+pub mod streamable_http;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "mcp_servers")]
@@ -20,6 +24,8 @@ pub struct Model {
     pub meta: Option<String>,  // JSON string containing additional metadata
     #[schema(value_type = String, format = DateTime)]
     pub created_at: DateTimeUtc,
+    /// Whether the server is started with the app, false once it was stopped explicitly
+    pub enabled: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -42,13 +48,198 @@ pub struct ConnectorCatalogEntryOauth {
     pub required: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 #[schema(as = MCPServerConfig)]
 pub struct ServerConfig {
-    pub transport: String, // "stdio" or "http"
+    pub transport: String, // "stdio", "http", "sse" (the legacy HTTP+SSE transport) or "docker"
     pub command: String,
+    /// May refer to stored secrets as `secret://<name>`, resolved when the server starts
     pub args: Vec<String>,
+    /// Environment of the process, or headers of a remote server. Values may refer to
+    /// stored secrets like args do.
     pub env: HashMap<String, String>,
+    /// Directory the server runs in, relative paths are taken from its own HOME under
+    /// the app data dir, which is also where it runs if this isn't set
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Let npm run install scripts when it installs the package of an `npx` server. Some
+    /// packages need them to build native code, but they run outside of any sandbox.
+    #[serde(default)]
+    pub run_install_scripts: bool,
+    #[serde(default)]
+    pub restart_policy: Option<RestartPolicy>,
+    /// Confine the server process, currently enforced on Linux only. Sandboxing is
+    /// opt-in: a server without this setting runs with the user's full permissions
+    #[serde(default)]
+    pub sandbox: Option<SandboxConfig>,
+    #[serde(default)]
+    pub resource_limits: Option<ResourceLimits>,
+    /// Persist the server's output to the database, keeping it this many days
+    #[serde(default)]
+    pub log_retention_days: Option<u32>,
+    #[serde(default)]
+    pub health_check: Option<HealthCheckConfig>,
+    #[serde(default)]
+    pub activation: Activation,
+    /// Stop a lazily activated server after this many minutes without requests, 10 by default
+    #[serde(default)]
+    pub idle_timeout_minutes: Option<u64>,
+    /// Run this many identical processes of a stdio server and spread requests across them
+    #[serde(default)]
+    pub replicas: Option<u32>,
+    #[serde(default)]
+    pub timeouts: Option<TimeoutConfig>,
+    /// Image and container settings of a server with the "docker" transport
+    #[serde(default)]
+    pub container: Option<ContainerConfig>,
+}
+
+/// How long requests forwarded to a server may take before they are cancelled
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+#[schema(as = MCPServerTimeoutConfig)]
+pub struct TimeoutConfig {
+    /// Milliseconds any request may take, the gateway's 30 seconds if not set
+    pub default_ms: Option<u64>,
+    /// Milliseconds by JSON-RPC method, e.g. a short limit for "tools/list"
+    pub methods: HashMap<String, u64>,
+    /// Milliseconds by tool name, taking precedence over a "tools/call" method limit
+    pub tools: HashMap<String, u64>,
+}
+
+/// A server run from an OCI image, talking stdio through `docker run -i`. It's run with
+/// podman if that's the server's command, otherwise with docker, or podman if docker is
+/// missing. The container gets the server's env and its args follow the image.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = MCPServerContainerConfig)]
+pub struct ContainerConfig {
+    pub image: String,
+    #[serde(default)]
+    pub mounts: Vec<ContainerMount>,
+    /// Network mode, e.g. "none" or "host", the runtime's default if not set
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = MCPServerContainerMount)]
+pub struct ContainerMount {
+    /// Directory on the host, relative paths are taken from the server's own HOME under
+    /// the app data dir
+    pub source: String,
+    /// Where the directory shows up in the container
+    pub target: String,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// When a server is started: with the app, or when a client first sends it a request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[schema(as = MCPServerActivation)]
+pub enum Activation {
+    #[default]
+    Eager,
+    Lazy,
+}
+
+/// How a running MCP server is pinged to tell whether it still responds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+#[schema(as = MCPServerHealthCheckConfig)]
+pub struct HealthCheckConfig {
+    /// Seconds between two pings
+    pub interval_seconds: u64,
+    /// How long to wait for an answer before the ping counts as failed
+    pub timeout_ms: u64,
+    /// Answers slower than this mark the server as degraded
+    pub degraded_latency_ms: u64,
+    /// Consecutive failed pings after which the server is unresponsive
+    pub unresponsive_after: u32,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 30,
+            timeout_ms: 5000,
+            degraded_latency_ms: 1000,
+            unresponsive_after: 3,
+        }
+    }
+}
+
+/// How a crashed MCP server process gets restarted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+#[schema(as = MCPServerRestartPolicy)]
+pub struct RestartPolicy {
+    /// Consecutive restarts before the server is given up on and marked as crashed
+    pub max_restarts: u32,
+    /// Delay before the first restart, doubled after every consecutive crash
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            initial_backoff_ms: 1000,
+        }
+    }
+}
+
+/// Caps on what a server process may consume. Everything but the lifetime is only
+/// enforced on Linux: memory and the process count through a cgroup of the server's
+/// own that covers everything it spawns, where cgroup v2 is available, CPU time and
+/// open files as rlimits of the server process. Without cgroup v2 memory is limited
+/// by an rlimit too and the process count isn't limited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+#[schema(as = MCPServerResourceLimits)]
+pub struct ResourceLimits {
+    /// Maximum memory, in megabytes
+    pub max_memory_mb: Option<u64>,
+    pub max_cpu_seconds: Option<u64>,
+    pub max_open_files: Option<u64>,
+    /// Maximum number of processes the server may run at once, itself included
+    pub max_processes: Option<u64>,
+    /// Kill (and restart) the process after it has been running this long
+    pub max_lifetime_seconds: Option<u64>,
+}
+
+/// Baseline set of paths and syscalls a sandboxed server gets, mirroring the
+/// tiers of `utils::node::generate_sandbox_profile`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[schema(as = MCPServerSandboxProfile)]
+pub enum SandboxProfile {
+    #[default]
+    Permissive,
+    Restrictive,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+#[schema(as = MCPServerSandboxConfig)]
+pub struct SandboxConfig {
+    pub profile: SandboxProfile,
+    /// Extra paths the server may read, on top of the profile's defaults
+    pub read_paths: Vec<String>,
+    /// Extra paths the server may write, on top of the profile's defaults
+    pub write_paths: Vec<String>,
+    pub allow_network: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            profile: SandboxProfile::default(),
+            read_paths: Vec::new(),
+            write_paths: Vec::new(),
+            allow_network: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -124,10 +315,12 @@ impl Model {
         // Save to database
         let result = Self::save_server_without_lifecycle(db, definition).await?;
 
-        // Start the server after saving
-        if let Err(e) = sandbox::start_mcp_server(definition).await {
-            eprintln!("Warning: Failed to start server after save: {e}");
-            // Don't fail the save operation, but log the error
+        // Start the server after saving, unless it was stopped explicitly
+        if result.enabled {
+            if let Err(e) = sandbox::activate_mcp_server(definition).await {
+                eprintln!("Warning: Failed to start server after save: {e}");
+                // Don't fail the save operation, but log the error
+            }
         }
 
         // Sync all connected external MCP clients
@@ -143,16 +336,38 @@ impl Model {
         Entity::find().all(db).await
     }
 
+    /// Remember whether an MCP server should be running, so that servers stopped
+    /// explicitly stay stopped on the next launch
+    pub async fn set_enabled(
+        db: &DatabaseConnection,
+        server_name: &str,
+        enabled: bool,
+    ) -> Result<(), DbErr> {
+        Entity::update_many()
+            .col_expr(Column::Enabled, Expr::value(enabled))
+            .filter(Column::Name.eq(server_name))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
     /// Uninstall an MCP server - stop its process running in the sandbox, delete it from the database and sync all connected external MCP clients
     pub async fn uninstall_mcp_server(
         db: &DatabaseConnection,
         server_name: &str,
+        keep_data: bool,
     ) -> Result<(), DbErr> {
         // Stop the server, in the background, before deleting
         // if there's an error stopping the server, that's fine (for now)
         let server_name_for_bg = server_name.to_string();
         tokio::spawn(async move {
             let _ = sandbox::stop_mcp_server(&server_name_for_bg).await;
+            // Only once the process is gone, it may still be using its files
+            if !keep_data {
+                if let Err(e) = sandbox::remove_mcp_server_data(&server_name_for_bg) {
+                    eprintln!("⚠️ MCP [{server_name_for_bg}] {e}");
+                }
+            }
         });
 
         Entity::delete_many()
@@ -287,6 +502,7 @@ mod tests {
             command: "echo".to_string(),
             args: vec!["hello".to_string()],
             env: HashMap::new(),
+            ..Default::default()
         };
 
         let definition = MCPServerDefinition {
@@ -313,6 +529,7 @@ mod tests {
             command: "node".to_string(),
             args: vec!["index.js".to_string()],
             env: HashMap::new(),
+            ..Default::default()
         };
 
         let meta = serde_json::json!({
@@ -343,6 +560,7 @@ mod tests {
             command: "echo".to_string(),
             args: vec!["hello".to_string()],
             env: HashMap::new(),
+            ..Default::default()
         };
 
         let definition = MCPServerDefinition {
@@ -397,6 +615,7 @@ mod tests {
                     command: "echo".to_string(),
                     args: vec![format!("hello_{i}")],
                     env: HashMap::new(),
+                    ..Default::default()
                 },
                 meta: None,
             };
@@ -423,6 +642,7 @@ mod tests {
                 command: "echo".to_string(),
                 args: vec!["hello".to_string()],
                 env: HashMap::new(),
+                ..Default::default()
             },
             meta: None,
         };
@@ -441,7 +661,7 @@ mod tests {
         assert!(found.is_some());
 
         // Uninstall it
-        let result = Model::uninstall_mcp_server(&db, "server_to_uninstall").await;
+        let result = Model::uninstall_mcp_server(&db, "server_to_uninstall", false).await;
         assert!(result.is_ok());
 
         // Verify it's gone
@@ -459,7 +679,7 @@ mod tests {
         let db = database.await;
 
         // Try to uninstall a server that doesn't exist
-        let result = Model::uninstall_mcp_server(&db, "nonexistent_server").await;
+        let result = Model::uninstall_mcp_server(&db, "nonexistent_server", false).await;
         // Should succeed (no-op)
         assert!(result.is_ok());
     }
@@ -476,6 +696,7 @@ mod tests {
                 command: "echo".to_string(),
                 args: vec!["hello".to_string()],
                 env: HashMap::new(),
+                ..Default::default()
             },
             meta: Some(serde_json::json!({"test": true})),
         };
@@ -498,6 +719,36 @@ mod tests {
         assert!(def.meta.is_some());
     }
 
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_enabled(#[future] database: DatabaseConnection) {
+        let db = database.await;
+
+        let definition = MCPServerDefinition {
+            name: "toggled_server".to_string(),
+            server_config: ServerConfig {
+                transport: "stdio".to_string(),
+                command: "echo".to_string(),
+                ..Default::default()
+            },
+            meta: None,
+        };
+        let saved = Model::save_server_without_lifecycle(&db, &definition)
+            .await
+            .unwrap();
+        assert!(saved.enabled);
+
+        Model::set_enabled(&db, "toggled_server", false)
+            .await
+            .unwrap();
+
+        // Saving the definition again keeps the server disabled
+        let saved = Model::save_server_without_lifecycle(&db, &definition)
+            .await
+            .unwrap();
+        assert!(!saved.enabled);
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_find_by_name_not_found(#[future] database: DatabaseConnection) {
@@ -518,6 +769,7 @@ mod tests {
             server_config: server_config_json.to_string(),
             meta: None,
             created_at: chrono::Utc::now(),
+            enabled: true,
         };
 
         let definition = model.to_definition().unwrap();
@@ -539,6 +791,7 @@ mod tests {
             server_config: server_config_json.to_string(),
             meta: Some(meta_json.to_string()),
             created_at: chrono::Utc::now(),
+            enabled: true,
         };
 
         let definition = model.to_definition().unwrap();
@@ -557,6 +810,7 @@ mod tests {
             server_config: "invalid json".to_string(),
             meta: None,
             created_at: chrono::Utc::now(),
+            enabled: true,
         };
 
         let result = model.to_definition();
@@ -585,6 +839,7 @@ mod tests {
                 command: "echo".to_string(),
                 args: vec!["hello".to_string()],
                 env: HashMap::new(),
+                ..Default::default()
             },
             meta: None,
         };
@@ -602,6 +857,7 @@ mod tests {
                 command: "node".to_string(),   // Different command
                 args: vec!["server.js".to_string()],
                 env: HashMap::new(),
+                ..Default::default()
             },
             meta: Some(serde_json::json!({"updated": true})),
         };
@@ -633,6 +889,7 @@ mod tests {
                 ("DEBUG".to_string(), "true".to_string()),
                 ("PORT".to_string(), "8080".to_string()),
             ]),
+            ..Default::default()
         };
 
         let definition = MCPServerDefinition {
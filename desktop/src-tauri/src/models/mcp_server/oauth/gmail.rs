@@ -1,8 +1,12 @@
 use crate::database::connection::get_database_connection_with_app;
 use crate::models::mcp_server::{MCPServerDefinition, Model as MCPServerModel, ServerConfig};
+use crate::models::secret::{secret_store, SECRET_SCHEME};
 use serde::{Deserialize, Serialize};
 use tauri::Emitter;
 
+const GMAIL_ACCESS_TOKEN_SECRET: &str = "gmail_access_token";
+const GMAIL_REFRESH_TOKEN_SECRET: &str = "gmail_refresh_token";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GmailTokens {
     pub access_token: String,
@@ -33,7 +37,29 @@ pub async fn handle_gmail_oauth_callback(app: tauri::AppHandle, url: String) {
                     .and_then(|d| d.parse::<u64>().ok()),
             };
 
-            // Save MCP server with tokens in meta field
+            // The tokens go into the secret store, the server only gets references to them
+            let Some(secrets) = secret_store() else {
+                eprintln!("Secret store is not available to save Gmail tokens");
+                let _ = app.emit("oauth-error", "Secret store is not available");
+                return;
+            };
+            for (name, value) in [
+                (GMAIL_ACCESS_TOKEN_SECRET, &tokens.access_token),
+                (GMAIL_REFRESH_TOKEN_SECRET, &tokens.refresh_token),
+            ] {
+                if let Err(e) = secrets.set(name, value).await {
+                    eprintln!("Failed to save Gmail token: {e}");
+                    let _ = app.emit("oauth-error", format!("Failed to save token: {e}"));
+                    return;
+                }
+            }
+            let tokens = GmailTokens {
+                access_token: format!("{SECRET_SCHEME}{GMAIL_ACCESS_TOKEN_SECRET}"),
+                refresh_token: format!("{SECRET_SCHEME}{GMAIL_REFRESH_TOKEN_SECRET}"),
+                expiry_date: tokens.expiry_date,
+            };
+
+            // Save MCP server with the token references in meta field
             let server_config = ServerConfig {
                 transport: "stdio".to_string(),
                 command: "npx".to_string(),
@@ -43,6 +69,7 @@ pub async fn handle_gmail_oauth_callback(app: tauri::AppHandle, url: String) {
                     format!("--refresh-token={}", tokens.refresh_token),
                 ],
                 env: std::collections::HashMap::new(),
+                ..Default::default()
             };
 
             let meta = serde_json::json!({
@@ -71,7 +98,7 @@ pub async fn handle_gmail_oauth_callback(app: tauri::AppHandle, url: String) {
                 }
             }
 
-            // Emit success event to frontend with tokens
+            // Emit success event to frontend with the token references
             let _ = app.emit(
                 "oauth-success",
                 serde_json::json!({
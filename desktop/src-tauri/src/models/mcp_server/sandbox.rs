@@ -1,22 +1,60 @@
-use super::{MCPServerDefinition, ServerConfig};
+use super::container::{self, ContainerGuard};
+use super::data_dir::ServerDataDir;
+use super::legacy_sse::SseTransport;
+use super::streamable_http::HttpTransport;
+use super::{
+    Activation, HealthCheckConfig, MCPServerDefinition, ResourceLimits, RestartPolicy,
+    SandboxConfig, ServerConfig, TimeoutConfig,
+};
 use crate::database::connection::get_database_connection_with_app;
 use crate::models::mcp_server::Model;
-use crate::utils::node;
-use rmcp::model::{Resource as MCPResource, Tool as MCPTool};
+use crate::models::mcp_server_log::{LogStream, ServerLogSink};
+use crate::models::secret;
+use crate::ollama::get_app_handle;
+use crate::utils::node::{self, NpmPackagePin};
+use crate::utils::python;
+#[cfg(target_os = "linux")]
+use crate::utils::{linux_sandbox, resource_limits};
+use once_cell::sync::OnceCell;
+use rmcp::model::{
+    Implementation, InitializeResult, Prompt as MCPPrompt, ProtocolVersion,
+    Resource as MCPResource, ServerCapabilities, Tool as MCPTool,
+};
+use sea_orm::DatabaseConnection;
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
-use tokio::sync::{mpsc, Mutex as TokioMutex, RwLock};
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex as TokioMutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
 
 // Constants for resource management
-const MAX_BUFFER_SIZE: usize = 1000;
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// Starting a server may include installing its package first
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(300);
 const CHANNEL_CAPACITY: usize = 100;
+const STDERR_TAIL_LINES: usize = 20;
+// Children the server left behind may keep its stderr open after it exited
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+// A process that has been up this long is considered healthy again
+const STABLE_RUN_DURATION: Duration = Duration::from_secs(60);
+/// How long a stopped server process gets to exit on its own before it is killed, unless
+/// set in milliseconds with `ARCHESTRA_MCP_SHUTDOWN_GRACE_PERIOD_MS`
+const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+const SHUTDOWN_GRACE_PERIOD_ENV: &str = "ARCHESTRA_MCP_SHUTDOWN_GRACE_PERIOD_MS";
+const DEFAULT_IDLE_TIMEOUT_MINUTES: u64 = 10;
+/// Error of a forwarded request that its client cancelled
+pub const REQUEST_CANCELLED: &str = "Request cancelled";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlexibleJsonRpcRequest {
@@ -33,12 +71,432 @@ pub enum ServerType {
         url: String,
         headers: HashMap<String, String>,
     },
+    /// A server speaking the legacy HTTP+SSE transport
+    Sse {
+        url: String,
+        headers: HashMap<String, String>,
+    },
 }
 
+/// A request forwarded to a stdio server that is waiting for its response
 #[derive(Debug)]
-pub struct ResponseEntry {
-    pub content: String,
-    pub timestamp: Instant,
+pub struct PendingRequest {
+    /// The client session the request came from: progress and the server's own requests
+    /// made while working on it go to this session, and only it may cancel the request
+    pub session_id: Option<String>,
+    pub client_id: serde_json::Value,
+    pub response_tx: oneshot::Sender<Result<String, String>>,
+    /// The progress token the client asked for, replaced upstream by the upstream id
+    pub progress_token: Option<serde_json::Value>,
+}
+
+/// What a router knows about a client session, moved along when the session is moved
+/// to another process
+#[derive(Debug, Default)]
+pub struct ClientSession {
+    stream: Option<mpsc::UnboundedSender<String>>,
+    capabilities: Option<serde_json::Value>,
+}
+
+/// Maps client JSON-RPC ids onto unique upstream ids, so that several clients
+/// (each numbering their requests from 1) can share one stdio server, and routes
+/// what the server sends on its own to the client sessions it is meant for
+#[derive(Debug, Default)]
+pub struct RequestRouter {
+    next_upstream_id: u64,
+    pending: HashMap<u64, PendingRequest>,
+    // Open message streams of client sessions, by MCP session id
+    sessions: HashMap<String, mpsc::UnboundedSender<String>>,
+    // Server-to-client requests waiting for an answer, by the (serialized) id the server used
+    server_requests: HashMap<String, String>,
+    // What client sessions declared they can answer in their initialize, by MCP session id
+    client_capabilities: HashMap<String, serde_json::Value>,
+}
+
+impl RequestRouter {
+    /// Allocate a unique upstream id for a client request and wait for its response
+    pub fn register(
+        &mut self,
+        session_id: Option<String>,
+        client_id: serde_json::Value,
+        response_tx: oneshot::Sender<Result<String, String>>,
+    ) -> u64 {
+        self.next_upstream_id += 1;
+        let upstream_id = self.next_upstream_id;
+        self.pending.insert(
+            upstream_id,
+            PendingRequest {
+                session_id,
+                client_id,
+                response_tx,
+                progress_token: None,
+            },
+        );
+        upstream_id
+    }
+
+    /// Remember the client's progress token of a pending request, so that progress
+    /// notifications sent for its upstream id can be routed back
+    pub fn track_progress(&mut self, upstream_id: u64, progress_token: serde_json::Value) {
+        if let Some(pending) = self.pending.get_mut(&upstream_id) {
+            pending.progress_token = Some(progress_token);
+        }
+    }
+
+    /// Stop waiting for the response to an upstream id, returning who was waiting for it
+    pub fn take(&mut self, upstream_id: u64) -> Option<PendingRequest> {
+        self.pending.remove(&upstream_id)
+    }
+
+    /// Stop waiting for a request a client cancelled, returning its upstream id if the
+    /// server is still working on it
+    pub fn cancel(
+        &mut self,
+        session_id: Option<&str>,
+        client_id: &serde_json::Value,
+    ) -> Option<u64> {
+        let upstream_id = self.pending.iter().find_map(|(upstream_id, pending)| {
+            (pending.session_id.as_deref() == session_id && &pending.client_id == client_id)
+                .then_some(*upstream_id)
+        })?;
+        if let Some(pending) = self.pending.remove(&upstream_id) {
+            let _ = pending.response_tx.send(Err(REQUEST_CANCELLED.to_string()));
+        }
+        Some(upstream_id)
+    }
+
+    /// Whether a client is still waiting on the server, the gateway's own requests don't count
+    pub fn has_pending_client_requests(&self) -> bool {
+        self.pending
+            .values()
+            .any(|pending| pending.session_id.is_some() || !pending.client_id.is_null())
+    }
+
+    /// Fail all in-flight requests, e.g. when the server process goes away
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.server_requests.clear();
+    }
+
+    /// Deliver the server's messages for a client session to this stream from now on
+    pub fn open_session(&mut self, session_id: String) -> mpsc::UnboundedReceiver<String> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.sessions.insert(session_id, tx);
+        rx
+    }
+
+    /// Forget a client session, handing back its stream and declared capabilities
+    pub fn close_session(&mut self, session_id: &str) -> ClientSession {
+        self.server_requests
+            .retain(|_, session| session.as_str() != session_id);
+        ClientSession {
+            stream: self.sessions.remove(session_id),
+            capabilities: self.client_capabilities.remove(session_id),
+        }
+    }
+
+    /// Take over a client session another router handed back with `close_session`
+    pub fn adopt_session(&mut self, session_id: &str, session: ClientSession) {
+        if let Some(stream) = session.stream {
+            self.sessions.insert(session_id.to_string(), stream);
+        }
+        if let Some(capabilities) = session.capabilities {
+            self.client_capabilities
+                .insert(session_id.to_string(), capabilities);
+        }
+    }
+
+    /// Remember the capabilities a client session declared in its initialize
+    pub fn set_client_capabilities(&mut self, session_id: String, capabilities: serde_json::Value) {
+        self.client_capabilities.insert(session_id, capabilities);
+    }
+
+    /// Route a notification or request the server sent on its own to client sessions,
+    /// returns false for a request that no client session can answer
+    pub fn route_server_message(&mut self, mut message: serde_json::Value) -> bool {
+        let method = message
+            .get("method")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        // Requests go to a single client that declared it can answer them, the one whose
+        // request the server is most likely working on, or else whichever client
+        // connected last
+        if let Some(id) = message.get("id") {
+            let id = id.to_string();
+            let mut in_flight: Vec<(u64, String)> = self
+                .pending
+                .iter()
+                .filter_map(|(upstream_id, pending)| {
+                    pending
+                        .session_id
+                        .clone()
+                        .map(|session| (*upstream_id, session))
+                })
+                .collect();
+            in_flight.sort_unstable_by_key(|(upstream_id, _)| std::cmp::Reverse(*upstream_id));
+            let capability = required_client_capability(&method);
+            let candidates: Vec<String> = in_flight
+                .into_iter()
+                .map(|(_, session)| session)
+                .chain(self.sessions.keys().cloned())
+                .filter(|session| {
+                    capability.is_none_or(|capability| {
+                        self.client_capabilities
+                            .get(session)
+                            .is_some_and(|capabilities| capabilities.get(capability).is_some())
+                    })
+                })
+                .collect();
+
+            for session in candidates {
+                if self.send_to_session(&session, &message) {
+                    self.server_requests.insert(id, session);
+                    return true;
+                }
+            }
+            return false;
+        }
+
+        match method.as_str() {
+            // Progress belongs to the request that asked for it
+            "notifications/progress" => {
+                let Some(upstream_id) = message
+                    .pointer("/params/progressToken")
+                    .and_then(|token| token.as_u64())
+                else {
+                    return true;
+                };
+                let Some(pending) = self.pending.get(&upstream_id) else {
+                    return true;
+                };
+                if let (Some(session), Some(token)) =
+                    (pending.session_id.clone(), pending.progress_token.clone())
+                {
+                    message["params"]["progressToken"] = token;
+                    self.send_to_session(&session, &message);
+                }
+            }
+            // The server gave up on one of its own requests
+            "notifications/cancelled" => {
+                let session = message
+                    .pointer("/params/requestId")
+                    .and_then(|id| self.server_requests.remove(&id.to_string()));
+                if let Some(session) = session {
+                    self.send_to_session(&session, &message);
+                }
+            }
+            // Logging, list changes and the like concern every client
+            _ => {
+                let sessions: Vec<String> = self.sessions.keys().cloned().collect();
+                for session in sessions {
+                    self.send_to_session(&session, &message);
+                }
+            }
+        }
+        true
+    }
+
+    /// Accept a client's answer to a server-to-client request, returns false if the
+    /// server isn't waiting for an answer from this session
+    pub fn complete_server_request(
+        &mut self,
+        session_id: Option<&str>,
+        id: &serde_json::Value,
+    ) -> bool {
+        let key = id.to_string();
+        match self.server_requests.get(&key) {
+            Some(session) if Some(session.as_str()) == session_id => {
+                self.server_requests.remove(&key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn send_to_session(&mut self, session_id: &str, message: &serde_json::Value) -> bool {
+        let Some(stream) = self.sessions.get(session_id) else {
+            return false;
+        };
+        if stream.send(message.to_string()).is_ok() {
+            return true;
+        }
+        // The client went away
+        self.sessions.remove(session_id);
+        false
+    }
+}
+
+pub type SharedRequestRouter = Arc<TokioMutex<RequestRouter>>;
+
+/// The client capability a server-to-client request needs, None if every client answers it
+fn required_client_capability(method: &str) -> Option<&'static str> {
+    match method {
+        "sampling/createMessage" => Some("sampling"),
+        "roots/list" => Some("roots"),
+        "elicitation/create" => Some("elicitation"),
+        _ => None,
+    }
+}
+
+/// Lifecycle state of a managed MCP server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(as = MCPServerState)]
+pub enum ServerState {
+    Running,
+    Restarting,
+    Crashed,
+    Stopped,
+    /// Lazily activated and not running, started by the next request
+    Idle,
+}
+
+/// Why a server process stopped running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(as = MCPServerTerminationReason)]
+pub enum TerminationReason {
+    /// Exited or died on its own
+    Exited,
+    /// Could not be spawned again
+    SpawnFailed,
+    MemoryLimit,
+    CpuLimit,
+    ProcessLimit,
+    /// Killed after running for its maximum lifetime
+    LifetimeLimit,
+}
+
+impl TerminationReason {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Exited => "exited unexpectedly",
+            Self::SpawnFailed => "failed to spawn",
+            Self::MemoryLimit => "hit its memory limit",
+            Self::CpuLimit => "hit its CPU time limit",
+            Self::ProcessLimit => "hit its process count limit",
+            Self::LifetimeLimit => "reached its maximum lifetime",
+        }
+    }
+}
+
+/// How a server process last exited
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(as = MCPServerExitInfo)]
+pub struct ExitInfo {
+    pub termination_reason: TerminationReason,
+    pub exit_code: Option<i32>,
+    /// The last lines the process wrote to stderr before exiting
+    pub stderr_tail: Vec<String>,
+    #[schema(value_type = String, format = DateTime)]
+    pub exited_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Whether a server answers its health check pings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(as = MCPServerHealthState)]
+pub enum HealthState {
+    /// Not answered a ping yet
+    Starting,
+    Healthy,
+    /// Answering slowly, or a few pings in a row went unanswered
+    Degraded,
+    Unresponsive,
+    /// No server process is running
+    Stopped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[schema(as = MCPServerHealth)]
+pub struct ServerHealth {
+    pub state: HealthState,
+    /// Round trip of the last answered ping
+    pub latency_ms: Option<u64>,
+    pub consecutive_failures: u32,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub last_checked_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ServerHealth {
+    fn with_state(state: HealthState) -> Self {
+        Self {
+            state,
+            latency_ms: None,
+            consecutive_failures: 0,
+            last_checked_at: None,
+        }
+    }
+
+    /// Record the outcome of a ping, `None` if it went unanswered
+    fn record_ping(&mut self, latency: Option<Duration>, config: &HealthCheckConfig) {
+        self.last_checked_at = Some(chrono::Utc::now());
+
+        match latency {
+            Some(latency) => {
+                let latency_ms = latency.as_millis() as u64;
+                self.latency_ms = Some(latency_ms);
+                self.consecutive_failures = 0;
+                self.state = if latency_ms > config.degraded_latency_ms {
+                    HealthState::Degraded
+                } else {
+                    HealthState::Healthy
+                };
+            }
+            None => {
+                self.consecutive_failures += 1;
+                if self.consecutive_failures >= config.unresponsive_after {
+                    self.state = HealthState::Unresponsive;
+                } else if self.state != HealthState::Starting {
+                    // Slow starters (e.g. npx installing a package) stay starting for a while
+                    self.state = HealthState::Degraded;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(as = MCPServerStatus)]
+pub struct ServerStatus {
+    pub state: ServerState,
+    pub restart_count: u32,
+    pub last_exit: Option<ExitInfo>,
+    pub health: ServerHealth,
+    /// Parts of the configured sandbox this system can't enforce, the server runs less
+    /// isolated than configured unless this is empty
+    pub sandbox_warnings: Vec<String>,
+}
+
+impl ServerStatus {
+    fn running() -> Self {
+        Self {
+            state: ServerState::Running,
+            restart_count: 0,
+            last_exit: None,
+            health: ServerHealth::with_state(HealthState::Starting),
+            sandbox_warnings: Vec::new(),
+        }
+    }
+
+    pub fn stopped() -> Self {
+        Self {
+            state: ServerState::Stopped,
+            restart_count: 0,
+            last_exit: None,
+            health: ServerHealth::with_state(HealthState::Stopped),
+            sandbox_warnings: Vec::new(),
+        }
+    }
+
+    pub fn idle() -> Self {
+        Self {
+            state: ServerState::Idle,
+            ..Self::stopped()
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -49,17 +507,153 @@ pub struct MCPServer {
     pub server_type: ServerType,
     pub tools: Vec<MCPTool>,
     pub resources: Vec<MCPResource>,
+    pub prompts: Vec<MCPPrompt>,
+    /// What the server answered to the gateway's initialize request
+    pub server_info: Option<InitializeResult>,
     pub stdin_tx: Option<mpsc::Sender<String>>,
-    pub response_buffer: Arc<TokioMutex<VecDeque<ResponseEntry>>>,
-    pub process_handle: Option<Arc<TokioMutex<Child>>>,
-    pub is_running: bool,
+    pub request_router: SharedRequestRouter,
+    pub status: ServerStatus,
+    pub shutdown: Option<CancellationToken>,
+    pub supervisor: Option<JoinHandle<()>>,
     pub last_health_check: Instant,
+    /// When a client last sent the server something
+    pub last_activity: Instant,
+    /// Flips to true once the gateway's handshake with the server completed and its
+    /// lists are cached
+    pub ready: watch::Sender<bool>,
+    /// Processes beyond the first one when the server runs with `replicas`
+    pub replicas: Vec<Replica>,
+    /// Which process each client session is pinned to
+    pub session_replicas: HashMap<String, usize>,
+    /// Limits for requests forwarded on behalf of clients
+    pub timeouts: TimeoutConfig,
+}
+
+/// An extra process of a server running with `replicas`. The server's status, health
+/// checks and capability cache follow its first process.
+#[derive(Debug)]
+pub struct Replica {
+    pub stdin_tx: Option<mpsc::Sender<String>>,
+    pub request_router: SharedRequestRouter,
+    pub supervisor: Option<JoinHandle<()>>,
+}
+
+impl MCPServer {
+    /// The stdin channel and router of one of the server's processes, 0 being the first
+    fn process_channel(
+        &self,
+        replica: usize,
+    ) -> (Option<mpsc::Sender<String>>, SharedRequestRouter) {
+        match replica.checked_sub(1).and_then(|i| self.replicas.get(i)) {
+            Some(replica) => (replica.stdin_tx.clone(), replica.request_router.clone()),
+            None => (self.stdin_tx.clone(), self.request_router.clone()),
+        }
+    }
+
+    fn set_stdin_tx(&mut self, replica: usize, stdin_tx: Option<mpsc::Sender<String>>) {
+        match replica
+            .checked_sub(1)
+            .and_then(|i| self.replicas.get_mut(i))
+        {
+            Some(replica) => replica.stdin_tx = stdin_tx,
+            None => self.stdin_tx = stdin_tx,
+        }
+    }
+
+    fn request_routers(&self) -> impl Iterator<Item = &SharedRequestRouter> {
+        std::iter::once(&self.request_router)
+            .chain(self.replicas.iter().map(|replica| &replica.request_router))
+    }
+
+    /// Let the sessions pinned to a process that went away move to another one
+    fn unpin_sessions(&mut self, replica: usize) {
+        self.session_replicas.retain(|_, pinned| *pinned != replica);
+    }
+
+    /// The stdin channels and routers of all of the server's processes, 0 being the first
+    fn process_channels(&self) -> Vec<(Option<mpsc::Sender<String>>, SharedRequestRouter)> {
+        (0..=self.replicas.len())
+            .map(|replica| self.process_channel(replica))
+            .collect()
+    }
+}
+
+type ServerMap = Arc<RwLock<HashMap<String, MCPServer>>>;
+
+/// The tools, resources and prompts a server advertised
+#[derive(Debug, Clone, Default)]
+struct ServerLists {
+    tools: Vec<MCPTool>,
+    resources: Vec<MCPResource>,
+    prompts: Vec<MCPPrompt>,
+}
+
+/// What lazily activated servers that were stopped for idling advertised, by server name
+type IdleLists = Arc<RwLock<HashMap<String, ServerLists>>>;
+
+/// Everything needed to (re)spawn a server process
+#[derive(Clone)]
+struct LaunchSpec {
+    command: String,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    cwd: Option<PathBuf>,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    sandbox: Option<SandboxConfig>,
+    resource_limits: Option<ResourceLimits>,
+    // The command is a container runtime, running the server in a container of its own
+    containerized: bool,
+    logs: Arc<ServerLogSink>,
+    // Which of the server's processes this is, 0 for the first one
+    replica: usize,
+}
+
+/// A freshly spawned server process with its I/O tasks running
+struct SpawnedProcess {
+    child: Child,
+    stdin_tx: mpsc::Sender<String>,
+    // Closes the process's stdin, however many senders are still around
+    close_stdin: CancellationToken,
+    stderr_tail: Arc<TokioMutex<VecDeque<String>>>,
+    // Finishes once everything the process wrote to stderr is in the tail
+    stderr_reader: JoinHandle<()>,
+    // Notifications and requests the server sends on its own
+    messages: mpsc::UnboundedReceiver<ServerMessage>,
+    sandbox_warnings: Vec<String>,
+    // Enforces the resource limits and tells which one the process ran into
+    #[cfg(target_os = "linux")]
+    applied_limits: Option<resource_limits::AppliedLimits>,
+    // However the process goes away, its container goes with it: killing the runtime's
+    // client process leaves the container running
+    _container: Option<ContainerGuard>,
+}
+
+impl SpawnedProcess {
+    /// The last lines of stderr, read once the exited process's output is drained
+    async fn stderr_tail(&mut self) -> Vec<String> {
+        let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, &mut self.stderr_reader).await;
+        self.stderr_tail.lock().await.iter().cloned().collect()
+    }
 }
 
 /// Manages MCP server processes and their lifecycle
 pub struct MCPServerManager {
-    servers: Arc<RwLock<HashMap<String, MCPServer>>>,
+    servers: ServerMap,
+    // Kept separately from `servers` so output outlives stopped and failed servers
+    logs: RwLock<HashMap<String, Arc<ServerLogSink>>>,
+    log_db: OnceCell<DatabaseConnection>,
+    // Where server processes get their own data directories, none in tests
+    data_root: OnceCell<PathBuf>,
     http_client: reqwest::Client,
+    // Configs of lazily activated servers, started by their first request
+    lazy_servers: RwLock<HashMap<String, ServerConfig>>,
+    // Served until a lazy server stopped for idling starts again
+    idle_lists: IdleLists,
+    // Held while a server is being started, so that a lazy server's first requests and
+    // API calls racing each other start it once, by server name
+    starts: std::sync::Mutex<HashMap<String, Arc<TokioMutex<()>>>>,
+    // How long stopped servers get to exit on their own
+    shutdown_grace_period: Duration,
 }
 
 impl Default for MCPServerManager {
@@ -70,242 +664,573 @@ impl Default for MCPServerManager {
 
 impl MCPServerManager {
     pub fn new() -> Self {
+        // No overall timeout, responses may be streamed for as long as a request runs
         let http_client = reqwest::Client::builder()
-            .timeout(REQUEST_TIMEOUT)
+            .connect_timeout(REQUEST_TIMEOUT)
             .build()
             .unwrap_or_default();
 
         Self {
             servers: Arc::new(RwLock::new(HashMap::new())),
+            logs: RwLock::new(HashMap::new()),
+            log_db: OnceCell::new(),
+            data_root: OnceCell::new(),
             http_client,
+            lazy_servers: RwLock::new(HashMap::new()),
+            idle_lists: Arc::new(RwLock::new(HashMap::new())),
+            starts: std::sync::Mutex::new(HashMap::new()),
+            shutdown_grace_period: parse_shutdown_grace_period(
+                std::env::var(SHUTDOWN_GRACE_PERIOD_ENV).ok().as_deref(),
+            ),
+        }
+    }
+
+    /// Start a server now or, if it's activated lazily, once a client sends it a request
+    pub async fn activate_server(&self, name: String, config: ServerConfig) -> Result<(), String> {
+        if config.activation == Activation::Eager {
+            return self.start_server(name, config).await;
+        }
+
+        println!("💤 MCP [{name}] Will start on its first request");
+        self.lazy_servers.write().await.insert(name, config);
+        Ok(())
+    }
+
+    /// Start a lazily activated server that isn't running and wait for its handshake.
+    /// Does nothing for servers that aren't activated lazily.
+    async fn ensure_started(&self, server_name: &str) -> Result<(), String> {
+        let Some(config) = self.lazy_servers.read().await.get(server_name).cloned() else {
+            return Ok(());
+        };
+
+        // A running server only has to finish its handshake, however long other starts take
+        if let Some(mut ready) = self.running_server_ready(server_name).await {
+            return wait_for_ready(server_name, &mut ready).await;
+        }
+
+        let start_lock = self.start_lock(server_name);
+        let mut ready = {
+            let _starting = start_lock.lock().await;
+            match self.running_server_ready(server_name).await {
+                Some(ready) => ready,
+                None => {
+                    println!("⏰ MCP [{server_name}] Starting on first request");
+                    self.start_server_locked(server_name.to_string(), config)
+                        .await?;
+                    self.servers
+                        .read()
+                        .await
+                        .get(server_name)
+                        .map(|server| server.ready.subscribe())
+                        .ok_or_else(|| format!("Server '{server_name}' stopped while starting"))?
+                }
+            }
+        };
+
+        wait_for_ready(server_name, &mut ready).await
+    }
+
+    /// What a server had when it was stopped for idling, served until its handshake
+    /// fetched its lists again
+    async fn take_idle_lists(&self, server_name: &str) -> ServerLists {
+        self.idle_lists
+            .write()
+            .await
+            .remove(server_name)
+            .unwrap_or_default()
+    }
+
+    /// The lock held while a server is being started
+    fn start_lock(&self, server_name: &str) -> Arc<TokioMutex<()>> {
+        self.starts
+            .lock()
+            .unwrap()
+            .entry(server_name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Whether a server is running (or restarting), watching it finish its handshake
+    async fn running_server_ready(&self, server_name: &str) -> Option<watch::Receiver<bool>> {
+        self.servers
+            .read()
+            .await
+            .get(server_name)
+            .filter(|server| server.status.state != ServerState::Crashed)
+            .map(|server| server.ready.subscribe())
+    }
+
+    /// Wait for the gateway's own handshake with a server, returning the server's answer
+    async fn wait_until_initialized(&self, server_name: &str) -> Result<InitializeResult, String> {
+        let mut ready = self
+            .servers
+            .read()
+            .await
+            .get(server_name)
+            .map(|server| server.ready.subscribe())
+            .ok_or_else(|| format!("Server '{server_name}' not found"))?;
+        wait_for_ready(server_name, &mut ready).await?;
+        self.servers
+            .read()
+            .await
+            .get(server_name)
+            .and_then(|server| server.server_info.clone())
+            .ok_or_else(|| {
+                format!("Server '{server_name}' stopped before it finished initializing")
+            })
+    }
+
+    /// Stop a lazily activated server once it had no requests for `idle_timeout`
+    async fn spawn_idle_watcher(&self, name: &str, idle_timeout: Duration) {
+        let shutdown = {
+            let servers = self.servers.read().await;
+            servers.get(name).and_then(|server| server.shutdown.clone())
+        };
+        if let Some(shutdown) = shutdown {
+            tokio::spawn(stop_when_idle(
+                self.servers.clone(),
+                self.idle_lists.clone(),
+                name.to_string(),
+                idle_timeout,
+                self.shutdown_grace_period,
+                shutdown,
+            ));
         }
     }
 
     /// Start an MCP server
-    pub async fn start_server(
-        &self,
-        name: String,
-        command: String,
-        args: Vec<String>,
-        env: Option<HashMap<String, String>>,
-    ) -> Result<(), String> {
-        // Check if server already exists
+    pub async fn start_server(&self, name: String, config: ServerConfig) -> Result<(), String> {
+        // A start that is already under way finishes first, this one then finds it running
+        let start_lock = self.start_lock(&name);
+        let _starting = start_lock.lock().await;
+        self.start_server_locked(name, config).await
+    }
+
+    /// Start an MCP server while holding its start lock
+    async fn start_server_locked(&self, name: String, config: ServerConfig) -> Result<(), String> {
+        // Check if server already exists (a crashed server may be started again)
         {
             let servers = self.servers.read().await;
             if let Some(existing) = servers.get(&name) {
-                if existing.is_running {
+                if existing.status.state != ServerState::Crashed {
                     return Err(format!("MCP server '{name}' is already running"));
                 }
             }
         }
 
-        // Handle special case for npx commands
-        let (actual_command, actual_args) = if command == "npx" {
-            let node_info = node::detect_node_installation();
+        // Remember lazy servers, so they can start again after being stopped for idling
+        let idle_timeout = if config.activation == Activation::Lazy {
+            self.lazy_servers
+                .write()
+                .await
+                .insert(name.clone(), config.clone());
+            let minutes = config
+                .idle_timeout_minutes
+                .unwrap_or(DEFAULT_IDLE_TIMEOUT_MINUTES);
+            Some(Duration::from_secs(minutes * 60))
+        } else {
+            None
+        };
 
-            if !node_info.is_available() {
-                let instructions = node::get_node_installation_instructions();
-                return Err(format!("Cannot start MCP server '{name}': {instructions}"));
+        let ServerConfig {
+            transport,
+            command,
+            args,
+            env,
+            restart_policy,
+            sandbox,
+            resource_limits,
+            log_retention_days,
+            health_check,
+            replicas,
+            timeouts,
+            cwd,
+            run_install_scripts,
+            container,
+            ..
+        } = config;
+        let health_check = health_check.unwrap_or_default();
+        let timeouts = timeouts.unwrap_or_default();
+
+        let logs = self.log_sink(&name).await;
+        logs.set_persistence(
+            log_retention_days.and_then(|days| self.log_db.get().map(|db| (db.clone(), days))),
+        );
+
+        // Secret references are resolved only now, so the values never end up in the config
+        let (args, env) = match resolve_secret_references(args, env) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                let e = format!("Failed to resolve secrets of MCP server '{name}': {e}");
+                logs.push(LogStream::System, &e);
+                return Err(e);
             }
+        };
 
-            if args.is_empty() {
-                return Err(format!(
-                    "No package specified for npx command in server '{name}'"
-                ));
+        // Handle remote MCP servers, reached at the URL in the first argument
+        if command == "http" || transport == "http" || transport == "sse" {
+            let Some(url) = args.into_iter().next() else {
+                return Err(format!("No URL specified for HTTP MCP server '{name}'"));
+            };
+            let server_type = if transport == "sse" {
+                ServerType::Sse { url, headers: env }
+            } else {
+                ServerType::Http { url, headers: env }
+            };
+            self.start_remote_mcp_server(name.clone(), server_type, health_check, timeouts)
+                .await?;
+            if let Some(idle_timeout) = idle_timeout {
+                self.spawn_idle_watcher(&name, idle_timeout).await;
             }
+            return Ok(());
+        }
 
-            let package_name = &args[0];
-            let remaining_args = args[1..].to_vec();
+        // Give the process a directory of its own for HOME, temp files and the npm cache
+        let data_dir = self
+            .data_root
+            .get()
+            .map(|data_root| ServerDataDir::new(data_root, &name));
+        if let Some(data_dir) = &data_dir {
+            if let Err(e) = data_dir.create() {
+                logs.push(LogStream::System, &e);
+                return Err(e);
+            }
+        }
 
-            match node::get_npm_execution_command(package_name, &node_info) {
-                Ok((cmd, cmd_args)) => {
-                    let mut all_args = cmd_args;
-                    all_args.extend(remaining_args);
-                    (cmd, all_args)
+        let containerized = transport == "docker";
+        let (actual_command, actual_args, process_env, cwd, sandbox) = if containerized {
+            // The container is the sandbox, and has a HOME and temp directory of its own
+            let launch = container::detect_runtime(&command).and_then(|runtime| {
+                let config = container
+                    .ok_or_else(|| format!("No container settings for MCP server '{name}'"))?;
+                let run_args = container::run_args(
+                    &config,
+                    &env,
+                    args,
+                    resource_limits.as_ref(),
+                    data_dir.as_ref(),
+                )?;
+                Ok((runtime, run_args))
+            });
+            match launch {
+                Ok((runtime, run_args)) => (runtime, run_args, env, None, None),
+                Err(e) => {
+                    let e = format!("Cannot start MCP server '{name}': {e}");
+                    logs.push(LogStream::System, &e);
+                    return Err(e);
                 }
-                Err(e) => return Err(format!("Failed to prepare npm execution for '{name}': {e}")),
             }
-        } else if command == "http" {
-            // Handle HTTP-based MCP server
-            return self.start_http_mcp_server(name, args, env).await;
         } else {
-            (command.clone(), args.clone())
+            // Installing a package or creating a virtual environment takes a while
+            let resolved = {
+                let server_name = name.clone();
+                let data_dir = data_dir.clone();
+                tokio::task::spawn_blocking(move || {
+                    let npm_prefix = data_dir
+                        .as_ref()
+                        .map(|data_dir| npm_prefix(data_dir, run_install_scripts));
+                    resolve_command(
+                        &server_name,
+                        command,
+                        args,
+                        data_dir.as_ref(),
+                        npm_prefix.as_ref(),
+                    )
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("Failed to resolve the command of '{name}': {e}")))
+            };
+            let (actual_command, actual_args) = match resolved {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    logs.push(LogStream::System, &e);
+                    return Err(e);
+                }
+            };
+            let mut process_env = data_dir
+                .as_ref()
+                .map(ServerDataDir::env)
+                .unwrap_or_default();
+            // The configured environment wins, e.g. to use the user's own npm cache after all
+            process_env.extend(env);
+
+            let cwd = match &data_dir {
+                Some(data_dir) => match data_dir.working_dir(cwd.as_deref()) {
+                    Ok(cwd) => Some(cwd),
+                    Err(e) => {
+                        logs.push(LogStream::System, &e);
+                        return Err(e);
+                    }
+                },
+                None => cwd.map(PathBuf::from),
+            };
+            if let Some(cwd) = cwd.as_ref().filter(|cwd| !cwd.is_dir()) {
+                let e = format!("Working directory {} does not exist", cwd.display());
+                logs.push(LogStream::System, &e);
+                return Err(e);
+            }
+
+            // A sandboxed server still has to get at its own directories
+            let sandbox = sandbox.map(|mut sandbox| {
+                if let Some(data_dir) = &data_dir {
+                    let root = data_dir.root().to_string_lossy().to_string();
+                    sandbox.write_paths.push(root);
+                }
+                if let Some(cwd) = &cwd {
+                    sandbox.read_paths.push(cwd.to_string_lossy().to_string());
+                }
+                sandbox
+            });
+
+            (actual_command, actual_args, process_env, cwd, sandbox)
         };
 
-        let env_vars = env.unwrap_or_default();
         println!(
             "🚀 MCP [{}] Starting: {} {}",
             name,
             actual_command,
-            actual_args.join(" ")
-        );
-
-        // Start the process with sandbox-exec for security (macOS only)
-        let mut cmd = if cfg!(target_os = "macos") {
-            let mut sandbox_cmd = Command::new("sandbox-exec");
-            sandbox_cmd
-                .arg("-f")
-                .arg("./sandbox-exec-profiles/mcp-server-everything-for-now.sb")
-                .arg(&actual_command)
-                .args(&actual_args)
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .kill_on_drop(true);
-            sandbox_cmd
-        } else {
-            let mut regular_cmd = Command::new(&actual_command);
-            regular_cmd
-                .args(&actual_args)
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .kill_on_drop(true);
-            regular_cmd
-        };
-
-        // Set environment variables
-        for (key, value) in env_vars {
-            cmd.env(key, value);
-        }
-
-        let mut child = cmd
-            .spawn()
-            .map_err(|e| format!("Failed to spawn MCP server process: {e}"))?;
-
-        let stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| "Failed to get stdin handle".to_string())?;
-
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| "Failed to get stdout handle".to_string())?;
-
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| "Failed to get stderr handle".to_string())?;
-
-        let (stdin_tx, mut stdin_rx) = mpsc::channel::<String>(CHANNEL_CAPACITY);
-        let response_buffer = Arc::new(TokioMutex::new(VecDeque::new()));
-        let process_handle = Arc::new(TokioMutex::new(child));
-
-        // Start stdin writer task
-        let _stdin_writer_handle = tokio::spawn(async move {
-            let mut stdin = stdin;
-            while let Some(message) = stdin_rx.recv().await {
-                if let Err(e) = stdin.write_all(message.as_bytes()).await {
-                    eprintln!("Failed to write to stdin: {e}");
-                    break;
-                }
-                if let Err(e) = stdin.flush().await {
-                    eprintln!("Failed to flush stdin: {e}");
-                    break;
-                }
-            }
-        });
-
-        // Start stdout reader task
-        let buffer_clone = response_buffer.clone();
-        let server_name_clone = name.clone();
-        let _stdout_handle = tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-
-            while let Ok(Some(line)) = lines.next_line().await {
-                // Only log non-JSON responses or errors for debugging
-                if !line.trim_start().starts_with('{') {
-                    println!("MCP [{server_name_clone}] {line}");
-                }
+            secret::redact(&actual_args.join(" "))
+        );
 
-                let mut buffer = buffer_clone.lock().await;
-                if buffer.len() >= MAX_BUFFER_SIZE {
-                    buffer.pop_front();
-                }
-                buffer.push_back(ResponseEntry {
-                    content: line,
-                    timestamp: Instant::now(),
-                });
+        let spec = LaunchSpec {
+            command: actual_command,
+            args: actual_args,
+            env: process_env,
+            cwd,
+            sandbox,
+            resource_limits,
+            containerized,
+            logs: logs.clone(),
+            replica: 0,
+        };
+        let request_router = SharedRequestRouter::default();
+        let process = match spawn_server_process(&name, &spec, &request_router).await {
+            Ok(process) => process,
+            Err(e) => {
+                logs.push(LogStream::System, &e);
+                return Err(e);
             }
-        });
-
-        // Start stderr reader task
-        let server_name_clone2 = name.clone();
-        let _stderr_handle = tokio::spawn(async move {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
+        };
 
-            while let Ok(Some(line)) = lines.next_line().await {
-                eprintln!("⚠️ MCP [{server_name_clone2}] {line}");
+        // Processes that were already spawned are killed on drop if a later one fails, and
+        // their containers removed
+        let mut extra_processes = Vec::new();
+        for replica in 1..replicas.unwrap_or(1).max(1) as usize {
+            let spec = LaunchSpec {
+                replica,
+                ..spec.clone()
+            };
+            let request_router = SharedRequestRouter::default();
+            match spawn_server_process(&name, &spec, &request_router).await {
+                Ok(process) => extra_processes.push((spec, process, request_router)),
+                Err(e) => {
+                    logs.push(LogStream::System, &e);
+                    return Err(e);
+                }
             }
-        });
+        }
+        if !extra_processes.is_empty() {
+            println!(
+                "👥 MCP [{name}] Running {} replicas",
+                extra_processes.len() + 1
+            );
+        }
+        let restart_policy = restart_policy.unwrap_or_default();
+        let shutdown = CancellationToken::new();
 
         // Create server instance
+        let ServerLists {
+            tools,
+            resources,
+            prompts,
+        } = self.take_idle_lists(&name).await;
         let server = MCPServer {
             name: name.clone(),
-            command: actual_command,
-            args: actual_args,
+            command: spec.command.clone(),
+            args: spec.args.clone(),
             server_type: ServerType::Process,
-            tools: Vec::new(),
-            resources: Vec::new(),
-            stdin_tx: Some(stdin_tx),
-            response_buffer,
-            process_handle: Some(process_handle),
-            is_running: true,
+            tools,
+            resources,
+            prompts,
+            server_info: None,
+            stdin_tx: Some(process.stdin_tx.clone()),
+            request_router: request_router.clone(),
+            status: ServerStatus {
+                sandbox_warnings: process.sandbox_warnings.clone(),
+                ..ServerStatus::running()
+            },
+            shutdown: Some(shutdown.clone()),
+            supervisor: None,
             last_health_check: Instant::now(),
+            last_activity: Instant::now(),
+            ready: watch::Sender::new(false),
+            replicas: extra_processes
+                .iter()
+                .map(|(_, process, request_router)| Replica {
+                    stdin_tx: Some(process.stdin_tx.clone()),
+                    request_router: request_router.clone(),
+                    supervisor: None,
+                })
+                .collect(),
+            session_replicas: HashMap::new(),
+            timeouts,
         };
 
-        // Store the server
-        {
-            let mut servers = self.servers.write().await;
-            servers.insert(name.clone(), server);
+        // Store the server, then hand the processes over to their supervisors
+        let mut servers = self.servers.write().await;
+        servers.insert(name.clone(), server);
+
+        let supervisor = tokio::spawn(supervise_server_process(
+            self.servers.clone(),
+            name.clone(),
+            spec,
+            process,
+            request_router,
+            shutdown.clone(),
+            restart_policy.clone(),
+        ));
+        let replica_supervisors: Vec<_> = extra_processes
+            .into_iter()
+            .map(|(spec, process, request_router)| {
+                tokio::spawn(supervise_server_process(
+                    self.servers.clone(),
+                    name.clone(),
+                    spec,
+                    process,
+                    request_router,
+                    shutdown.clone(),
+                    restart_policy.clone(),
+                ))
+            })
+            .collect();
+        if let Some(server) = servers.get_mut(&name) {
+            server.supervisor = Some(supervisor);
+            for (replica, supervisor) in server.replicas.iter_mut().zip(replica_supervisors) {
+                replica.supervisor = Some(supervisor);
+            }
+        }
+        drop(servers);
+
+        tokio::spawn(check_server_health(
+            self.servers.clone(),
+            name.clone(),
+            health_check,
+            shutdown,
+        ));
+        if let Some(idle_timeout) = idle_timeout {
+            self.spawn_idle_watcher(&name, idle_timeout).await;
         }
 
         println!("✅ MCP [{name}] Started successfully");
         Ok(())
     }
 
-    /// Start an HTTP-based MCP server
-    async fn start_http_mcp_server(
+    /// Start a remote MCP server, reached over HTTP
+    async fn start_remote_mcp_server(
         &self,
         name: String,
-        args: Vec<String>,
-        env: Option<HashMap<String, String>>,
+        server_type: ServerType,
+        health_check: HealthCheckConfig,
+        timeouts: TimeoutConfig,
     ) -> Result<(), String> {
-        if args.is_empty() {
-            return Err(format!("No URL specified for HTTP MCP server '{name}'"));
-        }
+        let (url, headers, command) = match &server_type {
+            ServerType::Http { url, headers } => (url.clone(), headers.clone(), "http"),
+            ServerType::Sse { url, headers } => (url.clone(), headers.clone(), "sse"),
+            ServerType::Process => unreachable!("process servers are started by start_server"),
+        };
 
-        let url = args[0].clone();
-        let headers = env.unwrap_or_default();
+        println!("🚀 MCP [{name}] Starting {command} server at: {url}");
 
-        println!("🚀 MCP [{name}] Starting HTTP server at: {url}");
+        let logs = self.log_sink(&name).await;
+        let request_router = SharedRequestRouter::default();
+        let shutdown = CancellationToken::new();
+        let (transport, stdin_tx, messages) = match &server_type {
+            ServerType::Sse { .. } => {
+                let (transport, stdin_tx, messages) = SseTransport::start(
+                    &name,
+                    url.clone(),
+                    headers,
+                    self.http_client.clone(),
+                    request_router.clone(),
+                    logs.clone(),
+                    shutdown.clone(),
+                );
+                (RemoteTransport::Sse(transport), stdin_tx, messages)
+            }
+            _ => {
+                let (transport, stdin_tx, messages) = HttpTransport::start(
+                    &name,
+                    url.clone(),
+                    headers,
+                    self.http_client.clone(),
+                    request_router.clone(),
+                    logs.clone(),
+                    shutdown.clone(),
+                );
+                (
+                    RemoteTransport::StreamableHttp(transport),
+                    stdin_tx,
+                    messages,
+                )
+            }
+        };
 
+        let ServerLists {
+            tools,
+            resources,
+            prompts,
+        } = self.take_idle_lists(&name).await;
         let server = MCPServer {
             name: name.clone(),
-            command: "http".to_string(),
-            args: vec![url.clone()],
-            server_type: ServerType::Http {
-                url: url.clone(),
-                headers: headers.clone(),
-            },
-            tools: Vec::new(),
-            resources: Vec::new(),
-            stdin_tx: None,
-            response_buffer: Arc::new(TokioMutex::new(VecDeque::new())),
-            process_handle: None,
-            is_running: true,
+            command: command.to_string(),
+            args: vec![url],
+            server_type,
+            tools,
+            resources,
+            prompts,
+            server_info: None,
+            stdin_tx: Some(stdin_tx.clone()),
+            request_router: request_router.clone(),
+            status: ServerStatus::running(),
+            shutdown: Some(shutdown.clone()),
+            supervisor: None,
             last_health_check: Instant::now(),
+            last_activity: Instant::now(),
+            ready: watch::Sender::new(false),
+            replicas: Vec::new(),
+            session_replicas: HashMap::new(),
+            timeouts,
         };
 
-        {
-            let mut servers = self.servers.write().await;
-            servers.insert(name.clone(), server);
+        let mut servers = self.servers.write().await;
+        servers.insert(name.clone(), server);
+
+        let client = UpstreamClient {
+            server_name: name.clone(),
+            stdin_tx,
+            request_router,
+        };
+        let supervisor = tokio::spawn(supervise_remote_server(
+            self.servers.clone(),
+            client,
+            transport,
+            messages,
+            logs,
+            shutdown.clone(),
+        ));
+        if let Some(server) = servers.get_mut(&name) {
+            server.supervisor = Some(supervisor);
         }
+        drop(servers);
 
-        println!("✅ MCP [{name}] HTTP server started successfully");
+        tokio::spawn(check_server_health(
+            self.servers.clone(),
+            name.clone(),
+            health_check,
+            shutdown,
+        ));
+
+        println!("✅ MCP [{name}] {command} server started successfully");
         Ok(())
     }
 
@@ -313,51 +1238,318 @@ impl MCPServerManager {
     pub async fn stop_server(&self, server_name: &str) -> Result<(), String> {
         println!("🛑 MCP [{server_name}] Stopping server");
 
+        // A stopped lazy server must not be started again by the next request
+        let was_lazy = self
+            .lazy_servers
+            .write()
+            .await
+            .remove(server_name)
+            .is_some();
+        let had_idle_lists = self.idle_lists.write().await.remove(server_name).is_some();
         let server = {
             let mut servers = self.servers.write().await;
             servers.remove(server_name)
         };
+        if server.is_some() || had_idle_lists {
+            CachedList::ALL.into_iter().for_each(notify_list_changed);
+        }
 
-        if let Some(mut server) = server {
-            server.is_running = false;
-
-            // Close stdin channel
-            drop(server.stdin_tx);
-
-            // Kill the process if it exists
-            if let Some(process_handle) = server.process_handle {
-                let mut child = process_handle.lock().await;
-                if let Err(e) = child.kill().await {
-                    eprintln!("⚠️ MCP [{server_name}] Failed to kill process: {e}");
-                }
-            }
-
+        if let Some(server) = server {
+            // Have the supervisor terminate the process and wait until it's gone
+            shut_down_server(server, self.shutdown_grace_period).await;
             println!("✅ MCP [{server_name}] Stopped successfully");
             Ok(())
+        } else if was_lazy || had_idle_lists {
+            println!("✅ MCP [{server_name}] Idle server deactivated");
+            Ok(())
         } else {
             eprintln!("❌ MCP [{server_name}] Server not found");
             Err(format!("MCP server '{server_name}' not found"))
         }
     }
 
-    /// Forward a raw request to a server
-    pub async fn forward_raw_request(
-        &self,
-        server_name: &str,
-        request_body: String,
-    ) -> Result<String, String> {
-        let servers = self.servers.read().await;
+    /// Stop every server at once, e.g. when the app exits. Processes get `grace_period`
+    /// to exit after their stdin is closed and they received SIGTERM, then they are killed.
+    pub async fn shutdown_all(&self, grace_period: Duration) {
+        let servers: Vec<MCPServer> = {
+            let mut servers = self.servers.write().await;
+            servers.drain().map(|(_, server)| server).collect()
+        };
+        if servers.is_empty() {
+            return;
+        }
 
-        let server = servers.get(server_name).ok_or_else(|| {
-            let available = servers.keys().map(|s| s.as_str()).collect::<Vec<_>>();
-            let available_str = if available.is_empty() {
-                "none".to_string()
-            } else {
-                available.join(", ")
-            };
-            eprintln!("❌ MCP [{server_name}] Server not found. Available: [{available_str}]");
-            format!("Server '{server_name}' not found")
-        })?;
+        println!("🛑 Shutting down {} MCP servers", servers.len());
+        futures_util::future::join_all(
+            servers
+                .into_iter()
+                .map(|server| shut_down_server(server, grace_period)),
+        )
+        .await;
+        println!("✅ All MCP servers shut down");
+    }
+
+    /// Get the status of every server known to the manager
+    pub async fn get_server_statuses(&self) -> HashMap<String, ServerStatus> {
+        let mut statuses: HashMap<String, ServerStatus> = self
+            .lazy_servers
+            .read()
+            .await
+            .keys()
+            .map(|name| (name.clone(), ServerStatus::idle()))
+            .collect();
+        let servers = self.servers.read().await;
+        statuses.extend(
+            servers
+                .iter()
+                .map(|(name, server)| (name.clone(), server.status.clone())),
+        );
+        statuses
+    }
+
+    /// Get the status of a single server, including its latest health check
+    pub async fn get_server_status(&self, server_name: &str) -> Option<ServerStatus> {
+        if let Some(server) = self.servers.read().await.get(server_name) {
+            return Some(server.status.clone());
+        }
+        self.lazy_servers
+            .read()
+            .await
+            .contains_key(server_name)
+            .then(ServerStatus::idle)
+    }
+
+    /// Get the lists a server advertised, as cached after its handshake, or as they were
+    /// when it was last stopped for idling. None for lazy servers that never ran.
+    async fn get_cached_lists(&self, server_name: &str) -> Option<ServerLists> {
+        if let Some(server) = self.servers.read().await.get(server_name) {
+            return Some(ServerLists {
+                tools: server.tools.clone(),
+                resources: server.resources.clone(),
+                prompts: server.prompts.clone(),
+            });
+        }
+        self.idle_lists.read().await.get(server_name).cloned()
+    }
+
+    /// Get the tools a server advertised, as cached after its handshake
+    pub async fn get_cached_tools(&self, server_name: &str) -> Option<Vec<MCPTool>> {
+        let lists = self.get_cached_lists(server_name).await?;
+        Some(lists.tools)
+    }
+
+    /// Get the resources a server advertised, as cached after its handshake
+    pub async fn get_cached_resources(&self, server_name: &str) -> Option<Vec<MCPResource>> {
+        let lists = self.get_cached_lists(server_name).await?;
+        Some(lists.resources)
+    }
+
+    /// Get the prompts a server advertised, as cached after its handshake
+    pub async fn get_cached_prompts(&self, server_name: &str) -> Option<Vec<MCPPrompt>> {
+        let lists = self.get_cached_lists(server_name).await?;
+        Some(lists.prompts)
+    }
+
+    /// Get the capabilities a server announced during its handshake
+    pub async fn get_server_capabilities(&self, server_name: &str) -> Option<ServerCapabilities> {
+        let servers = self.servers.read().await;
+        servers
+            .get(server_name)
+            .and_then(|server| server.server_info.as_ref())
+            .map(|info| info.capabilities.clone())
+    }
+
+    /// Open the stream on which a client session receives the notifications and
+    /// requests a server sends on its own
+    pub async fn open_session_stream(
+        &self,
+        server_name: &str,
+        session_id: &str,
+    ) -> Result<mpsc::UnboundedReceiver<String>, String> {
+        // The stream has to come from the process the session's requests go to
+        let (_, request_router) = self.pick_replica(server_name, Some(session_id)).await?;
+        let stream = request_router
+            .lock()
+            .await
+            .open_session(session_id.to_string());
+        Ok(stream)
+    }
+
+    /// Forget a client session that ended, along with the process it was pinned to
+    pub async fn close_session(&self, server_name: &str, session_id: &str) -> Result<(), String> {
+        let request_routers = {
+            let mut servers = self.servers.write().await;
+            let server = servers
+                .get_mut(server_name)
+                .ok_or_else(|| format!("Server '{server_name}' not found"))?;
+            server.session_replicas.remove(session_id);
+            server.request_routers().cloned().collect::<Vec<_>>()
+        };
+
+        for request_router in request_routers {
+            request_router.lock().await.close_session(session_id);
+        }
+        Ok(())
+    }
+
+    /// Pick the process a request goes to. A session stays on the process it started
+    /// on while that one is up, anything else goes to the process with the fewest
+    /// requests in flight. The server map isn't locked while the routers are.
+    async fn pick_replica(
+        &self,
+        server_name: &str,
+        session_id: Option<&str>,
+    ) -> Result<(Option<mpsc::Sender<String>>, SharedRequestRouter), String> {
+        let (mut processes, pinned) = {
+            let servers = self.servers.read().await;
+            let server = servers
+                .get(server_name)
+                .ok_or_else(|| format!("Server '{server_name}' not found"))?;
+            let pinned = session_id.and_then(|id| server.session_replicas.get(id).copied());
+            (server.process_channels(), pinned)
+        };
+        if processes.len() == 1 {
+            return Ok(processes.swap_remove(0));
+        }
+        if let Some(replica) = pinned {
+            if processes
+                .get(replica)
+                .is_some_and(|(stdin_tx, _)| stdin_tx.is_some())
+            {
+                return Ok(processes.swap_remove(replica));
+            }
+        }
+
+        let mut least_busy = None;
+        for (replica, (stdin_tx, request_router)) in processes.iter().enumerate() {
+            // Being restarted
+            if stdin_tx.is_none() {
+                continue;
+            }
+            let outstanding = request_router.lock().await.pending.len();
+            if least_busy.is_none_or(|(_, fewest)| outstanding < fewest) {
+                least_busy = Some((replica, outstanding));
+            }
+        }
+        // All of them are being restarted
+        let Some((replica, _)) = least_busy else {
+            return Ok(processes.swap_remove(0));
+        };
+
+        if let Some(session_id) = session_id {
+            if let Some(server) = self.servers.write().await.get_mut(server_name) {
+                server
+                    .session_replicas
+                    .insert(session_id.to_string(), replica);
+            }
+            // A session whose process went away keeps its stream and capabilities
+            let mut session = ClientSession::default();
+            for (other, (_, request_router)) in processes.iter().enumerate() {
+                if other != replica {
+                    let closed = request_router.lock().await.close_session(session_id);
+                    session.stream = session.stream.or(closed.stream);
+                    session.capabilities = session.capabilities.or(closed.capabilities);
+                }
+            }
+            processes[replica]
+                .1
+                .lock()
+                .await
+                .adopt_session(session_id, session);
+        }
+        Ok(processes.swap_remove(replica))
+    }
+
+    /// Use this database for servers that persist their logs
+    pub fn set_log_database(&self, db: DatabaseConnection) {
+        let _ = self.log_db.set(db);
+    }
+
+    /// Give server processes data directories of their own under this directory
+    pub fn set_data_directory(&self, data_root: PathBuf) {
+        let _ = self.data_root.set(data_root);
+    }
+
+    /// Delete a server's data directory, e.g. once it was uninstalled
+    pub fn remove_server_data(&self, server_name: &str) -> Result<(), String> {
+        match self.data_root.get() {
+            Some(data_root) => ServerDataDir::new(data_root, server_name).remove(),
+            None => Ok(()),
+        }
+    }
+
+    /// Install the newest version of a server's npm package matching the spec and pin it.
+    /// Stop the server first, its package files are replaced.
+    pub async fn upgrade_npm_package(
+        &self,
+        server_name: &str,
+        spec: String,
+        run_install_scripts: bool,
+    ) -> Result<NpmPackagePin, String> {
+        let Some(data_root) = self.data_root.get() else {
+            return Err("MCP servers have no data directory to install packages into".to_string());
+        };
+        let prefix = npm_prefix(
+            &ServerDataDir::new(data_root, server_name),
+            run_install_scripts,
+        );
+        tokio::task::spawn_blocking(move || {
+            let node_info = node::detect_node_installation();
+            node::install_npm_package(&spec, &node_info, &prefix)
+        })
+        .await
+        .map_err(|e| format!("Failed to upgrade the package of '{server_name}': {e}"))?
+    }
+
+    /// Get the log sink of a server, creating it if the server has none yet
+    pub async fn log_sink(&self, server_name: &str) -> Arc<ServerLogSink> {
+        if let Some(sink) = self.logs.read().await.get(server_name) {
+            return sink.clone();
+        }
+
+        let mut logs = self.logs.write().await;
+        logs.entry(server_name.to_string())
+            .or_insert_with(|| Arc::new(ServerLogSink::new(server_name)))
+            .clone()
+    }
+
+    /// Forward a raw request to a server on behalf of a client session, the client
+    /// may ask for a time limit of its own
+    pub async fn forward_raw_request(
+        &self,
+        server_name: &str,
+        session_id: Option<&str>,
+        request_body: String,
+        timeout: Option<Duration>,
+    ) -> Result<String, String> {
+        // A client giving up on one of its requests, no need to start a server for that
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&request_body) {
+            if json.get("method").and_then(|m| m.as_str()) == Some("notifications/cancelled") {
+                return self
+                    .forward_cancellation(server_name, session_id, json)
+                    .await;
+            }
+        }
+
+        self.ensure_started(server_name).await?;
+
+        let (server_info, timeouts) = {
+            let mut servers = self.servers.write().await;
+            let available = servers.keys().cloned().collect::<Vec<_>>();
+
+            let server = servers.get_mut(server_name).ok_or_else(|| {
+                let available_str = if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                };
+                eprintln!("❌ MCP [{server_name}] Server not found. Available: [{available_str}]");
+                format!("Server '{server_name}' not found")
+            })?;
+            server.last_activity = Instant::now();
+            (server.server_info.clone(), server.timeouts.clone())
+        };
+        let (stdin_tx, request_router) = self.pick_replica(server_name, session_id).await?;
 
         // Extract method and ID for clean logging
         let (method, request_id) =
@@ -377,216 +1569,3181 @@ impl MCPServerManager {
 
         println!("📡 MCP [{server_name}] {method} (id: {request_id})");
 
-        match &server.server_type {
-            ServerType::Http { url, headers } => {
-                let mut req = self
-                    .http_client
-                    .post(url)
-                    .body(request_body)
-                    .header("Content-Type", "application/json");
+        // HTTP servers are written to through the same channel as a process's stdin
+        let stdin_tx = stdin_tx.ok_or_else(|| {
+            eprintln!("❌ MCP [{server_name}] No stdin channel available");
+            "No stdin channel available".to_string()
+        })?;
 
-                for (key, value) in headers {
-                    req = req.header(key, value);
+        // A client answering one of the server's own requests, pass it on as is
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&request_body) {
+            if json.get("method").is_none()
+                && (json.get("result").is_some() || json.get("error").is_some())
+            {
+                let id = json.get("id").cloned().unwrap_or_default();
+                if !request_router
+                    .lock()
+                    .await
+                    .complete_server_request(session_id, &id)
+                {
+                    eprintln!("⚠️ MCP [{server_name}] No server request {id} for this session");
+                    return Err(format!("No pending server request with id {id}"));
                 }
+                send_to_stdin(server_name, &stdin_tx, &request_body).await?;
+                println!("↩️ MCP [{server_name}] Relayed client answer (id: {id})");
+                return Ok("".to_string());
+            }
+        }
 
-                let response = req.send().await.map_err(|e| {
-                    eprintln!("❌ MCP [{server_name}] HTTP request failed: {e}");
-                    format!("HTTP request failed: {e}")
-                })?;
-
-                let status = response.status();
-                let response_text = response.text().await.map_err(|e| {
-                    eprintln!("❌ MCP [{server_name}] Failed to read response: {e}");
-                    format!("Failed to read response: {e}")
-                })?;
+        // Parse request using our flexible structure
+        let request: FlexibleJsonRpcRequest = serde_json::from_str(&request_body).map_err(|e| {
+            eprintln!("❌ MCP [{server_name}] Invalid JSON-RPC: {e}");
+            format!("Failed to parse request: {e}")
+        })?;
 
-                if status.is_success() {
-                    println!("✅ MCP [{server_name}] HTTP {method} completed");
-                } else {
-                    eprintln!("⚠️ MCP [{server_name}] HTTP {method} returned {status}");
-                }
-                Ok(response_text)
+        // The gateway runs the handshake with the process itself, so the client's own
+        // handshake is answered from the cache instead of initializing the server again
+        if matches!(
+            request.method.as_str(),
+            "initialize" | "notifications/initialized"
+        ) {
+            let mut info = match server_info {
+                Some(info) => info,
+                None => self.wait_until_initialized(server_name).await?,
+            };
+            if request.method == "notifications/initialized" {
+                return Ok("".to_string());
             }
-            ServerType::Process => {
-                let stdin_tx = server.stdin_tx.as_ref().ok_or_else(|| {
-                    eprintln!("❌ MCP [{server_name}] No stdin channel available");
-                    "No stdin channel available".to_string()
-                })?;
 
-                stdin_tx
-                    .send(format!("{request_body}\n"))
+            // Only sessions that can answer them get the server's sampling, roots and
+            // elicitation requests
+            if let (Some(session_id), Some(capabilities)) = (
+                session_id,
+                request
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.get("capabilities")),
+            ) {
+                request_router
+                    .lock()
                     .await
-                    .map_err(|e| {
-                        eprintln!("❌ MCP [{server_name}] Failed to send to stdin: {e}");
-                        format!("Failed to send request: {e}")
-                    })?;
-
-                // Parse request using our flexible structure
-                let request: FlexibleJsonRpcRequest =
-                    serde_json::from_str(&request_body).map_err(|e| {
-                        eprintln!("❌ MCP [{server_name}] Invalid JSON-RPC: {e}");
-                        format!("Failed to parse request: {e}")
-                    })?;
-
-                // Check if this is a notification (no ID) or a regular request
-                if request.id.is_none() {
-                    println!("📢 MCP [{server_name}] {method} notification sent");
-                    return Ok("".to_string()); // Notifications don't expect responses
-                }
-                // Wait for response with matching ID
-                let start_time = Instant::now();
-                let mut last_status_log = start_time;
-                let mut discarded_count = 0;
-
-                loop {
-                    let elapsed = start_time.elapsed();
-
-                    if elapsed > REQUEST_TIMEOUT {
-                        eprintln!(
-                            "⏰ MCP [{server_name}] {method} (id: {request_id}) timed out after {elapsed:?}"
-                        );
-                        return Err("Request timeout".to_string());
-                    }
+                    .set_client_capabilities(session_id.to_string(), capabilities.clone());
+            }
 
-                    // Log status every 5 seconds instead of every 100 iterations
-                    if elapsed.saturating_sub(last_status_log.elapsed()) >= Duration::from_secs(5) {
-                        let buffer_size = server.response_buffer.lock().await.len();
-                        if buffer_size > 0 || discarded_count > 0 {
-                            println!("⏳ MCP [{server_name}] Waiting for {method} response (buffer: {buffer_size}, discarded: {discarded_count})");
-                        }
-                        last_status_log = Instant::now();
-                    }
+            let requested = request
+                .params
+                .as_ref()
+                .and_then(|params| params.get("protocolVersion"))
+                .and_then(|version| version.as_str());
+            info.protocol_version = negotiate_protocol_version(requested, &info.protocol_version);
+            println!("✅ MCP [{server_name}] initialize answered from cache");
+            return Ok(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "result": info,
+            })
+            .to_string());
+        }
 
-                    let mut buffer = server.response_buffer.lock().await;
-                    if let Some(entry) = buffer.pop_front() {
-                        // Try to parse as generic JSON to check structure
-                        if let Ok(json_value) =
-                            serde_json::from_str::<serde_json::Value>(&entry.content)
-                        {
-                            if let Some(obj) = json_value.as_object() {
-                                // Check if this is a response (has "result" or "error" field and "id")
-                                if (obj.contains_key("result") || obj.contains_key("error"))
-                                    && obj.contains_key("id")
-                                {
-                                    // Compare IDs directly
-                                    let response_id = obj.get("id");
-                                    let ids_match = match (&request.id, response_id) {
-                                        (Some(req_id), Some(resp_id)) => req_id == resp_id,
-                                        _ => false,
-                                    };
-
-                                    if ids_match {
-                                        println!("✅ MCP [{server_name}] {method} completed");
-                                        return Ok(entry.content);
-                                    } else {
-                                        // Silently discard non-matching responses (like init responses)
-                                        discarded_count += 1;
-                                    }
-                                } else {
-                                    // Discard notifications and other non-response messages
-                                    discarded_count += 1;
-                                }
-                            } else {
-                                discarded_count += 1;
-                            }
-                        } else {
-                            discarded_count += 1;
-                        }
-                    }
+        // Notifications (no ID) don't expect responses
+        let Some(id) = request.id else {
+            send_to_stdin(server_name, &stdin_tx, &request_body).await?;
+            println!("📢 MCP [{server_name}] {method} notification sent");
+            return Ok("".to_string());
+        };
 
-                    drop(buffer);
-                    tokio::time::sleep(Duration::from_millis(10)).await;
-                }
+        let tool = request
+            .params
+            .as_ref()
+            .filter(|_| request.method == "tools/call")
+            .and_then(|params| params.get("name"))
+            .and_then(|name| name.as_str());
+        let limit = request_timeout(&timeouts, &request.method, tool, timeout);
+
+        // Give the request an id that is unique across all clients of this server,
+        // registering the waiter before writing so a fast response can't be missed
+        let (response_tx, response_rx) = oneshot::channel();
+        let progress_token = request
+            .params
+            .as_ref()
+            .and_then(|params| params.pointer("/_meta/progressToken"))
+            .cloned();
+        let upstream_id = {
+            let mut router = request_router.lock().await;
+            let upstream_id = router.register(session_id.map(|s| s.to_string()), id, response_tx);
+            if let Some(token) = progress_token.clone() {
+                router.track_progress(upstream_id, token);
+            }
+            upstream_id
+        };
+
+        // Progress tokens are only unique per client too, so they become the upstream id
+        let upstream_body =
+            with_id(&request_body, upstream_id.into()).and_then(|body| match progress_token {
+                Some(_) => with_progress_token(&body, upstream_id.into()),
+                None => Ok(body),
+            });
+        let upstream_body = match upstream_body {
+            Ok(body) => body,
+            Err(e) => {
+                request_router.lock().await.take(upstream_id);
+                return Err(format!("Failed to rewrite request id: {e}"));
+            }
+        };
+
+        if let Err(e) = send_to_stdin(server_name, &stdin_tx, &upstream_body).await {
+            request_router.lock().await.take(upstream_id);
+            return Err(e);
+        }
+
+        // The handler waiting here is dropped when its client disconnects
+        let mut cancel_on_drop = CancelOnDrop {
+            server_name: server_name.to_string(),
+            stdin_tx: stdin_tx.clone(),
+            request_router: request_router.clone(),
+            upstream_id,
+            armed: true,
+        };
+        let response = tokio::time::timeout(limit.duration, response_rx).await;
+        cancel_on_drop.armed = false;
+
+        match response {
+            Ok(Ok(Ok(response))) => {
+                println!("✅ MCP [{server_name}] {method} completed");
+                Ok(response)
+            }
+            Ok(Ok(Err(e))) => {
+                println!("🚫 MCP [{server_name}] {method} (id: {request_id}) cancelled by client");
+                Err(e)
+            }
+            Ok(Err(_)) => {
+                eprintln!(
+                    "❌ MCP [{server_name}] {method} (id: {request_id}) lost: server closed its output"
+                );
+                Err("Server closed the connection before responding".to_string())
+            }
+            Err(_) => {
+                let error = format!(
+                    "Request timed out after {:?} ({})",
+                    limit.duration, limit.source
+                );
+                cancel_upstream_request(
+                    server_name,
+                    &stdin_tx,
+                    &request_router,
+                    upstream_id,
+                    &error,
+                )
+                .await;
+                eprintln!("⏰ MCP [{server_name}] {method} (id: {request_id}): {error}");
+                Err(error)
+            }
+        }
+    }
+
+    /// Pass a client's cancellation on to the process working on the request, using the
+    /// upstream id it knows the request by. Requests that already finished are ignored.
+    async fn forward_cancellation(
+        &self,
+        server_name: &str,
+        session_id: Option<&str>,
+        mut notification: serde_json::Value,
+    ) -> Result<String, String> {
+        let client_id = notification
+            .pointer("/params/requestId")
+            .cloned()
+            .unwrap_or_default();
+        let channels: Vec<_> = match self.servers.read().await.get(server_name) {
+            Some(server) => (0..=server.replicas.len())
+                .map(|replica| server.process_channel(replica))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        for (stdin_tx, request_router) in channels {
+            let upstream_id = request_router.lock().await.cancel(session_id, &client_id);
+            let Some(upstream_id) = upstream_id else {
+                continue;
+            };
+            notification["params"]["requestId"] = upstream_id.into();
+            if let Some(stdin_tx) = stdin_tx {
+                send_to_stdin(server_name, &stdin_tx, &notification.to_string()).await?;
             }
+            println!("🚫 MCP [{server_name}] Forwarded cancellation of request {client_id}");
+            return Ok("".to_string());
         }
+
+        println!("⚠️ MCP [{server_name}] No pending request {client_id} to cancel");
+        Ok("".to_string())
     }
 }
 
-// Create a global instance of the manager
-lazy_static::lazy_static! {
-    static ref MCP_SERVER_MANAGER: MCPServerManager = MCPServerManager::new();
+/// A request's time limit and the setting it comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTimeout {
+    pub duration: Duration,
+    pub source: String,
 }
 
-/// Start all configured MCP servers using the global manager
-pub async fn start_all_mcp_servers(app: tauri::AppHandle) -> Result<(), String> {
-    println!("Starting all persisted MCP servers...");
+/// Pick the time limit of a request: what the client asked for, else the limit of the
+/// tool it calls, of its method or the server's default, else the gateway's default
+fn request_timeout(
+    config: &TimeoutConfig,
+    method: &str,
+    tool: Option<&str>,
+    requested: Option<Duration>,
+) -> RequestTimeout {
+    let configured = tool
+        .and_then(|tool| {
+            let ms = config.tools.get(tool)?;
+            Some((*ms, format!("timeout of tool '{tool}'")))
+        })
+        .or_else(|| {
+            let ms = config.methods.get(method)?;
+            Some((*ms, format!("timeout of method '{method}'")))
+        })
+        .or_else(|| {
+            let ms = config.default_ms?;
+            Some((ms, "server default timeout".to_string()))
+        })
+        .map(|(ms, source)| (Duration::from_millis(ms), source));
 
-    let db = get_database_connection_with_app(&app)
-        .await
-        .map_err(|e| format!("Failed to connect to database: {e}"))?;
+    let (duration, source) = match (requested, configured) {
+        (Some(duration), _) => (duration, "timeout requested by the client".to_string()),
+        (None, Some(configured)) => configured,
+        (None, None) => (REQUEST_TIMEOUT, "gateway default timeout".to_string()),
+    };
+    RequestTimeout { duration, source }
+}
 
-    let installed_mcp_servers = Model::load_installed_mcp_servers(&db)
+/// Tell a server to stop working on a request nobody is waiting for anymore,
+/// unless its response came in first
+async fn cancel_upstream_request(
+    server_name: &str,
+    stdin_tx: &mpsc::Sender<String>,
+    request_router: &SharedRequestRouter,
+    upstream_id: u64,
+    reason: &str,
+) {
+    if request_router.lock().await.take(upstream_id).is_none() {
+        return;
+    }
+    let notification = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/cancelled",
+        "params": { "requestId": upstream_id, "reason": reason },
+    });
+    if send_to_stdin(server_name, stdin_tx, &notification.to_string())
         .await
-        .map_err(|e| format!("Failed to load MCP servers: {e}"))?;
+        .is_ok()
+    {
+        println!("🚫 MCP [{server_name}] Cancelled request {upstream_id} upstream: {reason}");
+    }
+}
 
-    if installed_mcp_servers.is_empty() {
-        println!("No installed MCP servers found to start.");
-        return Ok(());
+/// Cancels a forwarded request upstream if the future waiting for it is dropped
+struct CancelOnDrop {
+    server_name: String,
+    stdin_tx: mpsc::Sender<String>,
+    request_router: SharedRequestRouter,
+    upstream_id: u64,
+    armed: bool,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let server_name = self.server_name.clone();
+        let stdin_tx = self.stdin_tx.clone();
+        let request_router = self.request_router.clone();
+        let upstream_id = self.upstream_id;
+        tokio::spawn(async move {
+            cancel_upstream_request(
+                &server_name,
+                &stdin_tx,
+                &request_router,
+                upstream_id,
+                "Client disconnected",
+            )
+            .await;
+        });
     }
+}
 
-    println!("Found {} MCP servers to start", installed_mcp_servers.len());
+/// Replace `secret://` references in args and env values with the secrets' values
+fn resolve_secret_references(
+    args: Vec<String>,
+    env: HashMap<String, String>,
+) -> Result<(Vec<String>, HashMap<String, String>), String> {
+    let args = args
+        .iter()
+        .map(|arg| secret::resolve_references(arg))
+        .collect::<Result<_, _>>()?;
+    let env = env
+        .into_iter()
+        .map(|(key, value)| Ok((key, secret::resolve_references(&value)?)))
+        .collect::<Result<_, String>>()?;
+    Ok((args, env))
+}
 
-    let server_count = installed_mcp_servers.len();
+/// The npm prefix in a server's data directory, installed into with npm using the
+/// server's HOME and npm cache rather than the user's
+fn npm_prefix(data_dir: &ServerDataDir, run_install_scripts: bool) -> node::NpmPrefix {
+    node::NpmPrefix {
+        path: data_dir.npm_prefix(),
+        env: data_dir.env(),
+        run_install_scripts,
+    }
+}
 
-    for server in &installed_mcp_servers {
-        let server_name = server.name.clone();
+/// Turn a configured command into the program and arguments to spawn, resolving `npx`
+/// packages to the detected Node.js installation and Python commands to the detected
+/// uv, pipx or Python. With a data directory, `npx` packages are installed into the
+/// server's npm prefix and `python` runs in the server's virtual environment.
+fn resolve_command(
+    name: &str,
+    command: String,
+    args: Vec<String>,
+    data_dir: Option<&ServerDataDir>,
+    npm_prefix: Option<&node::NpmPrefix>,
+) -> Result<(String, Vec<String>), String> {
+    if python::is_python_command(&command) {
+        let venv_dir = data_dir.map(ServerDataDir::venv);
+        return resolve_python_command(name, &command, args, venv_dir.as_deref());
+    }
+    if command != "npx" {
+        return Ok((command, args));
+    }
 
-        let config: ServerConfig = serde_json::from_str(&server.server_config)
-            .map_err(|e| format!("Failed to parse server config for {server_name}: {e}"))?;
+    let node_info = node::detect_node_installation();
 
-        println!(
-            "🚀 MCP [{}] Queuing startup: {} {}",
-            server_name,
-            config.command,
-            config.args.join(" ")
-        );
+    if !node_info.is_available() {
+        let instructions = node::get_node_installation_instructions();
+        return Err(format!("Cannot start MCP server '{name}': {instructions}"));
+    }
 
-        tauri::async_runtime::spawn(async move {
-            let name = server_name.clone();
-            match MCP_SERVER_MANAGER
-                .start_server(
-                    server_name.clone(),
-                    config.command,
-                    config.args,
-                    Some(config.env),
-                )
-                .await
-            {
-                Ok(_) => {} // Success already logged by start_server
-                Err(e) => eprintln!("❌ MCP [{name}] Startup failed: {e}"),
-            }
-        });
+    let Some((package_name, remaining_args)) = node::split_npx_args(&args) else {
+        return Err(format!(
+            "No package specified for npx command in server '{name}'"
+        ));
+    };
+
+    match node::get_npm_execution_command(&package_name, &node_info, npm_prefix) {
+        Ok((cmd, cmd_args)) => {
+            let mut all_args = cmd_args;
+            all_args.extend(remaining_args);
+            Ok((cmd, all_args))
+        }
+        Err(e) => Err(format!("Failed to prepare npm execution for '{name}': {e}")),
     }
+}
 
-    if server_count > 0 {
-        println!("✅ Queued {server_count} MCP servers for startup");
+fn resolve_python_command(
+    name: &str,
+    command: &str,
+    args: Vec<String>,
+    venv_dir: Option<&Path>,
+) -> Result<(String, Vec<String>), String> {
+    let python_info = python::detect_python_installation();
+
+    if !python_info.is_available() && python_info.pipx_path.is_none() {
+        let instructions = python::get_python_installation_instructions();
+        return Err(format!("Cannot start MCP server '{name}': {instructions}"));
     }
-    Ok(())
+
+    python::get_python_execution_command(command, args, &python_info, venv_dir)
+        .map_err(|e| format!("Failed to prepare Python execution for '{name}': {e}"))
 }
 
-/// Start an MCP server using the global manager
-pub async fn start_mcp_server(definition: &MCPServerDefinition) -> Result<(), String> {
-    MCP_SERVER_MANAGER
-        .start_server(
-            definition.name.clone(),
-            definition.server_config.command.clone(),
-            definition.server_config.args.clone(),
-            Some(definition.server_config.env.clone()),
-        )
-        .await
+/// Spawn a server process and start the tasks that feed its stdin and read its output
+async fn spawn_server_process(
+    name: &str,
+    spec: &LaunchSpec,
+    request_router: &SharedRequestRouter,
+) -> Result<SpawnedProcess, String> {
+    let mut container = None;
+    let container_args;
+    let args = if spec.containerized {
+        container::remove_leftover_containers(&spec.command, name, spec.replica).await;
+        let container_name = container::container_name(name, spec.replica);
+        container = Some(ContainerGuard::new(&spec.command, &container_name));
+        container_args = [
+            container::run_prefix(name, spec.replica, &container_name),
+            spec.args.clone(),
+        ]
+        .concat();
+        &container_args
+    } else {
+        &spec.args
+    };
+
+    // Start the process with sandbox-exec for security (macOS only)
+    let mut cmd = if cfg!(target_os = "macos") {
+        // Resolved up front, the server itself may run in a directory of its own
+        let profile = std::env::current_dir()
+            .unwrap_or_default()
+            .join("sandbox-exec-profiles/mcp-server-everything-for-now.sb");
+        let mut sandbox_cmd = Command::new("sandbox-exec");
+        sandbox_cmd
+            .arg("-f")
+            .arg(profile)
+            .arg(&spec.command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        sandbox_cmd
+    } else {
+        let mut regular_cmd = Command::new(&spec.command);
+        regular_cmd
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        regular_cmd
+    };
+
+    // Set environment variables
+    for (key, value) in &spec.env {
+        cmd.env(key, value);
+    }
+    if let Some(cwd) = &spec.cwd {
+        cmd.current_dir(cwd);
+    }
+
+    // Apply the resource limits (Linux only, the lifetime limit is enforced by the
+    // supervisor), a container gets them from the runtime instead
+    #[cfg(target_os = "linux")]
+    let applied_limits = match spec
+        .resource_limits
+        .as_ref()
+        .filter(|_| !spec.containerized)
+    {
+        Some(limits) => {
+            let applied = resource_limits::apply(&mut cmd, name, limits)?;
+            for warning in &applied.warnings {
+                eprintln!("⚠️ MCP [{name}] {warning}");
+                spec.logs.push(LogStream::System, warning);
+            }
+            Some(applied)
+        }
+        None => None,
+    };
+
+    // Confine the process with namespaces, Landlock and seccomp (Linux only)
+    #[cfg(target_os = "linux")]
+    let sandbox_warnings = match &spec.sandbox {
+        Some(sandbox) => {
+            let policy = linux_sandbox::SandboxPolicy::new(
+                sandbox,
+                &node::detect_node_installation(),
+                &spec.command,
+            );
+            linux_sandbox::apply(&mut cmd, &policy)?
+        }
+        None => Vec::new(),
+    };
+    #[cfg(not(target_os = "linux"))]
+    let sandbox_warnings: Vec<String> = Vec::new();
+    for warning in &sandbox_warnings {
+        eprintln!("⚠️ MCP [{name}] {warning}");
+        spec.logs.push(LogStream::System, warning);
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to spawn MCP server process: {e}"))?;
+
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| "Failed to get stdin handle".to_string())?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "Failed to get stdout handle".to_string())?;
+
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| "Failed to get stderr handle".to_string())?;
+
+    let (stdin_tx, mut stdin_rx) = mpsc::channel::<String>(CHANNEL_CAPACITY);
+
+    // Start stdin writer task
+    let close_stdin = CancellationToken::new();
+    let close_stdin_clone = close_stdin.clone();
+    tokio::spawn(async move {
+        let mut stdin = stdin;
+        loop {
+            let message = tokio::select! {
+                message = stdin_rx.recv() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+                // Dropping stdin tells the server to exit
+                _ = close_stdin_clone.cancelled() => break,
+            };
+            if let Err(e) = stdin.write_all(message.as_bytes()).await {
+                eprintln!("Failed to write to stdin: {e}");
+                break;
+            }
+            if let Err(e) = stdin.flush().await {
+                eprintln!("Failed to flush stdin: {e}");
+                break;
+            }
+        }
+    });
+
+    // Start stdout dispatcher task, handing anything that isn't a response to the supervisor
+    let (messages_tx, messages) = mpsc::unbounded_channel();
+    let router_clone = request_router.clone();
+    let logs_clone = spec.logs.clone();
+    let server_name_clone = name.to_string();
+    tokio::spawn(async move {
+        let reader = BufReader::new(stdout);
+        let mut lines = reader.lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(message) =
+                dispatch_stdout_line(&server_name_clone, &line, &router_clone, &logs_clone).await
+            {
+                let _ = messages_tx.send(message);
+            }
+        }
+
+        // The process closed stdout, fail everything still waiting on it
+        router_clone.lock().await.clear();
+    });
+
+    // Start stderr reader task, keeping the last lines around for crash reports
+    let stderr_tail = Arc::new(TokioMutex::new(VecDeque::new()));
+    let stderr_tail_clone = stderr_tail.clone();
+    let logs_clone = spec.logs.clone();
+    let server_name_clone = name.to_string();
+    let stderr_reader = tokio::spawn(async move {
+        let reader = BufReader::new(stderr);
+        let mut lines = reader.lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            eprintln!("⚠️ MCP [{server_name_clone}] {line}");
+            logs_clone.push(LogStream::Stderr, &line);
+
+            let mut tail = stderr_tail_clone.lock().await;
+            if tail.len() >= STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    });
+
+    Ok(SpawnedProcess {
+        child,
+        stdin_tx,
+        close_stdin,
+        stderr_tail,
+        stderr_reader,
+        messages,
+        sandbox_warnings,
+        #[cfg(target_os = "linux")]
+        applied_limits,
+        _container: container,
+    })
 }
 
-/// Stop an MCP server using the global manager
-pub async fn stop_mcp_server(server_name: &str) -> Result<(), String> {
-    MCP_SERVER_MANAGER.stop_server(server_name).await
+/// Apply an update to a managed server, returns false if the server was removed meanwhile
+async fn update_server(
+    servers: &ServerMap,
+    name: &str,
+    update: impl FnOnce(&mut MCPServer),
+) -> bool {
+    let mut servers = servers.write().await;
+    match servers.get_mut(name) {
+        Some(server) => {
+            let health_before = server.status.health.state;
+            update(server);
+            if server.status.health.state != health_before {
+                emit_health_changed(name, &server.status.health);
+            }
+            true
+        }
+        None => false,
+    }
 }
 
-/// Forward a raw request using the global manager
-pub async fn forward_raw_request(
-    server_name: &str,
-    request_body: String,
-) -> Result<String, String> {
-    MCP_SERVER_MANAGER
-        .forward_raw_request(server_name, request_body)
-        .await
+/// Tell the frontend that a server's health state changed
+fn emit_health_changed(server_name: &str, health: &ServerHealth) {
+    println!("🩺 MCP [{server_name}] Health is now {:?}", health.state);
+    if let Some(handle) = get_app_handle() {
+        let _ = handle.emit(
+            "mcp-server-health-changed",
+            serde_json::json!({
+                "serverName": server_name,
+                "health": health,
+            }),
+        );
+    }
+}
+
+/// Stop a server once no client sent it anything for `idle_timeout`, leaving it to
+/// the next request to start it again
+async fn stop_when_idle(
+    servers: ServerMap,
+    idle_lists: IdleLists,
+    name: String,
+    idle_timeout: Duration,
+    grace_period: Duration,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let idle_at = {
+            let servers = servers.read().await;
+            match servers.get(&name) {
+                Some(server) => server.last_activity + idle_timeout,
+                None => return,
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep_until(idle_at.into()) => {}
+            _ = shutdown.cancelled() => return,
+        }
+
+        // A long running request keeps the server busy without any new traffic. The
+        // routers are locked without holding the server map.
+        let (request_routers, lists) = {
+            let servers = servers.read().await;
+            let Some(server) = servers.get(&name) else {
+                return;
+            };
+            let lists = ServerLists {
+                tools: server.tools.clone(),
+                resources: server.resources.clone(),
+                prompts: server.prompts.clone(),
+            };
+            (server.request_routers().cloned().collect::<Vec<_>>(), lists)
+        };
+        let mut busy = false;
+        for request_router in request_routers {
+            busy |= request_router.lock().await.has_pending_client_requests();
+        }
+        // Clients keep seeing what it advertised once it's stopped, their next request
+        // starts it again. Stored up front, as the lists of a running server win anyway.
+        if !busy {
+            idle_lists.write().await.insert(name.clone(), lists);
+        }
+
+        let idle_server = {
+            let mut servers = servers.write().await;
+            let Some(server) = servers.get_mut(&name) else {
+                return;
+            };
+            if busy {
+                server.last_activity = Instant::now();
+                continue;
+            }
+            if server.last_activity.elapsed() < idle_timeout {
+                continue;
+            }
+            servers.remove(&name)
+        };
+
+        if let Some(server) = idle_server {
+            println!("💤 MCP [{name}] No requests for {idle_timeout:?}, stopping");
+            shut_down_server(server, grace_period).await;
+        }
+        return;
+    }
+}
+
+/// Ping a server at the configured interval for as long as it is managed, tracking
+/// its health through restarts
+async fn check_server_health(
+    servers: ServerMap,
+    name: String,
+    config: HealthCheckConfig,
+    shutdown: CancellationToken,
+) {
+    let interval = Duration::from_secs(config.interval_seconds.max(1));
+    let timeout = Duration::from_millis(config.timeout_ms);
+
+    loop {
+        let next_check = {
+            let servers = servers.read().await;
+            match servers.get(&name) {
+                Some(server) => server.last_health_check + interval,
+                None => return,
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep_until(next_check.into()) => {}
+            _ = shutdown.cancelled() => return,
+        }
+
+        let client = {
+            let servers = servers.read().await;
+            let Some(server) = servers.get(&name) else {
+                return;
+            };
+            match (&server.stdin_tx, server.status.state) {
+                (Some(stdin_tx), ServerState::Running) => Some(UpstreamClient {
+                    server_name: name.clone(),
+                    stdin_tx: stdin_tx.clone(),
+                    request_router: server.request_router.clone(),
+                }),
+                _ => None,
+            }
+        };
+
+        // A server that is being restarted has nothing to answer with
+        let latency = match client {
+            Some(client) => client.ping(timeout).await.ok(),
+            None => {
+                update_server(&servers, &name, |server| {
+                    server.last_health_check = Instant::now();
+                })
+                .await;
+                continue;
+            }
+        };
+
+        update_server(&servers, &name, |server| {
+            server.last_health_check = Instant::now();
+            // The process may have gone away while we were waiting
+            if server.status.state == ServerState::Running {
+                server.status.health.record_ping(latency, &config);
+            }
+        })
+        .await;
+    }
+}
+
+/// Resource limit a process ran into, if any (limits are only applied on Linux)
+fn exceeded_limit(
+    status: Option<&std::process::ExitStatus>,
+    process: &SpawnedProcess,
+) -> Option<TerminationReason> {
+    #[cfg(target_os = "linux")]
+    {
+        status
+            .zip(process.applied_limits.as_ref())
+            .and_then(|(status, applied)| applied.exceeded_limit(status))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (status, process);
+        None
+    }
+}
+
+/// Delay before the given (1-based) restart attempt
+fn restart_backoff(policy: &RestartPolicy, attempt: u32) -> Duration {
+    let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+    Duration::from_millis(policy.initial_backoff_ms.saturating_mul(factor)).min(MAX_RESTART_BACKOFF)
+}
+
+/// Watch a server process until it is shut down, restarting it with exponential backoff
+/// when it exits on its own and marking it as crashed once the restart limit is reached
+async fn supervise_server_process(
+    servers: ServerMap,
+    name: String,
+    spec: LaunchSpec,
+    mut process: SpawnedProcess,
+    request_router: SharedRequestRouter,
+    shutdown: CancellationToken,
+    policy: RestartPolicy,
+) {
+    let mut restart_count = 0;
+
+    let max_lifetime = spec
+        .resource_limits
+        .as_ref()
+        .and_then(|limits| limits.max_lifetime_seconds)
+        .map(Duration::from_secs);
+
+    loop {
+        let started_at = Instant::now();
+        let client = UpstreamClient {
+            server_name: name.clone(),
+            stdin_tx: process.stdin_tx.clone(),
+            request_router: request_router.clone(),
+        };
+        tokio::spawn(initialize_server(
+            servers.clone(),
+            client.clone(),
+            spec.logs.clone(),
+        ));
+
+        let lifetime_expired = async {
+            match max_lifetime {
+                Some(lifetime) => tokio::time::sleep(lifetime).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(lifetime_expired);
+        let (exit_status, lifetime_exceeded) = loop {
+            tokio::select! {
+                status = process.child.wait() => break (status, false),
+                _ = &mut lifetime_expired => {
+                    if let Err(e) = process.child.kill().await {
+                        eprintln!("⚠️ MCP [{name}] Failed to kill process: {e}");
+                    }
+                    break (process.child.wait().await, true);
+                }
+                _ = shutdown.cancelled() => {
+                    terminate_process(&name, &mut process).await;
+                    return;
+                }
+                Some(message) = process.messages.recv() => {
+                    handle_server_message(&servers, &client, message).await;
+                }
+            }
+        };
+
+        let stderr_tail = process.stderr_tail().await;
+        let termination_reason = if lifetime_exceeded {
+            TerminationReason::LifetimeLimit
+        } else {
+            exceeded_limit(exit_status.as_ref().ok(), &process).unwrap_or(TerminationReason::Exited)
+        };
+        let exit_code = exit_status.ok().and_then(|status| status.code());
+        let exit_message = format!(
+            "Process {} (exit code: {exit_code:?})",
+            termination_reason.describe()
+        );
+        eprintln!("💥 MCP [{name}] {exit_message}");
+        spec.logs.push(LogStream::System, &exit_message);
+
+        // Nobody will answer requests sent to the old process anymore
+        request_router.lock().await.clear();
+
+        // A process that stayed up for a while gets a fresh set of restarts
+        if started_at.elapsed() >= STABLE_RUN_DURATION {
+            restart_count = 0;
+        }
+
+        let mut last_exit = ExitInfo {
+            termination_reason,
+            exit_code,
+            stderr_tail,
+            exited_at: chrono::Utc::now(),
+        };
+
+        process = loop {
+            if restart_count >= policy.max_restarts {
+                let message =
+                    format!("Giving up after {restart_count} restarts, marking as crashed");
+                eprintln!("❌ MCP [{name}] {message}");
+                spec.logs.push(LogStream::System, &message);
+                update_server(&servers, &name, |server| {
+                    server.set_stdin_tx(spec.replica, None);
+                    server.unpin_sessions(spec.replica);
+                    if spec.replica == 0 {
+                        server.status = ServerStatus {
+                            state: ServerState::Crashed,
+                            restart_count,
+                            last_exit: Some(last_exit),
+                            health: ServerHealth::with_state(HealthState::Stopped),
+                            sandbox_warnings: std::mem::take(&mut server.status.sandbox_warnings),
+                        };
+                    }
+                })
+                .await;
+                return;
+            }
+
+            restart_count += 1;
+            let backoff = restart_backoff(&policy, restart_count);
+            let still_managed = update_server(&servers, &name, |server| {
+                server.set_stdin_tx(spec.replica, None);
+                server.unpin_sessions(spec.replica);
+                if spec.replica == 0 {
+                    server.status = ServerStatus {
+                        state: ServerState::Restarting,
+                        restart_count,
+                        last_exit: Some(last_exit.clone()),
+                        health: ServerHealth::with_state(HealthState::Stopped),
+                        sandbox_warnings: std::mem::take(&mut server.status.sandbox_warnings),
+                    };
+                }
+            })
+            .await;
+            if !still_managed {
+                return;
+            }
+
+            println!(
+                "🔁 MCP [{name}] Restarting in {backoff:?} (attempt {restart_count}/{})",
+                policy.max_restarts
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = shutdown.cancelled() => return,
+            }
+
+            match spawn_server_process(&name, &spec, &request_router).await {
+                Ok(new_process) => break new_process,
+                Err(e) => {
+                    eprintln!("❌ MCP [{name}] Restart failed: {e}");
+                    spec.logs
+                        .push(LogStream::System, &format!("Restart failed: {e}"));
+                    last_exit = ExitInfo {
+                        termination_reason: TerminationReason::SpawnFailed,
+                        exit_code: None,
+                        stderr_tail: vec![e],
+                        exited_at: chrono::Utc::now(),
+                    };
+                }
+            }
+        };
+
+        let stdin_tx = process.stdin_tx.clone();
+        let still_managed = update_server(&servers, &name, |server| {
+            server.set_stdin_tx(spec.replica, Some(stdin_tx));
+            if spec.replica == 0 {
+                server.status.state = ServerState::Running;
+                server.status.health = ServerHealth::with_state(HealthState::Starting);
+            }
+        })
+        .await;
+        if !still_managed {
+            let _ = process.child.kill().await;
+            return;
+        }
+        println!("✅ MCP [{name}] Restarted successfully");
+    }
+}
+
+/// Ask a server process to exit and wait until it has: close its stdin and, on unix,
+/// send SIGTERM. Whoever stopped the server decides how long to wait before killing it.
+async fn terminate_process(name: &str, process: &mut SpawnedProcess) {
+    process.close_stdin.cancel();
+
+    #[cfg(unix)]
+    if let Some(pid) = process.child.id() {
+        // SAFETY: kill only sends a signal to the process we spawned
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+            eprintln!(
+                "⚠️ MCP [{name}] Failed to send SIGTERM: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    match process.child.wait().await {
+        Ok(status) => println!("👋 MCP [{name}] Process exited ({status})"),
+        Err(e) => eprintln!("⚠️ MCP [{name}] Failed to wait for process: {e}"),
+    }
+}
+
+/// Stop a server that was taken out of the manager. A process that doesn't exit within
+/// the grace period is killed: dropping its supervisor drops the child, which has
+/// `kill_on_drop` set and therefore receives SIGKILL.
+async fn shut_down_server(server: MCPServer, grace_period: Duration) {
+    let name = server.name;
+    drop(server.stdin_tx);
+
+    if let Some(shutdown) = server.shutdown {
+        shutdown.cancel();
+    }
+    let deadline = tokio::time::Instant::now() + grace_period;
+    let supervisors = server.supervisor.into_iter().chain(
+        server
+            .replicas
+            .into_iter()
+            .filter_map(|replica| replica.supervisor),
+    );
+    for mut supervisor in supervisors {
+        match tokio::time::timeout_at(deadline, &mut supervisor).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("⚠️ MCP [{name}] Supervisor task failed: {e}"),
+            Err(_) => {
+                eprintln!("🔪 MCP [{name}] Still running after {grace_period:?}, killing it");
+                supervisor.abort();
+                let _ = supervisor.await;
+            }
+        }
+    }
+
+    emit_health_changed(&name, &ServerHealth::with_state(HealthState::Stopped));
+}
+
+/// The connection to a remote server, whichever transport it speaks
+enum RemoteTransport {
+    StreamableHttp(Arc<HttpTransport>),
+    Sse(Arc<SseTransport>),
+}
+
+impl RemoteTransport {
+    async fn session_expired(&self) {
+        match self {
+            Self::StreamableHttp(transport) => transport.session_expired().await,
+            Self::Sse(transport) => transport.session_expired().await,
+        }
+    }
+
+    async fn end_session(&self) {
+        match self {
+            Self::StreamableHttp(transport) => transport.end_session().await,
+            // The session ends with the event stream, which closes on shutdown
+            Self::Sse(_) => {}
+        }
+    }
+}
+
+/// Run the handshake with a remote server and pass on what it sends until it is stopped,
+/// ending the upstream session on the way out
+async fn supervise_remote_server(
+    servers: ServerMap,
+    client: UpstreamClient,
+    transport: RemoteTransport,
+    mut messages: mpsc::UnboundedReceiver<ServerMessage>,
+    logs: Arc<ServerLogSink>,
+    shutdown: CancellationToken,
+) {
+    tokio::spawn(initialize_server(
+        servers.clone(),
+        client.clone(),
+        logs.clone(),
+    ));
+
+    loop {
+        tokio::select! {
+            Some(message) = messages.recv() => {
+                handle_server_message(&servers, &client, message).await;
+            }
+            _ = transport.session_expired() => {
+                println!("🔁 MCP [{}] Starting a new session", client.server_name);
+                // Whatever was in flight belonged to the old session
+                client.request_router.lock().await.clear();
+                tokio::spawn(initialize_server(
+                    servers.clone(),
+                    client.clone(),
+                    logs.clone(),
+                ));
+            }
+            _ = shutdown.cancelled() => {
+                transport.end_session().await;
+                return;
+            }
+        }
+    }
+}
+
+/// The lists a server advertises that the gateway keeps a copy of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachedList {
+    Tools,
+    Resources,
+    Prompts,
+}
+
+impl CachedList {
+    pub const ALL: [Self; 3] = [Self::Tools, Self::Resources, Self::Prompts];
+
+    /// The list a `list_changed` notification is about
+    fn from_notification(method: &str) -> Option<Self> {
+        match method {
+            "notifications/tools/list_changed" => Some(Self::Tools),
+            "notifications/resources/list_changed" => Some(Self::Resources),
+            "notifications/prompts/list_changed" => Some(Self::Prompts),
+            _ => None,
+        }
+    }
+
+    fn method(self) -> &'static str {
+        match self {
+            Self::Tools => "tools/list",
+            Self::Resources => "resources/list",
+            Self::Prompts => "prompts/list",
+        }
+    }
+
+    /// Field of the list result that holds the items
+    fn key(self) -> &'static str {
+        match self {
+            Self::Tools => "tools",
+            Self::Resources => "resources",
+            Self::Prompts => "prompts",
+        }
+    }
+
+    fn is_supported(self, capabilities: &ServerCapabilities) -> bool {
+        match self {
+            Self::Tools => capabilities.tools.is_some(),
+            Self::Resources => capabilities.resources.is_some(),
+            Self::Prompts => capabilities.prompts.is_some(),
+        }
+    }
+}
+
+/// The gateway's own connection to a stdio server, for requests it sends itself
+/// rather than on behalf of a client
+#[derive(Clone)]
+struct UpstreamClient {
+    server_name: String,
+    stdin_tx: mpsc::Sender<String>,
+    request_router: SharedRequestRouter,
+}
+
+impl UpstreamClient {
+    /// Send a request and wait for its result, turning JSON-RPC errors into `Err`
+    async fn request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let mut json = self.send_request(method, params, REQUEST_TIMEOUT).await?;
+        if let Some(error) = json.get("error") {
+            return Err(format!("{method} failed: {error}"));
+        }
+        Ok(json["result"].take())
+    }
+
+    /// Send a `ping` and measure how long the answer takes. Any answer counts,
+    /// even an error from a server that doesn't implement ping.
+    async fn ping(&self, timeout: Duration) -> Result<Duration, String> {
+        let started_at = Instant::now();
+        self.send_request("ping", serde_json::json!({}), timeout)
+            .await?;
+        Ok(started_at.elapsed())
+    }
+
+    /// Send a request and wait for the whole response message
+    async fn send_request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, String> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let upstream_id =
+            self.request_router
+                .lock()
+                .await
+                .register(None, serde_json::Value::Null, response_tx);
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": upstream_id,
+            "method": method,
+            "params": params,
+        });
+
+        if let Err(e) = send_to_stdin(&self.server_name, &self.stdin_tx, &body.to_string()).await {
+            self.request_router.lock().await.take(upstream_id);
+            return Err(e);
+        }
+
+        let response = match tokio::time::timeout(timeout, response_rx).await {
+            Ok(Ok(Ok(response))) => response,
+            Ok(Ok(Err(e))) => return Err(e),
+            Ok(Err(_)) => {
+                return Err(format!(
+                    "Server closed the connection before answering {method}"
+                ))
+            }
+            Err(_) => {
+                self.request_router.lock().await.take(upstream_id);
+                return Err(format!("{method} timed out after {timeout:?}"));
+            }
+        };
+
+        serde_json::from_str(&response).map_err(|e| format!("Invalid response to {method}: {e}"))
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), String> {
+        let body = serde_json::json!({ "jsonrpc": "2.0", "method": method });
+        send_to_stdin(&self.server_name, &self.stdin_tx, &body.to_string()).await
+    }
+
+    /// Fetch every page of one of the server's lists
+    async fn list_all<T: DeserializeOwned>(&self, list: CachedList) -> Result<Vec<T>, String> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = match &cursor {
+                Some(cursor) => serde_json::json!({ "cursor": cursor }),
+                None => serde_json::json!({}),
+            };
+            let mut result = self.request(list.method(), params).await?;
+
+            let page: Vec<T> = serde_json::from_value(result[list.key()].take())
+                .map_err(|e| format!("Invalid {} result: {e}", list.method()))?;
+            items.extend(page);
+
+            cursor = result
+                .get("nextCursor")
+                .and_then(|c| c.as_str())
+                .map(|c| c.to_string());
+            if cursor.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+}
+
+/// Run the MCP handshake with a freshly spawned server process and fill the capability cache
+async fn initialize_server(servers: ServerMap, client: UpstreamClient, logs: Arc<ServerLogSink>) {
+    let name = client.server_name.clone();
+
+    let handshake = async {
+        let params = serde_json::json!({
+            "protocolVersion": ProtocolVersion::LATEST,
+            // Relayed to the client session the server's request belongs to
+            "capabilities": {
+                "roots": { "listChanged": true },
+                "sampling": {},
+                "elicitation": {},
+            },
+            "clientInfo": Implementation {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        });
+        let result = client.request("initialize", params).await?;
+        let info: InitializeResult = serde_json::from_value(result)
+            .map_err(|e| format!("Invalid initialize result: {e}"))?;
+        client.notify("notifications/initialized").await?;
+        Ok::<_, String>(info)
+    };
+
+    let info = match handshake.await {
+        Ok(info) => info,
+        Err(e) => {
+            let message = format!("Handshake failed: {e}");
+            eprintln!("❌ MCP [{name}] {message}");
+            logs.push(LogStream::System, &message);
+            return;
+        }
+    };
+
+    println!(
+        "🤝 MCP [{name}] Initialized {} {} (protocol {})",
+        info.server_info.name, info.server_info.version, info.protocol_version
+    );
+    let capabilities = info.capabilities.clone();
+    let stored = update_server(&servers, &name, |server| {
+        server.server_info = Some(info);
+    })
+    .await;
+    if !stored {
+        return;
+    }
+
+    for list in CachedList::ALL {
+        if !list.is_supported(&capabilities) {
+            continue;
+        }
+        if let Err(e) = refresh_cached_list(&servers, &client, list).await {
+            let message = format!("Failed to fetch {}: {e}", list.method());
+            eprintln!("⚠️ MCP [{name}] {message}");
+            logs.push(LogStream::System, &message);
+        }
+    }
+
+    // Clients waiting for the server, e.g. to list it, get to see its lists right away
+    update_server(&servers, &name, |server| {
+        server.ready.send_replace(true);
+    })
+    .await;
+}
+
+/// The shutdown grace period set in the environment, or the default if it isn't a
+/// number of milliseconds
+fn parse_shutdown_grace_period(value: Option<&str>) -> Duration {
+    value
+        .and_then(|ms| ms.trim().parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_SHUTDOWN_GRACE_PERIOD)
+}
+
+/// Wait until a server finished the gateway's handshake
+async fn wait_for_ready(
+    server_name: &str,
+    ready: &mut watch::Receiver<bool>,
+) -> Result<(), String> {
+    let initialized = tokio::time::timeout(STARTUP_TIMEOUT, ready.wait_for(|ready| *ready))
+        .await
+        .map(|ready| ready.is_ok());
+    match initialized {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!(
+            "Server '{server_name}' stopped before it finished initializing"
+        )),
+        Err(_) => Err(format!(
+            "Server '{server_name}' did not finish initializing within {STARTUP_TIMEOUT:?}"
+        )),
+    }
+}
+
+/// The protocol version to answer a client's initialize with: the one it asked for if
+/// the gateway speaks it and it isn't newer than what the server negotiated, otherwise
+/// the server's, which the client may then decline
+fn negotiate_protocol_version(
+    requested: Option<&str>,
+    upstream: &ProtocolVersion,
+) -> ProtocolVersion {
+    let upstream_version = upstream.to_string();
+    [ProtocolVersion::V_2024_11_05, ProtocolVersion::V_2025_03_26]
+        .into_iter()
+        .find(|version| {
+            let version = version.to_string();
+            Some(version.as_str()) == requested && version <= upstream_version
+        })
+        .unwrap_or_else(|| upstream.clone())
+}
+
+/// Fetch one of a server's lists again and replace the cached copy
+async fn refresh_cached_list(
+    servers: &ServerMap,
+    client: &UpstreamClient,
+    list: CachedList,
+) -> Result<(), String> {
+    let name = &client.server_name;
+    let count = match list {
+        CachedList::Tools => {
+            let tools: Vec<MCPTool> = client.list_all(list).await?;
+            let count = tools.len();
+            update_server(servers, name, |server| server.tools = tools).await;
+            count
+        }
+        CachedList::Resources => {
+            let resources: Vec<MCPResource> = client.list_all(list).await?;
+            let count = resources.len();
+            update_server(servers, name, |server| server.resources = resources).await;
+            count
+        }
+        CachedList::Prompts => {
+            let prompts: Vec<MCPPrompt> = client.list_all(list).await?;
+            let count = prompts.len();
+            update_server(servers, name, |server| server.prompts = prompts).await;
+            count
+        }
+    };
+
+    println!("📋 MCP [{name}] Cached {count} {}", list.key());
+    notify_list_changed(list);
+    Ok(())
+}
+
+/// React to a notification or request the server sent on its own
+async fn handle_server_message(
+    servers: &ServerMap,
+    client: &UpstreamClient,
+    message: ServerMessage,
+) {
+    let ServerMessage { message, delivered } = message;
+    let Some(method) = message.get("method").and_then(|m| m.as_str()) else {
+        return;
+    };
+
+    if let Some(list) = CachedList::from_notification(method) {
+        println!(
+            "🔄 MCP [{}] {} changed, refreshing",
+            client.server_name,
+            list.key()
+        );
+        let servers = servers.clone();
+        let client = client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = refresh_cached_list(&servers, &client, list).await {
+                eprintln!(
+                    "⚠️ MCP [{}] Failed to refresh {}: {e}",
+                    client.server_name,
+                    list.key()
+                );
+            }
+        });
+    }
+
+    // Don't leave the server waiting on a request nobody is going to answer, to the
+    // server it's as if the gateway didn't offer what it asked for
+    if let (false, Some(id)) = (delivered, message.get("id")) {
+        eprintln!(
+            "⚠️ MCP [{}] No client session to answer {method}",
+            client.server_name
+        );
+        let error = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": -32601,
+                "message": format!("Method not found: no connected client supports {method}"),
+            },
+        });
+        let _ = send_to_stdin(&client.server_name, &client.stdin_tx, &error.to_string()).await;
+    }
+}
+
+/// Write a single newline-delimited message to a server's stdin
+async fn send_to_stdin(
+    server_name: &str,
+    stdin_tx: &mpsc::Sender<String>,
+    message: &str,
+) -> Result<(), String> {
+    stdin_tx.send(format!("{message}\n")).await.map_err(|e| {
+        eprintln!("❌ MCP [{server_name}] Failed to send to stdin: {e}");
+        format!("Failed to send request: {e}")
+    })
+}
+
+/// Replace the "id" of a JSON-RPC message, keeping every other field as is
+fn with_id(message: &str, id: serde_json::Value) -> Result<String, serde_json::Error> {
+    let mut json: serde_json::Value = serde_json::from_str(message)?;
+    json["id"] = id;
+    serde_json::to_string(&json)
+}
+
+/// Replace the progress token of a JSON-RPC request, keeping every other field as is
+fn with_progress_token(
+    message: &str,
+    token: serde_json::Value,
+) -> Result<String, serde_json::Error> {
+    let mut json: serde_json::Value = serde_json::from_str(message)?;
+    json["params"]["_meta"]["progressToken"] = token;
+    serde_json::to_string(&json)
+}
+
+/// A notification or request the server sent on its own
+#[derive(Debug)]
+pub struct ServerMessage {
+    pub message: serde_json::Value,
+    /// Whether it reached a client session, a request that didn't still needs an answer
+    pub delivered: bool,
+}
+
+/// Route a line of server stdout to the request waiting on its upstream id,
+/// restoring the id the client originally used. Notifications and requests the
+/// server sends on its own are passed on to client sessions right away, so they
+/// can't overtake the response they belong to, and then handed back to the caller.
+pub async fn dispatch_stdout_line(
+    server_name: &str,
+    line: &str,
+    request_router: &SharedRequestRouter,
+    logs: &ServerLogSink,
+) -> Option<ServerMessage> {
+    let mut json = match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(json) => json,
+        Err(_) => {
+            // Non-JSON output is not part of the protocol, keep it as a log line
+            println!("MCP [{server_name}] {line}");
+            logs.push(LogStream::Stdout, line);
+            return None;
+        }
+    };
+
+    // Only responses (with "result" or "error" and an "id") complete a pending request
+    let obj = json.as_object()?;
+    if !(obj.contains_key("result") || obj.contains_key("error")) {
+        if !obj.contains_key("method") {
+            return None;
+        }
+        let delivered = request_router
+            .lock()
+            .await
+            .route_server_message(json.clone());
+        return Some(ServerMessage {
+            message: json,
+            delivered,
+        });
+    }
+    let Some(upstream_id) = obj.get("id").and_then(|id| id.as_u64()) else {
+        eprintln!("⚠️ MCP [{server_name}] Dropping response with unexpected id: {line}");
+        return None;
+    };
+
+    let pending = request_router.lock().await.take(upstream_id);
+    match pending {
+        Some(pending) => {
+            json["id"] = pending.client_id;
+            // The receiver may have timed out in the meantime, nothing to do then
+            let _ = pending.response_tx.send(Ok(json.to_string()));
+        }
+        None => eprintln!("⚠️ MCP [{server_name}] Dropping response for unknown id {upstream_id}"),
+    }
+    None
+}
+
+// Create a global instance of the manager
+lazy_static::lazy_static! {
+    static ref MCP_SERVER_MANAGER: MCPServerManager = MCPServerManager::new();
+    static ref LIST_CHANGES: broadcast::Sender<CachedList> = broadcast::channel(64).0;
+}
+
+/// Tell the gateway's subscribers that the tools, resources or prompts of the managed
+/// servers changed, because a server cached a new list or went away
+fn notify_list_changed(list: CachedList) {
+    // Nobody listening is fine
+    let _ = LIST_CHANGES.send(list);
+}
+
+/// Get told whenever the tools, resources or prompts of the managed servers change
+pub fn subscribe_to_list_changes() -> broadcast::Receiver<CachedList> {
+    LIST_CHANGES.subscribe()
+}
+
+/// Start all configured MCP servers using the global manager
+pub async fn start_all_mcp_servers(app: tauri::AppHandle) -> Result<(), String> {
+    println!("Starting all persisted MCP servers...");
+
+    let db = get_database_connection_with_app(&app)
+        .await
+        .map_err(|e| format!("Failed to connect to database: {e}"))?;
+    MCP_SERVER_MANAGER.set_log_database(db.clone());
+    match app.path().app_data_dir() {
+        Ok(data_dir) => MCP_SERVER_MANAGER.set_data_directory(data_dir),
+        Err(e) => eprintln!("⚠️ No app data directory, servers share the user's HOME: {e}"),
+    }
+
+    let installed_mcp_servers = Model::load_installed_mcp_servers(&db)
+        .await
+        .map_err(|e| format!("Failed to load MCP servers: {e}"))?;
+
+    if installed_mcp_servers.is_empty() {
+        println!("No installed MCP servers found to start.");
+        return Ok(());
+    }
+
+    println!("Found {} MCP servers to start", installed_mcp_servers.len());
+
+    let server_count = installed_mcp_servers
+        .iter()
+        .filter(|server| server.enabled)
+        .count();
+
+    for server in &installed_mcp_servers {
+        let server_name = server.name.clone();
+
+        if !server.enabled {
+            println!("⏸️ MCP [{server_name}] Disabled, not starting");
+            continue;
+        }
+
+        let config: ServerConfig = serde_json::from_str(&server.server_config)
+            .map_err(|e| format!("Failed to parse server config for {server_name}: {e}"))?;
+
+        println!(
+            "🚀 MCP [{}] Queuing startup: {} {}",
+            server_name,
+            config.command,
+            config.args.join(" ")
+        );
+
+        tauri::async_runtime::spawn(async move {
+            let name = server_name.clone();
+            match MCP_SERVER_MANAGER
+                .activate_server(server_name, config)
+                .await
+            {
+                Ok(_) => {} // Success already logged by start_server
+                Err(e) => eprintln!("❌ MCP [{name}] Startup failed: {e}"),
+            }
+        });
+    }
+
+    if server_count > 0 {
+        println!("✅ Queued {server_count} MCP servers for startup");
+    }
+    Ok(())
+}
+
+/// Start an MCP server using the global manager
+pub async fn start_mcp_server(definition: &MCPServerDefinition) -> Result<(), String> {
+    MCP_SERVER_MANAGER
+        .start_server(definition.name.clone(), definition.server_config.clone())
+        .await
+}
+
+/// Start a lazily activated server that isn't running and wait until its lists are
+/// cached, using the global manager
+pub async fn start_lazy_mcp_server(server_name: &str) -> Result<(), String> {
+    MCP_SERVER_MANAGER.ensure_started(server_name).await
+}
+
+/// Start an MCP server, or wait for its first request if it is activated lazily,
+/// using the global manager
+pub async fn activate_mcp_server(definition: &MCPServerDefinition) -> Result<(), String> {
+    MCP_SERVER_MANAGER
+        .activate_server(definition.name.clone(), definition.server_config.clone())
+        .await
+}
+
+/// Stop an MCP server using the global manager
+pub async fn stop_mcp_server(server_name: &str) -> Result<(), String> {
+    MCP_SERVER_MANAGER.stop_server(server_name).await
+}
+
+/// Delete a server's data directory using the global manager
+pub fn remove_mcp_server_data(server_name: &str) -> Result<(), String> {
+    MCP_SERVER_MANAGER.remove_server_data(server_name)
+}
+
+/// The package spec an `npx` server is upgraded to, `None` for other servers and for
+/// packages pinned to an exact version in the server's args
+pub fn upgradable_npm_package(config: &ServerConfig) -> Option<String> {
+    if config.command != "npx" || config.transport == "docker" {
+        return None;
+    }
+    let (spec, _) = node::split_npx_args(&config.args)?;
+    match node::parse_package_spec(&spec) {
+        (_, Some(version)) if node::is_exact_version(version) => None,
+        (name, None) => Some(format!("{name}@latest")),
+        _ => Some(spec),
+    }
+}
+
+/// Upgrade the npm package of a server using the global manager
+pub async fn upgrade_mcp_server_npm_package(
+    server_name: &str,
+    spec: String,
+    run_install_scripts: bool,
+) -> Result<NpmPackagePin, String> {
+    MCP_SERVER_MANAGER
+        .upgrade_npm_package(server_name, spec, run_install_scripts)
+        .await
+}
+
+/// Stop every server using the global manager, giving each its configured grace period,
+/// see `MCPServerManager::shutdown_all`
+pub async fn shutdown_all_mcp_servers() {
+    MCP_SERVER_MANAGER
+        .shutdown_all(MCP_SERVER_MANAGER.shutdown_grace_period)
+        .await
+}
+
+/// Get the status of every running server using the global manager
+pub async fn get_mcp_server_statuses() -> HashMap<String, ServerStatus> {
+    MCP_SERVER_MANAGER.get_server_statuses().await
+}
+
+/// Get the status of a single server using the global manager
+pub async fn get_mcp_server_status(server_name: &str) -> Option<ServerStatus> {
+    MCP_SERVER_MANAGER.get_server_status(server_name).await
+}
+
+/// Get the cached tools of a server using the global manager
+pub async fn get_mcp_server_tools(server_name: &str) -> Option<Vec<MCPTool>> {
+    MCP_SERVER_MANAGER.get_cached_tools(server_name).await
+}
+
+/// Get the cached resources of a server using the global manager
+pub async fn get_mcp_server_resources(server_name: &str) -> Option<Vec<MCPResource>> {
+    MCP_SERVER_MANAGER.get_cached_resources(server_name).await
+}
+
+/// Get the cached prompts of a server using the global manager
+pub async fn get_mcp_server_prompts(server_name: &str) -> Option<Vec<MCPPrompt>> {
+    MCP_SERVER_MANAGER.get_cached_prompts(server_name).await
+}
+
+/// Get the capabilities of a server using the global manager
+pub async fn get_mcp_server_capabilities(server_name: &str) -> Option<ServerCapabilities> {
+    MCP_SERVER_MANAGER
+        .get_server_capabilities(server_name)
+        .await
+}
+
+/// Get the log sink of a server using the global manager
+pub async fn get_mcp_server_log_sink(server_name: &str) -> Arc<ServerLogSink> {
+    MCP_SERVER_MANAGER.log_sink(server_name).await
+}
+
+/// Open a client session's message stream using the global manager
+pub async fn open_session_stream(
+    server_name: &str,
+    session_id: &str,
+) -> Result<mpsc::UnboundedReceiver<String>, String> {
+    MCP_SERVER_MANAGER
+        .open_session_stream(server_name, session_id)
+        .await
+}
+
+/// Forget a client session that ended using the global manager
+pub async fn close_session(server_name: &str, session_id: &str) -> Result<(), String> {
+    MCP_SERVER_MANAGER
+        .close_session(server_name, session_id)
+        .await
+}
+
+/// Forward a raw request using the global manager
+pub async fn forward_raw_request(
+    server_name: &str,
+    session_id: Option<&str>,
+    request_body: String,
+    timeout: Option<Duration>,
+) -> Result<String, String> {
+    MCP_SERVER_MANAGER
+        .forward_raw_request(server_name, session_id, request_body, timeout)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mcp_server::ContainerConfig;
+    use crate::test_fixtures::database;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[test]
+    fn test_router_allocates_unique_upstream_ids_for_colliding_client_ids() {
+        let mut router = RequestRouter::default();
+        let (tx_a, _rx_a) = oneshot::channel();
+        let (tx_b, _rx_b) = oneshot::channel();
+
+        // Two clients both start numbering at 1
+        let upstream_a = router.register(Some("session-a".to_string()), json!(1), tx_a);
+        let upstream_b = router.register(Some("session-b".to_string()), json!(1), tx_b);
+        assert_ne!(upstream_a, upstream_b);
+
+        let pending_b = router.take(upstream_b).unwrap();
+        assert_eq!(pending_b.session_id.as_deref(), Some("session-b"));
+        assert_eq!(pending_b.client_id, json!(1));
+        assert!(router.take(upstream_b).is_none());
+        assert!(router.take(upstream_a).is_some());
+    }
+
+    #[test]
+    fn test_router_cancels_only_the_requesting_sessions_request() {
+        let mut router = RequestRouter::default();
+        let (tx_a, mut rx_a) = oneshot::channel();
+        let (tx_b, mut rx_b) = oneshot::channel();
+        let upstream_a = router.register(Some("session-a".to_string()), json!(1), tx_a);
+        let upstream_b = router.register(Some("session-b".to_string()), json!(1), tx_b);
+
+        // Session b cancelling its request 1 leaves session a's request 1 alone
+        assert_eq!(
+            router.cancel(Some("session-b"), &json!(1)),
+            Some(upstream_b)
+        );
+        assert_eq!(rx_b.try_recv().unwrap().unwrap_err(), REQUEST_CANCELLED);
+        assert!(rx_a.try_recv().is_err());
+        assert_eq!(router.cancel(Some("session-c"), &json!(1)), None);
+        assert_eq!(
+            router.take(upstream_a).unwrap().session_id.as_deref(),
+            Some("session-a")
+        );
+    }
+
+    #[test]
+    fn test_router_routes_progress_to_requesting_session() {
+        let mut router = RequestRouter::default();
+        let mut stream_a = router.open_session("session-a".to_string());
+        let mut stream_b = router.open_session("session-b".to_string());
+        let (tx, _rx) = oneshot::channel();
+        let upstream_id = router.register(Some("session-a".to_string()), json!(1), tx);
+        router.track_progress(upstream_id, json!("client-token"));
+
+        assert!(router.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": { "progressToken": upstream_id, "progress": 50 }
+        })));
+
+        let message: serde_json::Value =
+            serde_json::from_str(&stream_a.try_recv().unwrap()).unwrap();
+        assert_eq!(message["params"]["progressToken"], "client-token");
+        assert_eq!(message["params"]["progress"], 50);
+        assert!(stream_b.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_router_sends_server_requests_to_one_session() {
+        let mut router = RequestRouter::default();
+        let mut stream_a = router.open_session("session-a".to_string());
+        let mut stream_b = router.open_session("session-b".to_string());
+        for session in ["session-a", "session-b"] {
+            router.set_client_capabilities(session.to_string(), json!({ "sampling": {} }));
+        }
+        let (tx, _rx) = oneshot::channel();
+        router.register(Some("session-b".to_string()), json!(1), tx);
+
+        // The server asks for a completion while working on session b's request
+        assert!(router.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "sampling/createMessage",
+            "params": {}
+        })));
+        assert!(stream_a.try_recv().is_err());
+        assert!(stream_b.try_recv().is_ok());
+
+        // Only session b may answer, and only once
+        assert!(!router.complete_server_request(Some("session-a"), &json!(7)));
+        assert!(router.complete_server_request(Some("session-b"), &json!(7)));
+        assert!(!router.complete_server_request(Some("session-b"), &json!(7)));
+    }
+
+    #[test]
+    fn test_router_sends_server_requests_only_to_capable_sessions() {
+        let mut router = RequestRouter::default();
+        let mut stream_a = router.open_session("session-a".to_string());
+        let mut stream_b = router.open_session("session-b".to_string());
+        router.set_client_capabilities("session-a".to_string(), json!({ "roots": {} }));
+        router.set_client_capabilities("session-b".to_string(), json!({}));
+        let (tx, _rx) = oneshot::channel();
+        router.register(Some("session-b".to_string()), json!(1), tx);
+
+        // Session b is busy with a request, but can't answer this one
+        assert!(router.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "roots/list"
+        })));
+        assert!(stream_a.try_recv().is_ok());
+        assert!(stream_b.try_recv().is_err());
+
+        // Nobody offered sampling
+        assert!(!router.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "sampling/createMessage",
+            "params": {}
+        })));
+
+        // Requests without a capability of their own go to anyone
+        assert!(router.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "ping"
+        })));
+        assert!(stream_b.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_router_broadcasts_notifications_and_drops_closed_sessions() {
+        let mut router = RequestRouter::default();
+        let mut stream_a = router.open_session("session-a".to_string());
+        drop(router.open_session("session-b".to_string()));
+
+        assert!(router.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/tools/list_changed"
+        })));
+        assert!(stream_a.try_recv().is_ok());
+        assert!(!router.sessions.contains_key("session-b"));
+
+        // With nobody left to answer it, a server request can't be delivered
+        drop(stream_a);
+        assert!(!router.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "id": "roots-1",
+            "method": "roots/list"
+        })));
+    }
+
+    #[test]
+    fn test_with_progress_token_keeps_other_meta() {
+        let rewritten = with_progress_token(
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"x","_meta":{"progressToken":"abc","other":true}}}"#,
+            json!(9),
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&rewritten).unwrap();
+        assert_eq!(json["params"]["_meta"]["progressToken"], 9);
+        assert_eq!(json["params"]["_meta"]["other"], true);
+        assert_eq!(json["params"]["name"], "x");
+    }
+
+    #[test]
+    fn test_with_id_keeps_other_fields() {
+        let rewritten = with_id(
+            r#"{"jsonrpc":"2.0","id":"client-1","method":"tools/call","params":{"name":"x"}}"#,
+            json!(42),
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&rewritten).unwrap();
+        assert_eq!(json["id"], 42);
+        assert_eq!(json["method"], "tools/call");
+        assert_eq!(json["params"]["name"], "x");
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_restores_client_ids_per_session() {
+        let router = SharedRequestRouter::default();
+        let logs = ServerLogSink::new("test");
+        let (tx_a, rx_a) = oneshot::channel();
+        let (tx_b, rx_b) = oneshot::channel();
+        let upstream_a =
+            router
+                .lock()
+                .await
+                .register(Some("session-a".to_string()), json!(1), tx_a);
+        let upstream_b =
+            router
+                .lock()
+                .await
+                .register(Some("session-b".to_string()), json!(1), tx_b);
+
+        // Responses arrive out of order
+        let response_b =
+            format!(r#"{{"jsonrpc":"2.0","id":{upstream_b},"result":{{"from":"b"}}}}"#);
+        let response_a =
+            format!(r#"{{"jsonrpc":"2.0","id":{upstream_a},"result":{{"from":"a"}}}}"#);
+        dispatch_stdout_line("test", &response_b, &router, &logs).await;
+        dispatch_stdout_line("test", &response_a, &router, &logs).await;
+
+        let json_a: serde_json::Value =
+            serde_json::from_str(&rx_a.await.unwrap().unwrap()).unwrap();
+        let json_b: serde_json::Value =
+            serde_json::from_str(&rx_b.await.unwrap().unwrap()).unwrap();
+        assert_eq!(json_a["id"], 1);
+        assert_eq!(json_a["result"]["from"], "a");
+        assert_eq!(json_b["id"], 1);
+        assert_eq!(json_b["result"]["from"], "b");
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_hands_back_server_messages() {
+        let router = SharedRequestRouter::default();
+        let logs = ServerLogSink::new("test");
+        let (tx, mut rx) = oneshot::channel();
+        let upstream_id = router.lock().await.register(None, json!(1), tx);
+
+        let output =
+            dispatch_stdout_line("test", "Server listening on stdio", &router, &logs).await;
+        assert!(output.is_none());
+        let notification = dispatch_stdout_line(
+            "test",
+            r#"{"jsonrpc":"2.0","method":"notifications/progress","params":{}}"#,
+            &router,
+            &logs,
+        )
+        .await;
+        assert_eq!(
+            notification.unwrap().message["method"],
+            "notifications/progress"
+        );
+        let server_request = dispatch_stdout_line(
+            "test",
+            &format!(r#"{{"jsonrpc":"2.0","id":{upstream_id},"method":"roots/list"}}"#),
+            &router,
+            &logs,
+        )
+        .await;
+        let server_request = server_request.unwrap();
+        assert_eq!(server_request.message["method"], "roots/list");
+        // No client session is open to answer it
+        assert!(!server_request.delivered);
+
+        assert!(rx.try_recv().is_err());
+        assert!(router.lock().await.take(upstream_id).is_some());
+
+        // Only the non-JSON line ends up in the server's logs
+        let (lines, _) = logs.page(None, 1, 10);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].stream, LogStream::Stdout);
+        assert_eq!(lines[0].line, "Server listening on stdio");
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_error_response_completes_request() {
+        let router = SharedRequestRouter::default();
+        let logs = ServerLogSink::new("test");
+        let (tx, rx) = oneshot::channel();
+        let upstream_id = router.lock().await.register(None, json!("req-7"), tx);
+
+        let response = format!(
+            r#"{{"jsonrpc":"2.0","id":{upstream_id},"error":{{"code":-32601,"message":"nope"}}}}"#
+        );
+        dispatch_stdout_line("test", &response, &router, &logs).await;
+
+        let json: serde_json::Value = serde_json::from_str(&rx.await.unwrap().unwrap()).unwrap();
+        assert_eq!(json["id"], "req-7");
+        assert_eq!(json["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn test_forward_raw_request_unknown_server() {
+        let manager = MCPServerManager::new();
+        let result = manager
+            .forward_raw_request(
+                "missing",
+                None,
+                r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#.to_string(),
+                None,
+            )
+            .await;
+        assert_eq!(result.unwrap_err(), "Server 'missing' not found");
+    }
+
+    fn test_server(name: &str, stdin_tx: mpsc::Sender<String>) -> MCPServer {
+        MCPServer {
+            name: name.to_string(),
+            command: "fake".to_string(),
+            args: Vec::new(),
+            server_type: ServerType::Process,
+            tools: Vec::new(),
+            resources: Vec::new(),
+            prompts: Vec::new(),
+            server_info: None,
+            stdin_tx: Some(stdin_tx),
+            request_router: SharedRequestRouter::default(),
+            status: ServerStatus::running(),
+            shutdown: None,
+            supervisor: None,
+            last_health_check: Instant::now(),
+            last_activity: Instant::now(),
+            ready: watch::Sender::new(false),
+            replicas: Vec::new(),
+            session_replicas: HashMap::new(),
+            timeouts: TimeoutConfig::default(),
+        }
+    }
+
+    /// Answer requests written to stdin like a server that only has (paginated) tools
+    fn spawn_fake_server(
+        mut stdin_rx: mpsc::Receiver<String>,
+        router: SharedRequestRouter,
+    ) -> Arc<TokioMutex<Vec<String>>> {
+        let received = Arc::new(TokioMutex::new(Vec::new()));
+        let received_clone = received.clone();
+        let logs = ServerLogSink::new("fake");
+
+        tokio::spawn(async move {
+            while let Some(line) = stdin_rx.recv().await {
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let method = request["method"].as_str().unwrap().to_string();
+                received_clone.lock().await.push(method.clone());
+
+                let result = match (method.as_str(), request["params"]["cursor"].as_str()) {
+                    ("initialize", _) => json!({
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": { "tools": { "listChanged": true } },
+                        "serverInfo": { "name": "fake", "version": "1.0.0" }
+                    }),
+                    ("tools/list", None) => json!({
+                        "tools": [{ "name": "first", "inputSchema": { "type": "object" } }],
+                        "nextCursor": "page-2"
+                    }),
+                    ("tools/list", Some("page-2")) => json!({
+                        "tools": [{ "name": "second", "inputSchema": { "type": "object" } }]
+                    }),
+                    ("ping", _) => json!({}),
+                    _ => continue,
+                };
+                let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+                dispatch_stdout_line("fake", &response.to_string(), &router, &logs).await;
+            }
+        });
+
+        received
+    }
+
+    #[tokio::test]
+    async fn test_initialize_server_fills_capability_cache() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let server = test_server("fake", stdin_tx.clone());
+        let client = UpstreamClient {
+            server_name: "fake".to_string(),
+            stdin_tx,
+            request_router: server.request_router.clone(),
+        };
+        let received = spawn_fake_server(stdin_rx, server.request_router.clone());
+        manager
+            .servers
+            .write()
+            .await
+            .insert("fake".to_string(), server);
+
+        initialize_server(
+            manager.servers.clone(),
+            client,
+            Arc::new(ServerLogSink::new("fake")),
+        )
+        .await;
+
+        let tools = manager.get_cached_tools("fake").await.unwrap();
+        let names: Vec<_> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(names, vec!["first", "second"]);
+        assert!(manager
+            .get_cached_resources("fake")
+            .await
+            .unwrap()
+            .is_empty());
+        let capabilities = manager.get_server_capabilities("fake").await.unwrap();
+        assert!(capabilities.tools.is_some());
+
+        // Lists the server doesn't advertise are never requested
+        assert_eq!(
+            *received.lock().await,
+            vec![
+                "initialize",
+                "notifications/initialized",
+                "tools/list",
+                "tools/list"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_initialize_is_answered_from_cache() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, mut stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let mut server = test_server("fake", stdin_tx);
+        server.server_info = Some(InitializeResult::default());
+        manager
+            .servers
+            .write()
+            .await
+            .insert("fake".to_string(), server);
+
+        let response = manager
+            .forward_raw_request(
+                "fake",
+                Some("session-a"),
+                r#"{"jsonrpc":"2.0","id":"client-1","method":"initialize","params":{}}"#
+                    .to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(json["id"], "client-1");
+        assert!(json["result"]["capabilities"].is_object());
+
+        let response = manager
+            .forward_raw_request(
+                "fake",
+                Some("session-a"),
+                r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#.to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, "");
+
+        // Neither reached the server process
+        assert!(stdin_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_initialize_waits_for_the_gateway_handshake() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, mut stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        manager
+            .servers
+            .write()
+            .await
+            .insert("fake".to_string(), test_server("fake", stdin_tx));
+
+        let request = manager.forward_raw_request(
+            "fake",
+            Some("session-a"),
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#
+                .to_string(),
+            None,
+        );
+        let handshake = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            update_server(&manager.servers, "fake", |server| {
+                server.server_info = Some(InitializeResult {
+                    protocol_version: ProtocolVersion::V_2025_03_26,
+                    ..Default::default()
+                });
+                server.ready.send_replace(true);
+            })
+            .await;
+        };
+        let (response, _) = tokio::join!(request, handshake);
+
+        // Answered from the cache in the version the client asked for
+        let json: serde_json::Value = serde_json::from_str(&response.unwrap()).unwrap();
+        assert_eq!(json["id"], 1);
+        assert_eq!(json["result"]["protocolVersion"], "2024-11-05");
+        assert!(stdin_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_negotiate_protocol_version() {
+        let upstream = ProtocolVersion::V_2025_03_26;
+        assert_eq!(
+            negotiate_protocol_version(Some("2024-11-05"), &upstream),
+            ProtocolVersion::V_2024_11_05
+        );
+        assert_eq!(
+            negotiate_protocol_version(Some("2025-03-26"), &upstream),
+            upstream
+        );
+        // Unknown, or newer than what the server speaks
+        assert_eq!(
+            negotiate_protocol_version(Some("2099-01-01"), &upstream),
+            upstream
+        );
+        assert_eq!(negotiate_protocol_version(None, &upstream), upstream);
+        assert_eq!(
+            negotiate_protocol_version(Some("2025-03-26"), &ProtocolVersion::V_2024_11_05),
+            ProtocolVersion::V_2024_11_05
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_answer_is_relayed_to_server() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, mut stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let mut server = test_server("fake", stdin_tx);
+        server.server_info = Some(InitializeResult::default());
+        let router = server.request_router.clone();
+        manager
+            .servers
+            .write()
+            .await
+            .insert("fake".to_string(), server);
+
+        // The client says it can list its roots
+        manager
+            .forward_raw_request(
+                "fake",
+                Some("session-a"),
+                r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"capabilities":{"roots":{}}}}"#
+                    .to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        let mut stream = manager
+            .open_session_stream("fake", "session-a")
+            .await
+            .unwrap();
+        router.lock().await.route_server_message(json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "roots/list"
+        }));
+        let forwarded: serde_json::Value =
+            serde_json::from_str(&stream.recv().await.unwrap()).unwrap();
+        assert_eq!(forwarded["method"], "roots/list");
+
+        let answer = r#"{"jsonrpc":"2.0","id":3,"result":{"roots":[]}}"#;
+        let response = manager
+            .forward_raw_request("fake", Some("session-a"), answer.to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(response, "");
+        assert_eq!(stdin_rx.recv().await.unwrap(), format!("{answer}\n"));
+
+        // A second answer to the same request isn't passed on
+        let result = manager
+            .forward_raw_request("fake", Some("session-a"), answer.to_string(), None)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_cancellation_is_forwarded_with_upstream_id() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, mut stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let server = test_server("fake", stdin_tx);
+        manager
+            .servers
+            .write()
+            .await
+            .insert("fake".to_string(), server);
+
+        let request = r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"slow"}}"#;
+        let cancel =
+            r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":7}}"#;
+        let (result, ()) = tokio::join!(
+            manager.forward_raw_request("fake", Some("session-a"), request.to_string(), None),
+            async {
+                let forwarded: serde_json::Value =
+                    serde_json::from_str(&stdin_rx.recv().await.unwrap()).unwrap();
+
+                // Another session can't cancel the request
+                let response = manager
+                    .forward_raw_request("fake", Some("session-b"), cancel.to_string(), None)
+                    .await
+                    .unwrap();
+                assert_eq!(response, "");
+                assert!(stdin_rx.try_recv().is_err());
+
+                manager
+                    .forward_raw_request("fake", Some("session-a"), cancel.to_string(), None)
+                    .await
+                    .unwrap();
+                let cancelled: serde_json::Value =
+                    serde_json::from_str(&stdin_rx.recv().await.unwrap()).unwrap();
+                assert_eq!(cancelled["method"], "notifications/cancelled");
+                assert_eq!(cancelled["params"]["requestId"], forwarded["id"]);
+            }
+        );
+        assert_eq!(result.unwrap_err(), REQUEST_CANCELLED);
+    }
+
+    #[tokio::test]
+    async fn test_server_process_gets_its_own_data_directory() {
+        let data_root = tempfile::tempdir().unwrap();
+        let manager = MCPServerManager::new();
+        manager.set_data_directory(data_root.path().to_path_buf());
+        let data_dir = ServerDataDir::new(data_root.path(), "isolated");
+
+        // The directory is set up before the process is spawned
+        let result = manager
+            .start_server(
+                "isolated".to_string(),
+                ServerConfig {
+                    transport: "stdio".to_string(),
+                    command: "archestra-test-missing-command".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(result.unwrap_err().contains("Failed to spawn"));
+        assert!(data_dir.home().is_dir());
+        assert!(data_dir.tmp().is_dir());
+
+        let result = manager
+            .start_server(
+                "isolated".to_string(),
+                ServerConfig {
+                    transport: "stdio".to_string(),
+                    command: "archestra-test-missing-command".to_string(),
+                    cwd: Some("missing".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(result.unwrap_err().starts_with("Working directory"));
+
+        manager.remove_server_data("isolated").unwrap();
+        assert!(!data_dir.root().exists());
+    }
+
+    #[tokio::test]
+    async fn test_unresolvable_secret_reference_fails_start() {
+        let manager = MCPServerManager::new();
+        let result = manager
+            .start_server(
+                "with-secret".to_string(),
+                ServerConfig {
+                    transport: "stdio".to_string(),
+                    command: "archestra-test-missing-command".to_string(),
+                    env: HashMap::from([(
+                        "GITHUB_TOKEN".to_string(),
+                        "secret://github_token".to_string(),
+                    )]),
+                    ..Default::default()
+                },
+            )
+            .await;
+        let error = result.unwrap_err();
+        assert!(error.starts_with("Failed to resolve secrets of MCP server 'with-secret'"));
+        assert!(manager
+            .log_sink("with-secret")
+            .await
+            .page(Some(LogStream::System), 1, 10)
+            .0[0]
+            .line
+            .contains("Failed to resolve secrets"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_concurrent_starts_spawn_the_server_once() {
+        let data_root = tempfile::tempdir().unwrap();
+        let manager = MCPServerManager::new();
+        manager.set_data_directory(data_root.path().to_path_buf());
+        let config = ServerConfig {
+            transport: "stdio".to_string(),
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "cat".to_string()],
+            ..Default::default()
+        };
+
+        let (first, second) = tokio::join!(
+            manager.start_server("twice".to_string(), config.clone()),
+            manager.start_server("twice".to_string(), config),
+        );
+        let error = match (first, second) {
+            (Ok(()), Err(e)) | (Err(e), Ok(())) => e,
+            results => panic!("exactly one start should succeed: {results:?}"),
+        };
+        assert!(error.contains("already running"), "{error}");
+        manager.stop_server("twice").await.unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_crash_report_has_the_last_stderr_lines() {
+        let data_root = tempfile::tempdir().unwrap();
+        let manager = MCPServerManager::new();
+        manager.set_data_directory(data_root.path().to_path_buf());
+        manager
+            .start_server(
+                "crashing".to_string(),
+                ServerConfig {
+                    transport: "stdio".to_string(),
+                    command: "sh".to_string(),
+                    args: vec![
+                        "-c".to_string(),
+                        "for i in 1 2 3; do echo line $i >&2; done; exit 3".to_string(),
+                    ],
+                    restart_policy: Some(RestartPolicy {
+                        max_restarts: 0,
+                        initial_backoff_ms: 0,
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let last_exit = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let status = manager.get_server_status("crashing").await.unwrap();
+                if status.state == ServerState::Crashed {
+                    break status.last_exit.unwrap();
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("server should be marked as crashed");
+        assert_eq!(last_exit.exit_code, Some(3));
+        assert_eq!(last_exit.stderr_tail, ["line 1", "line 2", "line 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_container_is_removed_when_server_stops() {
+        // Runs with docker or podman and an image that is already there, nothing is pulled
+        let image = std::env::var("ARCHESTRA_TEST_CONTAINER_IMAGE")
+            .unwrap_or_else(|_| "busybox".to_string());
+        let Ok(runtime) = container::detect_runtime("docker") else {
+            eprintln!("Skipping: neither docker nor podman is installed");
+            return;
+        };
+        let image_available = tokio::process::Command::new(&runtime)
+            .args(["image", "inspect", &image])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .is_ok_and(|status| status.success());
+        if !image_available {
+            eprintln!("Skipping: image '{image}' isn't available locally to {runtime}");
+            return;
+        }
+
+        let filter = "label=archestra.mcp-server=container-test".to_string();
+        let wait_for = |expected: bool| {
+            let (runtime, filter) = (runtime.clone(), filter.clone());
+            async move {
+                for _ in 0..100 {
+                    let exists = tokio::process::Command::new(&runtime)
+                        .args(["ps", "-a", "-q", "--filter", &filter])
+                        .output()
+                        .await
+                        .is_ok_and(|output| !output.stdout.is_empty());
+                    if exists == expected {
+                        return true;
+                    }
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                false
+            }
+        };
+
+        // The server's command picks the runtime, podman has to be asked for by name
+        let command = if Path::new(&runtime).ends_with("podman") {
+            "podman"
+        } else {
+            "docker"
+        };
+        let manager = MCPServerManager::new();
+        manager
+            .start_server(
+                "container-test".to_string(),
+                ServerConfig {
+                    transport: "docker".to_string(),
+                    command: command.to_string(),
+                    args: vec!["sleep".to_string(), "300".to_string()],
+                    container: Some(ContainerConfig {
+                        image,
+                        network: Some("none".to_string()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(wait_for(true).await);
+
+        manager.stop_server("container-test").await.unwrap();
+        assert!(wait_for(false).await);
+    }
+
+    #[tokio::test]
+    async fn test_container_server_needs_container_settings() {
+        let manager = MCPServerManager::new();
+        let result = manager
+            .start_server(
+                "no-image".to_string(),
+                ServerConfig {
+                    transport: "docker".to_string(),
+                    command: "docker".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(result
+            .unwrap_err()
+            .starts_with("Cannot start MCP server 'no-image'"));
+    }
+
+    #[test]
+    fn test_resolve_secret_references_keeps_plain_values() {
+        let (args, env) = resolve_secret_references(
+            vec!["--port=3000".to_string()],
+            HashMap::from([("DEBUG".to_string(), "1".to_string())]),
+        )
+        .unwrap();
+        assert_eq!(args, vec!["--port=3000"]);
+        assert_eq!(env["DEBUG"], "1");
+    }
+
+    #[test]
+    fn test_request_timeout_precedence() {
+        let config = TimeoutConfig {
+            default_ms: Some(60_000),
+            methods: HashMap::from([
+                ("tools/list".to_string(), 5_000),
+                ("tools/call".to_string(), 120_000),
+            ]),
+            tools: HashMap::from([("build".to_string(), 600_000)]),
+        };
+
+        let limit = request_timeout(&config, "tools/call", Some("build"), None);
+        assert_eq!(limit.duration, Duration::from_secs(600));
+        assert_eq!(limit.source, "timeout of tool 'build'");
+
+        let limit = request_timeout(&config, "tools/call", Some("search"), None);
+        assert_eq!(limit.duration, Duration::from_secs(120));
+        assert_eq!(limit.source, "timeout of method 'tools/call'");
+
+        let limit = request_timeout(&config, "resources/read", None, None);
+        assert_eq!(limit.duration, Duration::from_secs(60));
+        assert_eq!(limit.source, "server default timeout");
+
+        let limit = request_timeout(&config, "tools/list", None, Some(Duration::from_secs(1)));
+        assert_eq!(limit.duration, Duration::from_secs(1));
+        assert_eq!(limit.source, "timeout requested by the client");
+
+        let limit = request_timeout(&TimeoutConfig::default(), "tools/list", None, None);
+        assert_eq!(limit.duration, REQUEST_TIMEOUT);
+        assert_eq!(limit.source, "gateway default timeout");
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_request_names_the_limit() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, mut stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let mut server = test_server("fake", stdin_tx);
+        server.timeouts.tools.insert("slow".to_string(), 50);
+        manager
+            .servers
+            .write()
+            .await
+            .insert("fake".to_string(), server);
+
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"slow"}}"#;
+        let error = manager
+            .forward_raw_request("fake", Some("session-a"), request.to_string(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "Request timed out after 50ms (timeout of tool 'slow')"
+        );
+
+        // The server is told to stop working on it
+        let forwarded: serde_json::Value =
+            serde_json::from_str(&stdin_rx.recv().await.unwrap()).unwrap();
+        let cancelled: serde_json::Value =
+            serde_json::from_str(&stdin_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(cancelled["params"]["requestId"], forwarded["id"]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_request_is_cancelled_upstream() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, mut stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let server = test_server("fake", stdin_tx);
+        let router = server.request_router.clone();
+        manager
+            .servers
+            .write()
+            .await
+            .insert("fake".to_string(), server);
+
+        // The client goes away while the server is still working on the request
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"slow"}}"#;
+        let result = tokio::time::timeout(
+            Duration::from_millis(50),
+            manager.forward_raw_request("fake", Some("session-a"), request.to_string(), None),
+        )
+        .await;
+        assert!(result.is_err());
+
+        let forwarded: serde_json::Value =
+            serde_json::from_str(&stdin_rx.recv().await.unwrap()).unwrap();
+        let cancelled: serde_json::Value =
+            serde_json::from_str(&stdin_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(cancelled["method"], "notifications/cancelled");
+        assert_eq!(cancelled["params"]["requestId"], forwarded["id"]);
+        assert_eq!(cancelled["params"]["reason"], "Client disconnected");
+        assert!(!router.lock().await.has_pending_client_requests());
+    }
+
+    #[test]
+    fn test_cached_list_from_notification() {
+        assert_eq!(
+            CachedList::from_notification("notifications/tools/list_changed"),
+            Some(CachedList::Tools)
+        );
+        assert_eq!(
+            CachedList::from_notification("notifications/prompts/list_changed"),
+            Some(CachedList::Prompts)
+        );
+        assert_eq!(
+            CachedList::from_notification("notifications/resources/updated"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_restart_backoff_doubles_up_to_cap() {
+        let policy = RestartPolicy {
+            max_restarts: 10,
+            initial_backoff_ms: 500,
+        };
+        assert_eq!(restart_backoff(&policy, 1), Duration::from_millis(500));
+        assert_eq!(restart_backoff(&policy, 2), Duration::from_millis(1000));
+        assert_eq!(restart_backoff(&policy, 4), Duration::from_millis(4000));
+        assert_eq!(restart_backoff(&policy, 10), MAX_RESTART_BACKOFF);
+        assert_eq!(restart_backoff(&policy, u32::MAX), MAX_RESTART_BACKOFF);
+    }
+
+    #[test]
+    fn test_server_state_serializes_snake_case() {
+        let status = ServerStatus {
+            state: ServerState::Crashed,
+            restart_count: 5,
+            last_exit: Some(ExitInfo {
+                termination_reason: TerminationReason::MemoryLimit,
+                exit_code: Some(1),
+                stderr_tail: vec!["boom".to_string()],
+                exited_at: chrono::Utc::now(),
+            }),
+            health: ServerHealth::with_state(HealthState::Stopped),
+            sandbox_warnings: Vec::new(),
+        };
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["state"], "crashed");
+        assert_eq!(json["health"]["state"], "stopped");
+        assert_eq!(json["restart_count"], 5);
+        assert_eq!(json["last_exit"]["termination_reason"], "memory_limit");
+        assert_eq!(json["last_exit"]["exit_code"], 1);
+        assert_eq!(json["last_exit"]["stderr_tail"], json!(["boom"]));
+    }
+
+    #[tokio::test]
+    async fn test_update_server_reports_removed_server() {
+        let manager = MCPServerManager::new();
+        assert!(!update_server(&manager.servers, "missing", |_| {}).await);
+        assert!(manager.get_server_statuses().await.is_empty());
+    }
+
+    async fn pool_manager() -> MCPServerManager {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, _stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let mut server = test_server("pool", stdin_tx.clone());
+        server.replicas = (0..2)
+            .map(|_| Replica {
+                stdin_tx: Some(stdin_tx.clone()),
+                request_router: SharedRequestRouter::default(),
+                supervisor: None,
+            })
+            .collect();
+        manager
+            .servers
+            .write()
+            .await
+            .insert("pool".to_string(), server);
+        manager
+    }
+
+    async fn picked_replica(manager: &MCPServerManager, session_id: Option<&str>) -> usize {
+        let (_, picked) = manager.pick_replica("pool", session_id).await.unwrap();
+        let servers = manager.servers.read().await;
+        let replica = servers["pool"]
+            .request_routers()
+            .position(|request_router| Arc::ptr_eq(request_router, &picked));
+        replica.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pick_replica_balances_and_keeps_sessions() {
+        let manager = pool_manager().await;
+        let process = |replica| {
+            let servers = &manager.servers;
+            async move { servers.read().await["pool"].process_channel(replica) }
+        };
+
+        // Keep the first process busy with a slow request
+        let (response_tx, _response_rx) = oneshot::channel();
+        process(0)
+            .await
+            .1
+            .lock()
+            .await
+            .register(None, json!(1), response_tx);
+
+        let pinned = picked_replica(&manager, Some("session-a")).await;
+        assert_ne!(pinned, 0);
+
+        // The session's process gets busy too, the session stays on it anyway
+        let (response_tx, _response_rx) = oneshot::channel();
+        process(pinned).await.1.lock().await.register(
+            Some("session-a".to_string()),
+            json!(1),
+            response_tx,
+        );
+        assert_eq!(picked_replica(&manager, Some("session-a")).await, pinned);
+
+        // Stateless requests go to the one process that is still idle
+        let idle = 3 - pinned;
+        assert_eq!(picked_replica(&manager, None).await, idle);
+
+        // A process that is being restarted gets nothing
+        manager
+            .servers
+            .write()
+            .await
+            .get_mut("pool")
+            .unwrap()
+            .set_stdin_tx(idle, None);
+        assert_ne!(picked_replica(&manager, None).await, idle);
+    }
+
+    #[tokio::test]
+    async fn test_session_of_a_dead_replica_moves_with_its_stream() {
+        let manager = pool_manager().await;
+        let pinned = picked_replica(&manager, Some("session-a")).await;
+        let mut stream = manager
+            .open_session_stream("pool", "session-a")
+            .await
+            .unwrap();
+
+        // The session's process exits
+        update_server(&manager.servers, "pool", |server| {
+            server.set_stdin_tx(pinned, None);
+            server.unpin_sessions(pinned);
+        })
+        .await;
+        assert!(manager.servers.read().await["pool"]
+            .session_replicas
+            .is_empty());
+
+        let moved = picked_replica(&manager, Some("session-a")).await;
+        assert_ne!(moved, pinned);
+
+        // What the new process sends reaches the stream the client already listens to
+        let request_router = manager.servers.read().await["pool"]
+            .process_channel(moved)
+            .1;
+        request_router
+            .lock()
+            .await
+            .route_server_message(json!({"jsonrpc": "2.0", "method": "notifications/message"}));
+        assert!(stream.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_closed_session_is_forgotten() {
+        let manager = pool_manager().await;
+        let pinned = picked_replica(&manager, Some("session-a")).await;
+        let mut stream = manager
+            .open_session_stream("pool", "session-a")
+            .await
+            .unwrap();
+
+        manager.close_session("pool", "session-a").await.unwrap();
+        assert!(manager.servers.read().await["pool"]
+            .session_replicas
+            .is_empty());
+        let request_router = manager.servers.read().await["pool"]
+            .process_channel(pinned)
+            .1;
+        assert!(!request_router
+            .lock()
+            .await
+            .sessions
+            .contains_key("session-a"));
+        // The stream ends
+        assert!(stream.recv().await.is_none());
+
+        assert!(manager.close_session("missing", "session-a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_all_kills_servers_after_grace_period() {
+        let manager = MCPServerManager::new();
+
+        // One supervisor exits as soon as it's told to, the other one never does
+        for (name, stuck) in [("quick", false), ("stuck", true)] {
+            let (stdin_tx, _stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+            let mut server = test_server(name, stdin_tx);
+            let shutdown = CancellationToken::new();
+            server.shutdown = Some(shutdown.clone());
+            server.supervisor = Some(tokio::spawn(async move {
+                shutdown.cancelled().await;
+                if stuck {
+                    std::future::pending::<()>().await;
+                }
+            }));
+            manager
+                .servers
+                .write()
+                .await
+                .insert(name.to_string(), server);
+        }
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            manager.shutdown_all(Duration::from_millis(50)),
+        )
+        .await
+        .expect("shutdown_all should not wait for the stuck server");
+        assert!(manager.get_server_statuses().await.is_empty());
+    }
+
+    #[test]
+    fn test_parse_shutdown_grace_period() {
+        assert_eq!(
+            parse_shutdown_grace_period(None),
+            DEFAULT_SHUTDOWN_GRACE_PERIOD
+        );
+        assert_eq!(
+            parse_shutdown_grace_period(Some("250")),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            parse_shutdown_grace_period(Some("soon")),
+            DEFAULT_SHUTDOWN_GRACE_PERIOD
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_server_uses_configured_grace_period() {
+        let mut manager = MCPServerManager::new();
+        manager.shutdown_grace_period = Duration::from_millis(50);
+
+        // A supervisor that never finishes has to be killed after the grace period
+        let (stdin_tx, _stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let mut server = test_server("stuck", stdin_tx);
+        server.supervisor = Some(tokio::spawn(std::future::pending()));
+        manager
+            .servers
+            .write()
+            .await
+            .insert("stuck".to_string(), server);
+
+        let started = Instant::now();
+        manager.stop_server("stuck").await.unwrap();
+        assert!(started.elapsed() < DEFAULT_SHUTDOWN_GRACE_PERIOD);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    /// Answer like a remote server without tools, counting the requests it gets
+    async fn fake_remote_post(
+        axum::extract::State(received): axum::extract::State<Arc<TokioMutex<Vec<String>>>>,
+        body: String,
+    ) -> axum::response::Response {
+        use axum::response::IntoResponse;
+
+        let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        received.lock().await.push(method.clone());
+        let result = match method.as_str() {
+            "initialize" => json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "sleepy", "version": "1.0.0" }
+            }),
+            "tools/list" => json!({ "tools": [] }),
+            _ => return axum::http::StatusCode::ACCEPTED.into_response(),
+        };
+        axum::Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            .into_response()
+    }
+
+    /// Serve `fake_remote_post`, returning its URL
+    async fn spawn_fake_remote(received: Arc<TokioMutex<Vec<String>>>) -> String {
+        let app = axum::Router::new()
+            .route("/mcp", axum::routing::post(fake_remote_post))
+            .with_state(received);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_remote_server_logs_are_persisted(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let url = spawn_fake_remote(Arc::default()).await;
+        let manager = MCPServerManager::new();
+        manager.set_log_database(db.clone());
+        manager
+            .start_server(
+                "remote".to_string(),
+                ServerConfig {
+                    transport: "http".to_string(),
+                    args: vec![url],
+                    log_retention_days: Some(7),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        manager
+            .log_sink("remote")
+            .await
+            .push(LogStream::System, "persist me");
+        for _ in 0..50 {
+            let (logs, _) = crate::models::mcp_server_log::Model::get_server_logs(
+                &db,
+                "remote",
+                Some(LogStream::System),
+                1,
+                10,
+            )
+            .await
+            .unwrap();
+            if logs.iter().any(|log| log.line == "persist me") {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("log line was never persisted");
+    }
+
+    #[tokio::test]
+    async fn test_lazy_server_starts_on_first_request() {
+        let received = Arc::new(TokioMutex::new(Vec::new()));
+        let url = spawn_fake_remote(received.clone()).await;
+
+        let manager = MCPServerManager::new();
+        manager
+            .activate_server(
+                "sleepy".to_string(),
+                ServerConfig {
+                    transport: "http".to_string(),
+                    args: vec![url],
+                    activation: Activation::Lazy,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let status = manager.get_server_status("sleepy").await.unwrap();
+        assert_eq!(status.state, ServerState::Idle);
+        assert!(received.lock().await.is_empty());
+
+        // The client's handshake is answered once the gateway's own one completed
+        let response = manager
+            .forward_raw_request(
+                "sleepy",
+                Some("session-a"),
+                r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["result"]["serverInfo"]["name"], "sleepy");
+        let status = manager.get_server_status("sleepy").await.unwrap();
+        assert_eq!(status.state, ServerState::Running);
+        assert_eq!(
+            received.lock().await.first().map(String::as_str),
+            Some("initialize")
+        );
+
+        // Explicitly stopped, it stays stopped
+        manager.stop_server("sleepy").await.unwrap();
+        assert!(manager.get_server_status("sleepy").await.is_none());
+        assert!(manager
+            .forward_raw_request(
+                "sleepy",
+                None,
+                r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#.to_string(),
+                None,
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_running_lazy_server_does_not_wait_for_other_starts() {
+        let manager = MCPServerManager::new();
+        for name in ["awake", "installing"] {
+            manager
+                .lazy_servers
+                .write()
+                .await
+                .insert(name.to_string(), ServerConfig::default());
+        }
+        let (stdin_tx, _stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let server = test_server("awake", stdin_tx);
+        server.ready.send_replace(true);
+        manager
+            .servers
+            .write()
+            .await
+            .insert("awake".to_string(), server);
+
+        // Both servers are in the middle of being started
+        let mut held = Vec::new();
+        for name in ["awake", "installing"] {
+            let start_lock = manager.start_lock(name);
+            held.push(start_lock.lock_owned().await);
+        }
+
+        tokio::time::timeout(Duration::from_secs(1), manager.ensure_started("awake"))
+            .await
+            .expect("a running server should not wait for a start lock")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_server_is_stopped() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, _stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let mut server = test_server("idler", stdin_tx);
+        server.tools = vec![serde_json::from_value(
+            json!({ "name": "search", "inputSchema": { "type": "object" } }),
+        )
+        .unwrap()];
+        let request_router = server.request_router.clone();
+        manager
+            .servers
+            .write()
+            .await
+            .insert("idler".to_string(), server);
+
+        // A client request that is still running keeps the server alive
+        let (response_tx, _response_rx) = oneshot::channel();
+        let upstream_id = request_router.lock().await.register(
+            Some("session-a".to_string()),
+            json!(1),
+            response_tx,
+        );
+        let watcher = tokio::spawn(stop_when_idle(
+            manager.servers.clone(),
+            manager.idle_lists.clone(),
+            "idler".to_string(),
+            Duration::from_millis(50),
+            DEFAULT_SHUTDOWN_GRACE_PERIOD,
+            CancellationToken::new(),
+        ));
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(manager.get_server_status("idler").await.is_some());
+
+        request_router.lock().await.take(upstream_id);
+        tokio::time::timeout(Duration::from_secs(5), watcher)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(manager.get_server_status("idler").await.is_none());
+
+        // Its tools are still listed until it starts again
+        let tools = manager.get_cached_tools("idler").await.unwrap();
+        assert_eq!(tools[0].name, "search");
+        manager.stop_server("idler").await.unwrap();
+        assert!(manager.get_cached_tools("idler").await.is_none());
+    }
+
+    #[test]
+    fn test_record_ping_transitions() {
+        let config = HealthCheckConfig {
+            degraded_latency_ms: 100,
+            unresponsive_after: 2,
+            ..Default::default()
+        };
+        let mut health = ServerHealth::with_state(HealthState::Starting);
+
+        // Failures while starting up don't count as degraded yet
+        health.record_ping(None, &config);
+        assert_eq!(health.state, HealthState::Starting);
+        assert_eq!(health.consecutive_failures, 1);
+
+        health.record_ping(Some(Duration::from_millis(10)), &config);
+        assert_eq!(health.state, HealthState::Healthy);
+        assert_eq!(health.latency_ms, Some(10));
+        assert_eq!(health.consecutive_failures, 0);
+        assert!(health.last_checked_at.is_some());
+
+        health.record_ping(Some(Duration::from_millis(250)), &config);
+        assert_eq!(health.state, HealthState::Degraded);
+
+        health.record_ping(Some(Duration::from_millis(10)), &config);
+        health.record_ping(None, &config);
+        assert_eq!(health.state, HealthState::Degraded);
+        health.record_ping(None, &config);
+        assert_eq!(health.state, HealthState::Unresponsive);
+        assert_eq!(health.consecutive_failures, 2);
+
+        health.record_ping(Some(Duration::from_millis(10)), &config);
+        assert_eq!(health.state, HealthState::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_pings_server() {
+        let manager = MCPServerManager::new();
+        let (stdin_tx, stdin_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let server = test_server("pinged", stdin_tx);
+        let received = spawn_fake_server(stdin_rx, server.request_router.clone());
+        manager
+            .servers
+            .write()
+            .await
+            .insert("pinged".to_string(), server);
+
+        let shutdown = CancellationToken::new();
+        tokio::spawn(check_server_health(
+            manager.servers.clone(),
+            "pinged".to_string(),
+            HealthCheckConfig {
+                interval_seconds: 1,
+                ..Default::default()
+            },
+            shutdown.clone(),
+        ));
+
+        let health = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let status = manager.get_server_status("pinged").await.unwrap();
+                if status.health.state != HealthState::Starting {
+                    return status.health;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+        shutdown.cancel();
+
+        assert_eq!(health.state, HealthState::Healthy);
+        assert!(health.latency_ms.is_some());
+        assert_eq!(*received.lock().await, vec!["ping"]);
+    }
 }
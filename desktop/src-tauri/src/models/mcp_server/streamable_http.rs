@@ -0,0 +1,722 @@
+//! Client side of the Streamable HTTP transport for remote MCP servers.
+//!
+//! Messages are written to the transport the same way they are written to a
+//! process's stdin, and whatever the server sends back, as a JSON body or as
+//! server-sent events, is dispatched like a line of process stdout.
+
+use super::sandbox::{dispatch_stdout_line, ServerMessage, SharedRequestRouter};
+use crate::models::mcp_server_log::{LogStream, ServerLogSink};
+use futures_util::StreamExt;
+use reqwest::{header, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify, RwLock};
+use tokio_util::sync::CancellationToken;
+
+const SESSION_ID_HEADER: &str = "Mcp-Session-Id";
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+const CHANNEL_CAPACITY: usize = 100;
+// How often a broken response stream is picked up again before giving up on the request
+const MAX_RESUME_ATTEMPTS: u32 = 3;
+const DEFAULT_RETRY: Duration = Duration::from_secs(1);
+
+/// A single server-sent event
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+    pub retry: Option<u64>,
+}
+
+/// Incremental parser for a `text/event-stream` body
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: SseEvent,
+    has_data: bool,
+}
+
+impl SseParser {
+    /// Feed the next chunk of the stream, returning the events it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                let event = std::mem::take(&mut self.event);
+                if std::mem::take(&mut self.has_data) || event != SseEvent::default() {
+                    events.push(event);
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "data" => {
+                    if self.has_data {
+                        self.event.data.push('\n');
+                    }
+                    self.event.data.push_str(value);
+                    self.has_data = true;
+                }
+                "event" => self.event.event = Some(value.to_string()),
+                "id" if !value.contains('\0') => self.event.id = Some(value.to_string()),
+                "retry" => self.event.retry = value.parse().ok(),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// What was read from one response stream
+#[derive(Debug, Default)]
+struct StreamOutcome {
+    answered: bool,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+/// Connection to a remote MCP server speaking the Streamable HTTP transport
+pub struct HttpTransport {
+    server_name: String,
+    url: String,
+    headers: HashMap<String, String>,
+    http_client: reqwest::Client,
+    request_router: SharedRequestRouter,
+    logs: Arc<ServerLogSink>,
+    messages_tx: mpsc::UnboundedSender<ServerMessage>,
+    // Assigned by the server when it answers initialize
+    session_id: RwLock<Option<String>>,
+    session_expired: Notify,
+    listening: AtomicBool,
+    shutdown: CancellationToken,
+}
+
+impl HttpTransport {
+    /// Start the transport, returning the channel to write messages to and the
+    /// notifications and requests the server sends on its own
+    pub fn start(
+        server_name: &str,
+        url: String,
+        headers: HashMap<String, String>,
+        http_client: reqwest::Client,
+        request_router: SharedRequestRouter,
+        logs: Arc<ServerLogSink>,
+        shutdown: CancellationToken,
+    ) -> (
+        Arc<Self>,
+        mpsc::Sender<String>,
+        mpsc::UnboundedReceiver<ServerMessage>,
+    ) {
+        let (messages_tx, messages) = mpsc::unbounded_channel();
+        let transport = Arc::new(Self {
+            server_name: server_name.to_string(),
+            url,
+            headers,
+            http_client,
+            request_router,
+            logs,
+            messages_tx,
+            session_id: RwLock::new(None),
+            session_expired: Notify::new(),
+            listening: AtomicBool::new(false),
+            shutdown,
+        });
+
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<String>(CHANNEL_CAPACITY);
+        let writer = transport.clone();
+        tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    message = outgoing_rx.recv() => match message {
+                        Some(message) => message,
+                        None => return,
+                    },
+                    _ = writer.shutdown.cancelled() => return,
+                };
+
+                // Requests may take long, everything else is posted in order
+                let is_request = serde_json::from_str::<serde_json::Value>(&message)
+                    .map(|json| json.get("method").is_some() && json.get("id").is_some())
+                    .unwrap_or(false);
+                if is_request {
+                    tokio::spawn(writer.clone().post(message));
+                } else {
+                    writer.clone().post(message).await;
+                }
+            }
+        });
+
+        (transport, outgoing_tx, messages)
+    }
+
+    /// Resolves when the server forgot our session and a new handshake is needed
+    pub async fn session_expired(&self) {
+        self.session_expired.notified().await;
+    }
+
+    /// Tell the server the session is over
+    pub async fn end_session(&self) {
+        let Some(session_id) = self.session_id.write().await.take() else {
+            return;
+        };
+
+        let request = self
+            .with_headers(self.http_client.delete(&self.url))
+            .header(SESSION_ID_HEADER, &session_id);
+        match request.send().await {
+            Ok(response)
+                if response.status().is_success()
+                    || response.status() == StatusCode::METHOD_NOT_ALLOWED => {}
+            Ok(response) => eprintln!(
+                "⚠️ MCP [{}] Ending session returned {}",
+                self.server_name,
+                response.status()
+            ),
+            Err(e) => eprintln!("⚠️ MCP [{}] Failed to end session: {e}", self.server_name),
+        }
+    }
+
+    fn with_headers(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        request
+    }
+
+    async fn with_session(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let request = self.with_headers(request);
+        match self.session_id.read().await.as_ref() {
+            Some(session_id) => request.header(SESSION_ID_HEADER, session_id),
+            None => request,
+        }
+    }
+
+    /// A 404 for a request carrying a session id means the server dropped the session
+    async fn check_session(&self, status: StatusCode) -> bool {
+        if status != StatusCode::NOT_FOUND || self.session_id.write().await.take().is_none() {
+            return false;
+        }
+        eprintln!("⚠️ MCP [{}] Upstream session expired", self.server_name);
+        self.logs
+            .push(LogStream::System, "Upstream session expired");
+        self.session_expired.notify_one();
+        true
+    }
+
+    /// POST a single message and dispatch whatever comes back
+    async fn post(self: Arc<Self>, message: String) {
+        let json = serde_json::from_str::<serde_json::Value>(&message).unwrap_or_default();
+        let method = json
+            .get("method")
+            .and_then(|m| m.as_str())
+            .map(|m| m.to_string());
+        let request_id = method.as_ref().and_then(|_| json.get("id").cloned());
+
+        let request = self
+            .with_session(self.http_client.post(&self.url))
+            .await
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, "application/json, text/event-stream")
+            .body(message);
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                return self
+                    .fail(request_id, format!("HTTP request failed: {e}"))
+                    .await
+            }
+        };
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            *self.session_id.write().await = Some(session_id.to_string());
+        }
+
+        let status = response.status();
+        if self.check_session(status).await {
+            return self
+                .fail(request_id, "Upstream session expired".to_string())
+                .await;
+        }
+
+        if is_event_stream(&response) {
+            let mut outcome = self.read_stream(response, request_id.as_ref()).await;
+
+            // Pick a broken stream up again where it left off
+            let mut attempts = 0;
+            while request_id.is_some() && !outcome.answered && attempts < MAX_RESUME_ATTEMPTS {
+                let Some(last_event_id) = outcome.last_event_id.clone() else {
+                    break;
+                };
+                attempts += 1;
+                tokio::time::sleep(outcome.retry.unwrap_or(DEFAULT_RETRY)).await;
+
+                let response = match self.open_stream(Some(&last_event_id)).await {
+                    Ok(Some(response)) => response,
+                    _ => break,
+                };
+                let resumed = self.read_stream(response, request_id.as_ref()).await;
+                outcome = StreamOutcome {
+                    answered: resumed.answered,
+                    last_event_id: resumed.last_event_id.or(outcome.last_event_id),
+                    retry: resumed.retry.or(outcome.retry),
+                };
+            }
+
+            if request_id.is_some() && !outcome.answered {
+                self.fail(
+                    request_id,
+                    "Stream ended before the server responded".to_string(),
+                )
+                .await;
+            }
+        } else {
+            let body = match response.text().await {
+                Ok(body) => body,
+                Err(e) => {
+                    return self
+                        .fail(request_id, format!("Failed to read response: {e}"))
+                        .await
+                }
+            };
+
+            let answered = self.dispatch_body(&body).await;
+            if request_id.is_some() && !answered {
+                self.fail(request_id, format!("HTTP {status}: {body}"))
+                    .await;
+            } else if !status.is_success() {
+                eprintln!("⚠️ MCP [{}] HTTP returned {status}", self.server_name);
+            }
+        }
+
+        // The server can push messages outside of requests once the session is up
+        if method.as_deref() == Some("notifications/initialized")
+            && status.is_success()
+            && !self.listening.swap(true, Ordering::SeqCst)
+        {
+            tokio::spawn(self.clone().listen());
+        }
+    }
+
+    /// Keep a GET stream open for messages the server sends outside of requests
+    async fn listen(self: Arc<Self>) {
+        let mut last_event_id: Option<String> = None;
+        let mut retry = DEFAULT_RETRY;
+
+        loop {
+            match self.open_stream(last_event_id.as_deref()).await {
+                Ok(Some(response)) => {
+                    let outcome = self.read_stream(response, None).await;
+                    last_event_id = outcome.last_event_id.or(last_event_id);
+                    retry = outcome.retry.unwrap_or(retry);
+                }
+                Ok(None) => {
+                    println!(
+                        "MCP [{}] Server only sends messages in response to requests",
+                        self.server_name
+                    );
+                    self.listening.store(false, Ordering::SeqCst);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("⚠️ MCP [{}] Message stream failed: {e}", self.server_name);
+                    // Event ids don't carry over to another session
+                    last_event_id = None;
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(retry) => {}
+                _ = self.shutdown.cancelled() => return,
+            }
+        }
+    }
+
+    /// Open a GET event stream, `None` if the server doesn't offer one
+    async fn open_stream(&self, last_event_id: Option<&str>) -> Result<Option<Response>, String> {
+        let mut request = self
+            .with_session(self.http_client.get(&self.url))
+            .await
+            .header(header::ACCEPT, "text/event-stream");
+        if let Some(last_event_id) = last_event_id {
+            request = request.header(LAST_EVENT_ID_HEADER, last_event_id);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("HTTP request failed: {e}"))?;
+        let status = response.status();
+        if status == StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(None);
+        }
+        if self.check_session(status).await || !status.is_success() {
+            return Err(format!("HTTP {status}"));
+        }
+        Ok(Some(response))
+    }
+
+    /// Dispatch every event of a response stream, noting whether the response to
+    /// the given request was among them
+    async fn read_stream(
+        &self,
+        response: Response,
+        request_id: Option<&serde_json::Value>,
+    ) -> StreamOutcome {
+        let mut outcome = StreamOutcome::default();
+        let mut parser = SseParser::default();
+        let mut stream = response.bytes_stream();
+
+        loop {
+            let chunk = tokio::select! {
+                chunk = stream.next() => chunk,
+                _ = self.shutdown.cancelled() => return outcome,
+            };
+            let chunk = match chunk {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    eprintln!("⚠️ MCP [{}] Event stream broke: {e}", self.server_name);
+                    return outcome;
+                }
+                None => return outcome,
+            };
+
+            for event in parser.feed(&chunk) {
+                if event.id.is_some() {
+                    outcome.last_event_id = event.id.clone();
+                }
+                if let Some(retry) = event.retry {
+                    outcome.retry = Some(Duration::from_millis(retry));
+                }
+                if event.data.is_empty() {
+                    continue;
+                }
+                if request_id.is_some_and(|id| is_response_to(&event.data, id)) {
+                    outcome.answered = true;
+                }
+                self.dispatch(&event.data).await;
+            }
+
+            if outcome.answered {
+                return outcome;
+            }
+        }
+    }
+
+    /// Dispatch a JSON response body (a single message or a batch), returns whether it
+    /// contained a response
+    async fn dispatch_body(&self, body: &str) -> bool {
+        let messages = match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(serde_json::Value::Array(messages)) => messages,
+            Ok(message) => vec![message],
+            Err(_) => return false,
+        };
+
+        let mut answered = false;
+        for message in messages {
+            answered |= message.get("result").is_some() || message.get("error").is_some();
+            self.dispatch(&message.to_string()).await;
+        }
+        answered
+    }
+
+    async fn dispatch(&self, message: &str) {
+        if let Some(message) =
+            dispatch_stdout_line(&self.server_name, message, &self.request_router, &self.logs).await
+        {
+            let _ = self.messages_tx.send(message);
+        }
+    }
+
+    /// Answer a request that didn't make it to the server with an error
+    async fn fail(&self, request_id: Option<serde_json::Value>, error: String) {
+        eprintln!("❌ MCP [{}] {error}", self.server_name);
+        self.logs.push(LogStream::System, &error);
+
+        if let Some(id) = request_id {
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32603, "message": error },
+            });
+            self.dispatch(&response.to_string()).await;
+        }
+    }
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/event-stream"))
+}
+
+fn is_response_to(data: &str, request_id: &serde_json::Value) -> bool {
+    serde_json::from_str::<serde_json::Value>(data).is_ok_and(|json| {
+        json.get("id") == Some(request_id)
+            && (json.get("result").is_some() || json.get("error").is_some())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mcp_server::sandbox::MCPServerManager;
+    use crate::models::mcp_server::ServerConfig;
+    use axum::{
+        extract::State,
+        http::{HeaderMap, StatusCode as AxumStatusCode},
+        response::{IntoResponse, Response as AxumResponse},
+        routing::post,
+        Router,
+    };
+    use serde_json::json;
+    use tokio::sync::Mutex as TokioMutex;
+
+    /// What a fake upstream server was sent: method (or HTTP verb) and session header
+    type Received = Arc<TokioMutex<Vec<(String, Option<String>)>>>;
+
+    fn session_header(headers: &HeaderMap) -> Option<String> {
+        headers
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string())
+    }
+
+    fn sse(body: String) -> AxumResponse {
+        ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response()
+    }
+
+    fn event(id: &str, message: serde_json::Value) -> String {
+        format!("id: {id}\nevent: message\ndata: {message}\n\n")
+    }
+
+    async fn fake_post(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: String,
+    ) -> AxumResponse {
+        let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let method = request["method"].as_str().unwrap_or("response").to_string();
+        received
+            .lock()
+            .await
+            .push((method.clone(), session_header(&headers)));
+        let id = request["id"].clone();
+
+        match method.as_str() {
+            "initialize" => {
+                let result = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": {
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "remote", "version": "1.0.0" }
+                    }
+                });
+                ([(SESSION_ID_HEADER, "upstream-1")], axum::Json(result)).into_response()
+            }
+            "tools/list" => sse(event(
+                "list-1",
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": { "tools": [{ "name": "echo", "inputSchema": { "type": "object" } }] }
+                }),
+            )),
+            // Report progress, then drop the stream before the response, the event id
+            // carries the request id for the resumed stream to answer
+            "tools/call" => sse(event(
+                &format!("call-{id}-1"),
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": { "progressToken": request["params"]["_meta"]["progressToken"], "progress": 1 }
+                }),
+            )),
+            _ => AxumStatusCode::ACCEPTED.into_response(),
+        }
+    }
+
+    async fn fake_get(State(received): State<Received>, headers: HeaderMap) -> AxumResponse {
+        let last_event_id = headers
+            .get(LAST_EVENT_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        received.lock().await.push((
+            format!("GET {}", last_event_id.clone().unwrap_or_default()),
+            session_header(&headers),
+        ));
+
+        // The rest of the tools/call stream, answering the request
+        let call_id = last_event_id
+            .as_deref()
+            .and_then(|event_id| event_id.strip_prefix("call-"))
+            .and_then(|rest| rest.strip_suffix("-1"))
+            .and_then(|id| serde_json::from_str::<serde_json::Value>(id).ok());
+        match call_id {
+            Some(id) => sse(event(
+                &format!("call-{id}-2"),
+                json!({ "jsonrpc": "2.0", "id": id, "result": { "content": [] } }),
+            )),
+            None => AxumStatusCode::METHOD_NOT_ALLOWED.into_response(),
+        }
+    }
+
+    async fn fake_delete(State(received): State<Received>, headers: HeaderMap) -> AxumStatusCode {
+        received
+            .lock()
+            .await
+            .push(("DELETE".to_string(), session_header(&headers)));
+        AxumStatusCode::OK
+    }
+
+    async fn start_fake_server() -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/mcp", post(fake_post).get(fake_get).delete(fake_delete))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn test_remote_server_session_lifecycle() {
+        let (url, received) = start_fake_server().await;
+        let manager = MCPServerManager::new();
+        manager
+            .start_server(
+                "remote".to_string(),
+                ServerConfig {
+                    transport: "http".to_string(),
+                    command: "http".to_string(),
+                    args: vec![url],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // The gateway runs the handshake and caches the tools on its own
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while manager
+                .get_cached_tools("remote")
+                .await
+                .is_none_or(|tools| tools.is_empty())
+            {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // A tool call whose response stream breaks is resumed with Last-Event-ID
+        let mut stream = manager
+            .open_session_stream("remote", "session-a")
+            .await
+            .unwrap();
+        let response = manager
+            .forward_raw_request(
+                "remote",
+                Some("session-a"),
+                r#"{"jsonrpc":"2.0","id":"call","method":"tools/call","params":{"name":"echo","_meta":{"progressToken":"p"}}}"#
+                    .to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["id"], "call");
+        assert!(response["result"]["content"].is_array());
+
+        let progress: serde_json::Value =
+            serde_json::from_str(&stream.recv().await.unwrap()).unwrap();
+        assert_eq!(progress["params"]["progressToken"], "p");
+
+        manager.stop_server("remote").await.unwrap();
+
+        let received = received.lock().await.clone();
+        let upstream_session = Some("upstream-1".to_string());
+        assert_eq!(received[0], ("initialize".to_string(), None));
+        assert!(received.contains(&("tools/list".to_string(), upstream_session.clone())));
+        assert!(received
+            .iter()
+            .any(|(what, session)| what.starts_with("GET call-") && session == &upstream_session));
+        assert_eq!(
+            received.last().unwrap(),
+            &("DELETE".to_string(), upstream_session)
+        );
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+
+        assert!(parser.feed(b"id: 1\nevent: mess").is_empty());
+        assert!(parser.feed(b"age\ndata: {\"a\":").is_empty());
+        let events = parser.feed(b"1}\r\n\r\n: keep-alive\n\ndata: x\n");
+
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                id: Some("1".to_string()),
+                event: Some("message".to_string()),
+                data: r#"{"a":1}"#.to_string(),
+                retry: None,
+            }]
+        );
+        assert_eq!(parser.feed(b"\n")[0].data, "x");
+    }
+
+    #[test]
+    fn test_sse_parser_joins_data_lines_and_reads_retry() {
+        let mut parser = SseParser::default();
+        let events = parser.feed(b"retry: 2500\ndata: first\ndata:second\n\n");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "first\nsecond");
+        assert_eq!(events[0].retry, Some(2500));
+        assert_eq!(events[0].id, None);
+    }
+
+    #[test]
+    fn test_is_response_to_matches_id_and_kind() {
+        let id = serde_json::json!(4);
+        assert!(is_response_to(
+            r#"{"jsonrpc":"2.0","id":4,"result":{}}"#,
+            &id
+        ));
+        assert!(!is_response_to(
+            r#"{"jsonrpc":"2.0","id":5,"result":{}}"#,
+            &id
+        ));
+        assert!(!is_response_to(
+            r#"{"jsonrpc":"2.0","id":4,"method":"roots/list"}"#,
+            &id
+        ));
+    }
+}
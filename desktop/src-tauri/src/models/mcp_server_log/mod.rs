@@ -0,0 +1,373 @@
+use crate::models::secret;
+use sea_orm::entity::prelude::*;
+use sea_orm::{PaginatorTrait, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use utoipa::ToSchema;
+
+/// Lines kept in memory per server
+const LOG_BUFFER_LINES: usize = 1000;
+const LIVE_CHANNEL_CAPACITY: usize = 256;
+/// Lines written to the database in a single insert
+const PERSIST_BATCH_SIZE: usize = 100;
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[sea_orm(table_name = "mcp_server_logs")]
+#[schema(as = MCPServerLog)]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub server_name: String,
+    pub stream: LogStream,
+    pub line: String,
+    #[schema(value_type = String, format = DateTime)]
+    pub timestamp: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Where a log line came from. `system` lines are written by the manager itself,
+/// e.g. when the process exits or fails to spawn.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+#[schema(as = MCPServerLogStream)]
+pub enum LogStream {
+    #[sea_orm(string_value = "stdout")]
+    Stdout,
+    #[sea_orm(string_value = "stderr")]
+    Stderr,
+    #[sea_orm(string_value = "system")]
+    System,
+}
+
+impl Model {
+    /// Store a batch of log lines, ids are assigned by the database
+    pub async fn insert_logs(db: &DatabaseConnection, lines: Vec<Model>) -> Result<(), DbErr> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let active_models = lines.into_iter().map(|line| ActiveModel {
+            server_name: Set(line.server_name),
+            stream: Set(line.stream),
+            line: Set(line.line),
+            timestamp: Set(line.timestamp),
+            ..Default::default()
+        });
+        Entity::insert_many(active_models).exec(db).await?;
+        Ok(())
+    }
+
+    /// Get persisted log lines of a server, newest first
+    pub async fn get_server_logs(
+        db: &DatabaseConnection,
+        server_name: &str,
+        stream: Option<LogStream>,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<Model>, u64), DbErr> {
+        let mut query = Entity::find()
+            .filter(Column::ServerName.eq(server_name))
+            .order_by_desc(Column::Timestamp)
+            .order_by_desc(Column::Id);
+        if let Some(stream) = stream {
+            query = query.filter(Column::Stream.eq(stream));
+        }
+
+        let paginator = query.paginate(db, page_size);
+        let total_pages = paginator.num_pages().await?;
+        // SeaORM uses 0-based page indexing, but our API uses 1-based
+        let page_index = if page > 0 { page - 1 } else { 0 };
+        let logs = paginator.fetch_page(page_index).await?;
+
+        Ok((logs, total_pages))
+    }
+
+    /// Clean up a server's logs older than the given number of days
+    pub async fn cleanup_old_logs(
+        db: &DatabaseConnection,
+        server_name: &str,
+        retention_days: u32,
+    ) -> Result<u64, DbErr> {
+        let cutoff_date = chrono::Utc::now() - chrono::Duration::days(retention_days as i64);
+
+        let delete_result = Entity::delete_many()
+            .filter(Column::ServerName.eq(server_name))
+            .filter(Column::Timestamp.lt(cutoff_date))
+            .exec(db)
+            .await?;
+
+        Ok(delete_result.rows_affected)
+    }
+}
+
+/// Collects the output of a single MCP server: a bounded in-memory ring buffer,
+/// a broadcast channel for live tailing and, when enabled, a background writer
+/// persisting lines to `mcp_server_logs`
+pub struct ServerLogSink {
+    server_name: String,
+    buffer: Mutex<LogBuffer>,
+    live_tx: broadcast::Sender<Model>,
+    persist_tx: Mutex<Option<mpsc::UnboundedSender<Model>>>,
+}
+
+struct LogBuffer {
+    next_id: i32,
+    lines: VecDeque<Model>,
+}
+
+impl ServerLogSink {
+    pub fn new(server_name: &str) -> Self {
+        let (live_tx, _) = broadcast::channel(LIVE_CHANNEL_CAPACITY);
+        Self {
+            server_name: server_name.to_string(),
+            buffer: Mutex::new(LogBuffer {
+                next_id: 1,
+                lines: VecDeque::with_capacity(LOG_BUFFER_LINES),
+            }),
+            live_tx,
+            persist_tx: Mutex::new(None),
+        }
+    }
+
+    /// Record a line of output, with any secret values in it redacted
+    pub fn push(&self, stream: LogStream, line: &str) {
+        let line = secret::redact(line);
+        let entry = {
+            let mut buffer = self.buffer.lock().unwrap();
+            let entry = Model {
+                id: buffer.next_id,
+                server_name: self.server_name.clone(),
+                stream,
+                line,
+                timestamp: chrono::Utc::now(),
+            };
+            buffer.next_id = buffer.next_id.wrapping_add(1);
+            if buffer.lines.len() >= LOG_BUFFER_LINES {
+                buffer.lines.pop_front();
+            }
+            buffer.lines.push_back(entry.clone());
+            entry
+        };
+
+        if let Some(persist_tx) = self.persist_tx.lock().unwrap().as_ref() {
+            let _ = persist_tx.send(entry.clone());
+        }
+        // Nobody tailing the logs is not an error
+        let _ = self.live_tx.send(entry);
+    }
+
+    /// Get buffered lines, newest first, along with the total number of pages
+    pub fn page(&self, stream: Option<LogStream>, page: u64, page_size: u64) -> (Vec<Model>, u64) {
+        let buffer = self.buffer.lock().unwrap();
+        let lines: Vec<&Model> = buffer
+            .lines
+            .iter()
+            .rev()
+            .filter(|line| stream.is_none_or(|stream| line.stream == stream))
+            .collect();
+
+        let page_size = page_size.max(1);
+        let total_pages = (lines.len() as u64).div_ceil(page_size);
+        let page_index = page.saturating_sub(1);
+        let data = lines
+            .into_iter()
+            .skip((page_index * page_size) as usize)
+            .take(page_size as usize)
+            .cloned()
+            .collect();
+
+        (data, total_pages)
+    }
+
+    /// Receive every line pushed from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Model> {
+        self.live_tx.subscribe()
+    }
+
+    /// Start (or stop, with `None`) writing lines to the database, dropping persisted
+    /// lines once they are older than the retention period
+    pub fn set_persistence(&self, persistence: Option<(DatabaseConnection, u32)>) {
+        let persist_tx = persistence.map(|(db, retention_days)| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(persist_logs(
+                db,
+                self.server_name.clone(),
+                retention_days,
+                rx,
+            ));
+            tx
+        });
+
+        // Replacing the sender ends the previous writer once it has drained its queue
+        *self.persist_tx.lock().unwrap() = persist_tx;
+    }
+}
+
+/// Write lines to the database in batches until the sink stops persisting
+async fn persist_logs(
+    db: DatabaseConnection,
+    server_name: String,
+    retention_days: u32,
+    mut rx: mpsc::UnboundedReceiver<Model>,
+) {
+    let mut last_cleanup: Option<Instant> = None;
+
+    loop {
+        if last_cleanup.is_none_or(|at| at.elapsed() >= CLEANUP_INTERVAL) {
+            if let Err(e) = Model::cleanup_old_logs(&db, &server_name, retention_days).await {
+                eprintln!("⚠️ MCP [{server_name}] Failed to clean up old logs: {e}");
+            }
+            last_cleanup = Some(Instant::now());
+        }
+
+        let Some(line) = rx.recv().await else {
+            break;
+        };
+        let mut batch = vec![line];
+        while batch.len() < PERSIST_BATCH_SIZE {
+            match rx.try_recv() {
+                Ok(line) => batch.push(line),
+                Err(_) => break,
+            }
+        }
+
+        if let Err(e) = Model::insert_logs(&db, batch).await {
+            eprintln!("⚠️ MCP [{server_name}] Failed to persist logs: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::database;
+    use rstest::*;
+
+    #[test]
+    fn test_sink_buffer_is_bounded() {
+        let sink = ServerLogSink::new("test-server");
+        for i in 0..LOG_BUFFER_LINES + 10 {
+            sink.push(LogStream::Stderr, &format!("line {i}"));
+        }
+
+        let (lines, total_pages) = sink.page(None, 1, LOG_BUFFER_LINES as u64);
+        assert_eq!(total_pages, 1);
+        assert_eq!(lines.len(), LOG_BUFFER_LINES);
+        assert_eq!(lines[0].line, format!("line {}", LOG_BUFFER_LINES + 9));
+        assert_eq!(lines.last().unwrap().line, "line 10");
+    }
+
+    #[test]
+    fn test_sink_pages_newest_first_with_stream_filter() {
+        let sink = ServerLogSink::new("test-server");
+        sink.push(LogStream::Stdout, "starting");
+        sink.push(LogStream::Stderr, "warn 1");
+        sink.push(LogStream::Stderr, "warn 2");
+        sink.push(LogStream::Stderr, "warn 3");
+
+        let (lines, total_pages) = sink.page(Some(LogStream::Stderr), 1, 2);
+        assert_eq!(total_pages, 2);
+        assert_eq!(
+            lines.iter().map(|l| l.line.as_str()).collect::<Vec<_>>(),
+            vec!["warn 3", "warn 2"]
+        );
+
+        let (lines, _) = sink.page(Some(LogStream::Stderr), 2, 2);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line, "warn 1");
+
+        let (lines, _) = sink.page(Some(LogStream::Stdout), 1, 10);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sink_broadcasts_live_lines() {
+        let sink = ServerLogSink::new("test-server");
+        let mut rx = sink.subscribe();
+        sink.push(LogStream::Stdout, "hello");
+
+        let line = rx.recv().await.unwrap();
+        assert_eq!(line.line, "hello");
+        assert_eq!(line.stream, LogStream::Stdout);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_persisted_logs_paging_and_cleanup(#[future] database: DatabaseConnection) {
+        let db = database.await;
+
+        let old = Model {
+            id: 0,
+            server_name: "test-server".to_string(),
+            stream: LogStream::Stderr,
+            line: "ancient".to_string(),
+            timestamp: chrono::Utc::now() - chrono::Duration::days(30),
+        };
+        let recent = Model {
+            line: "recent".to_string(),
+            timestamp: chrono::Utc::now(),
+            ..old.clone()
+        };
+        let other_server = Model {
+            server_name: "other-server".to_string(),
+            ..old.clone()
+        };
+        Model::insert_logs(&db, vec![old, recent, other_server])
+            .await
+            .unwrap();
+
+        let (logs, total_pages) = Model::get_server_logs(&db, "test-server", None, 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(total_pages, 1);
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].line, "recent");
+
+        let deleted = Model::cleanup_old_logs(&db, "test-server", 7)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        // Other servers keep their own logs
+        let (logs, _) = Model::get_server_logs(&db, "other-server", None, 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_sink_persists_lines(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let sink = ServerLogSink::new("test-server");
+        sink.set_persistence(Some((db.clone(), 7)));
+
+        sink.push(LogStream::Stderr, "persist me");
+        sink.set_persistence(None);
+
+        // The writer drains its queue in the background
+        for _ in 0..50 {
+            let (logs, _) = Model::get_server_logs(&db, "test-server", None, 1, 10)
+                .await
+                .unwrap();
+            if !logs.is_empty() {
+                assert_eq!(logs[0].line, "persist me");
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("log line was never persisted");
+    }
+}
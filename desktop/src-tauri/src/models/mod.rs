@@ -1,3 +1,5 @@
 pub mod external_mcp_client;
 pub mod mcp_request_log;
 pub mod mcp_server;
+pub mod mcp_server_log;
+pub mod secret;
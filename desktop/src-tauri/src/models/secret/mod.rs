@@ -0,0 +1,434 @@
+//! Secrets such as API tokens, stored encrypted in the database with a key that is kept
+//! in a file of its own. Server configs refer to them as `secret://<name>` in env values
+//! and args, and the references are only resolved when the server is spawned.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use once_cell::sync::OnceCell;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use sea_orm::entity::prelude::*;
+use sea_orm::{QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tauri::Manager;
+
+/// Prefix of a reference to a secret, e.g. `secret://github_token`
+pub const SECRET_SCHEME: &str = "secret://";
+const KEY_FILE_NAME: &str = "secrets.key";
+const KEY_LEN: usize = 32;
+const MAX_NAME_LEN: usize = 128;
+/// Shorter values would be found all over unrelated text, they are not redacted
+const MIN_REDACTED_LEN: usize = 4;
+
+static SECRET_STORE: OnceCell<Arc<SecretStore>> = OnceCell::new();
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "secrets")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub name: String,
+    /// Base64 of the nonce followed by the AES-256-GCM ciphertext
+    pub encrypted_value: String,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Encrypted secrets, along with their decrypted values kept in memory so that references
+/// can be resolved and values redacted without going to the database
+pub struct SecretStore {
+    db: DatabaseConnection,
+    key: LessSafeKey,
+    values: RwLock<HashMap<String, String>>,
+}
+
+impl SecretStore {
+    /// Open the store, creating the key file on first use
+    pub async fn open(db: DatabaseConnection, key_path: &Path) -> Result<Self, String> {
+        let key = load_or_create_key(key_path)?;
+        let store = Self {
+            db,
+            key,
+            values: RwLock::new(HashMap::new()),
+        };
+
+        let secrets = Entity::find()
+            .all(&store.db)
+            .await
+            .map_err(|e| format!("Failed to load secrets: {e}"))?;
+        let mut values = HashMap::new();
+        for secret in secrets {
+            // A secret encrypted with another key is listed, but can't be used until it's set again
+            match store.decrypt(&secret.name, &secret.encrypted_value) {
+                Ok(value) => {
+                    values.insert(secret.name, value);
+                }
+                Err(e) => eprintln!("⚠️ Secret '{}' can't be decrypted: {e}", secret.name),
+            }
+        }
+        *store.values.write().unwrap() = values;
+
+        Ok(store)
+    }
+
+    /// Create or replace a secret
+    pub async fn set(&self, name: &str, value: &str) -> Result<Model, String> {
+        validate_name(name)?;
+        let encrypted_value = self.encrypt(name, value)?;
+        let now = chrono::Utc::now();
+
+        let existing = Entity::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load secret: {e}"))?;
+        let secret = match existing {
+            Some(existing) => {
+                let mut active_model: ActiveModel = existing.into();
+                active_model.encrypted_value = Set(encrypted_value);
+                active_model.updated_at = Set(now);
+                active_model.update(&self.db).await
+            }
+            None => {
+                ActiveModel {
+                    name: Set(name.to_string()),
+                    encrypted_value: Set(encrypted_value),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+                .insert(&self.db)
+                .await
+            }
+        }
+        .map_err(|e| format!("Failed to save secret: {e}"))?;
+
+        self.values
+            .write()
+            .unwrap()
+            .insert(name.to_string(), value.to_string());
+        Ok(secret)
+    }
+
+    /// Delete a secret, returns whether it existed
+    pub async fn delete(&self, name: &str) -> Result<bool, String> {
+        let result = Entity::delete_many()
+            .filter(Column::Name.eq(name))
+            .exec(&self.db)
+            .await
+            .map_err(|e| format!("Failed to delete secret: {e}"))?;
+        self.values.write().unwrap().remove(name);
+        Ok(result.rows_affected > 0)
+    }
+
+    /// All stored secrets, sorted by name
+    pub async fn list(&self) -> Result<Vec<Model>, String> {
+        Entity::find()
+            .order_by_asc(Column::Name)
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load secrets: {e}"))
+    }
+
+    /// Replace every `secret://<name>` in the text with the secret's value
+    pub fn resolve(&self, text: &str) -> Result<String, String> {
+        let values = self.values.read().unwrap();
+        replace_references(text, |name| {
+            values
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("Secret '{name}' is not set"))
+        })
+    }
+
+    /// Replace every secret value in the text with its reference
+    pub fn redact(&self, text: &str) -> String {
+        let values = self.values.read().unwrap();
+        // Longest first, so a secret containing another one is replaced as a whole
+        let mut secrets: Vec<(&String, &String)> = values
+            .iter()
+            .filter(|(_, value)| value.len() >= MIN_REDACTED_LEN)
+            .collect();
+        secrets.sort_by_key(|(_, value)| Reverse(value.len()));
+
+        let mut text = text.to_string();
+        for (name, value) in secrets {
+            let reference = format!("{SECRET_SCHEME}{name}");
+            text = text.replace(value.as_str(), &reference);
+            // Inside JSON, values with quotes or backslashes show up escaped
+            let escaped = serde_json::to_string(value).unwrap_or_default();
+            let escaped = &escaped[1..escaped.len() - 1];
+            if escaped != value {
+                text = text.replace(escaped, &reference);
+            }
+        }
+        text
+    }
+
+    fn encrypt(&self, name: &str, value: &str) -> Result<String, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| "Failed to generate a nonce".to_string())?;
+
+        // The name is authenticated too, so a value can't be moved to another secret
+        let mut in_out = value.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(name.as_bytes()),
+                &mut in_out,
+            )
+            .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(in_out);
+        Ok(BASE64.encode(encrypted))
+    }
+
+    fn decrypt(&self, name: &str, encrypted_value: &str) -> Result<String, String> {
+        let mut encrypted = BASE64
+            .decode(encrypted_value)
+            .map_err(|e| format!("Invalid encrypted value: {e}"))?;
+        if encrypted.len() < NONCE_LEN {
+            return Err("Invalid encrypted value".to_string());
+        }
+
+        let (nonce, ciphertext) = encrypted.split_at_mut(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| "Invalid encrypted value".to_string())?;
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::from(name.as_bytes()), ciphertext)
+            .map_err(|_| "Wrong key or corrupted value".to_string())?;
+        String::from_utf8(plaintext.to_vec()).map_err(|e| format!("Invalid value: {e}"))
+    }
+}
+
+/// Secret names end where a reference ends, so they're limited to a safe set of characters
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN || !name.chars().all(is_name_char) {
+        return Err(format!(
+            "Invalid secret name '{name}', use up to {MAX_NAME_LEN} letters, digits, '_', '-' and '.'"
+        ));
+    }
+    Ok(())
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+fn replace_references(
+    text: &str,
+    mut lookup: impl FnMut(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(SECRET_SCHEME) {
+        resolved.push_str(&rest[..start]);
+        let after_scheme = &rest[start + SECRET_SCHEME.len()..];
+        let name_len = after_scheme
+            .find(|c| !is_name_char(c))
+            .unwrap_or(after_scheme.len());
+        if name_len == 0 {
+            return Err(format!("Secret reference without a name: '{text}'"));
+        }
+        resolved.push_str(&lookup(&after_scheme[..name_len])?);
+        rest = &after_scheme[name_len..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+fn load_or_create_key(path: &Path) -> Result<LessSafeKey, String> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => create_key_file(path)?,
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+    };
+    if bytes.len() != KEY_LEN {
+        return Err(format!("{} is not a valid key file", path.display()));
+    }
+    let key = UnboundKey::new(&AES_256_GCM, &bytes)
+        .map_err(|_| format!("{} is not a valid key file", path.display()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn create_key_file(path: &Path) -> Result<Vec<u8>, String> {
+    let mut key = vec![0u8; KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| "Failed to generate a key".to_string())?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // Only the user running the app gets to read the key
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+    file.write_all(&key)
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    println!("🔑 Created secret key at {}", path.display());
+    Ok(key)
+}
+
+/// Open the app's secret store, with the key kept in the config directory rather than
+/// next to the database
+pub async fn init_secret_store(
+    app: &tauri::AppHandle,
+    db: DatabaseConnection,
+) -> Result<(), String> {
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get app config directory: {e}"))?;
+    let store = SecretStore::open(db, &config_dir.join(KEY_FILE_NAME)).await?;
+    SECRET_STORE
+        .set(Arc::new(store))
+        .map_err(|_| "Secret store is already initialized".to_string())
+}
+
+/// The app's secret store, `None` until it's initialized
+pub fn secret_store() -> Option<Arc<SecretStore>> {
+    SECRET_STORE.get().cloned()
+}
+
+/// Resolve secret references in the text with the app's secret store
+pub fn resolve_references(text: &str) -> Result<String, String> {
+    if !text.contains(SECRET_SCHEME) {
+        return Ok(text.to_string());
+    }
+    match SECRET_STORE.get() {
+        Some(store) => store.resolve(text),
+        None => Err("Secret store is not available".to_string()),
+    }
+}
+
+/// Redact secret values in the text with the app's secret store, if there is one
+pub fn redact(text: &str) -> String {
+    match SECRET_STORE.get() {
+        Some(store) => store.redact(text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::database;
+    use rstest::*;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_secrets_are_encrypted_and_resolved(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let key_dir = tempfile::tempdir().unwrap();
+        let key_path = key_dir.path().join(KEY_FILE_NAME);
+        let store = SecretStore::open(db.clone(), &key_path).await.unwrap();
+
+        let secret = store.set("github_token", "ghp_abc123").await.unwrap();
+        assert!(!secret.encrypted_value.contains("ghp_abc123"));
+        assert_eq!(
+            store.resolve("--token=secret://github_token").unwrap(),
+            "--token=ghp_abc123"
+        );
+        assert_eq!(
+            store.resolve("secret://missing").unwrap_err(),
+            "Secret 'missing' is not set"
+        );
+        assert!(store.resolve("secret://").is_err());
+
+        // Reopening with the same key file decrypts what was stored
+        let reopened = SecretStore::open(db.clone(), &key_path).await.unwrap();
+        assert_eq!(
+            reopened.resolve("secret://github_token").unwrap(),
+            "ghp_abc123"
+        );
+
+        // Another key can't
+        let other_key_dir = tempfile::tempdir().unwrap();
+        let other = SecretStore::open(db, &other_key_dir.path().join(KEY_FILE_NAME))
+            .await
+            .unwrap();
+        assert!(other.resolve("secret://github_token").is_err());
+        assert_eq!(other.list().await.unwrap().len(), 1);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_replaces_and_delete_removes(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let key_dir = tempfile::tempdir().unwrap();
+        let store = SecretStore::open(db, &key_dir.path().join(KEY_FILE_NAME))
+            .await
+            .unwrap();
+
+        store.set("token", "first-value").await.unwrap();
+        store.set("token", "second-value").await.unwrap();
+        assert_eq!(store.list().await.unwrap().len(), 1);
+        assert_eq!(store.resolve("secret://token").unwrap(), "second-value");
+
+        assert!(store.set("bad name", "value").await.is_err());
+
+        assert!(store.delete("token").await.unwrap());
+        assert!(!store.delete("token").await.unwrap());
+        assert!(store.resolve("secret://token").is_err());
+        assert!(store.list().await.unwrap().is_empty());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_redact(#[future] database: DatabaseConnection) {
+        let db = database.await;
+        let key_dir = tempfile::tempdir().unwrap();
+        let store = SecretStore::open(db, &key_dir.path().join(KEY_FILE_NAME))
+            .await
+            .unwrap();
+        store.set("token", "abcd1234").await.unwrap();
+        store.set("long_token", "abcd1234efgh").await.unwrap();
+        store.set("quoted", "pa\"ss").await.unwrap();
+        store.set("short", "ab").await.unwrap();
+
+        assert_eq!(
+            store.redact("Authorization: Bearer abcd1234efgh, or abcd1234"),
+            "Authorization: Bearer secret://long_token, or secret://token"
+        );
+        let json = serde_json::json!({ "password": "pa\"ss" }).to_string();
+        assert_eq!(store.redact(&json), r#"{"password":"secret://quoted"}"#);
+        // Values this short are left alone
+        assert_eq!(store.redact("about"), "about");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let key_dir = tempfile::tempdir().unwrap();
+        let key_path = key_dir.path().join("nested").join(KEY_FILE_NAME);
+        load_or_create_key(&key_path).unwrap();
+        let mode = std::fs::metadata(&key_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -8,6 +8,8 @@ use utoipauto::utoipauto;
         (name = "external_mcp_client", description = "External MCP Client management API"),
         (name = "mcp_request_log", description = "MCP Request logging and analytics API"),
         (name = "mcp_server", description = "MCP Server management API"),
+        (name = "runtime", description = "Node.js runtime detection and settings API"),
+        (name = "secret", description = "Secret store API"),
     ),
     info(
         title = "Archestra API",
@@ -0,0 +1,511 @@
+//! Linux counterpart of the macOS `sandbox-exec` profiles.
+//!
+//! A sandboxed server process is confined in three layers, all set up in the
+//! forked child right before `exec`:
+//! - a user + network namespace when network access is denied, leaving the
+//!   process with nothing but an unconfigured loopback interface
+//! - Landlock rules limiting which paths can be read, executed and written
+//! - a seccomp filter rejecting syscalls no MCP server should need (and socket
+//!   creation for internet families when network access is denied)
+//!
+//! Older kernels or hardened distributions may not offer namespaces or Landlock,
+//! without them the sandbox is weaker and `apply` says so in its warnings. Seccomp
+//! is always on, and once a layer is known to be available failing to set it up
+//! fails the spawn.
+
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use landlock::{
+    Access, AccessFs, PathBeneath, PathFd, Ruleset, RulesetAttr, RulesetCreated,
+    RulesetCreatedAttr, ABI,
+};
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+    SeccompRule,
+};
+use tokio::process::Command;
+
+use super::node::NodeInfo;
+use crate::models::mcp_server::{SandboxConfig, SandboxProfile};
+
+const LANDLOCK_ABI: ABI = ABI::V3;
+const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1;
+
+const PERMISSIVE_READ_PATHS: &[&str] = &[
+    "/usr", "/lib", "/lib32", "/lib64", "/bin", "/sbin", "/etc", "/opt", "/proc", "/sys", "/dev",
+    "/run",
+];
+const PERMISSIVE_WRITE_PATHS: &[&str] = &["/tmp", "/dev/null", "/dev/zero", "/dev/tty"];
+const PERMISSIVE_HOME_READ_PATHS: &[&str] = &[".npm", ".nvm", ".cache", ".local"];
+const PERMISSIVE_HOME_WRITE_PATHS: &[&str] = &[".npm", ".cache"];
+
+const RESTRICTIVE_READ_PATHS: &[&str] = &[
+    "/usr",
+    "/lib",
+    "/lib64",
+    "/bin",
+    "/etc",
+    "/proc",
+    "/dev/null",
+    "/dev/urandom",
+];
+const RESTRICTIVE_WRITE_PATHS: &[&str] = &["/tmp", "/dev/null"];
+const RESTRICTIVE_HOME_READ_PATHS: &[&str] = &[".npm"];
+
+/// Syscalls denied to every sandboxed server
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_setns,
+    libc::SYS_unshare,
+    libc::SYS_kexec_load,
+    libc::SYS_kexec_file_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_acct,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_add_key,
+    libc::SYS_keyctl,
+    libc::SYS_request_key,
+];
+
+/// Additional syscalls denied by the restrictive profile
+const RESTRICTIVE_DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_personality,
+    libc::SYS_userfaultfd,
+    libc::SYS_clock_settime,
+    libc::SYS_settimeofday,
+];
+
+/// io_uring can open sockets without going through the `socket` syscall, so it's denied
+/// wherever the socket filter is relied on
+const IO_URING_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_io_uring_setup,
+    libc::SYS_io_uring_enter,
+    libc::SYS_io_uring_register,
+];
+
+/// Socket families blocked when network access is denied
+const NETWORK_SOCKET_FAMILIES: &[libc::c_int] = &[libc::AF_INET, libc::AF_INET6, libc::AF_PACKET];
+
+/// Resolved sandbox policy for a single server process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    pub profile: SandboxProfile,
+    pub read_paths: Vec<PathBuf>,
+    pub write_paths: Vec<PathBuf>,
+    pub allow_network: bool,
+}
+
+impl SandboxPolicy {
+    /// Combine the profile's default paths with the server's own settings
+    pub fn new(config: &SandboxConfig, node_info: &NodeInfo, command: &str) -> Self {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+
+        let (system_read, system_write, home_read, home_write) = match config.profile {
+            SandboxProfile::Permissive => (
+                PERMISSIVE_READ_PATHS,
+                PERMISSIVE_WRITE_PATHS,
+                PERMISSIVE_HOME_READ_PATHS,
+                PERMISSIVE_HOME_WRITE_PATHS,
+            ),
+            SandboxProfile::Restrictive => (
+                RESTRICTIVE_READ_PATHS,
+                RESTRICTIVE_WRITE_PATHS,
+                RESTRICTIVE_HOME_READ_PATHS,
+                &[][..],
+            ),
+        };
+
+        let mut read_paths: Vec<PathBuf> = system_read.iter().map(PathBuf::from).collect();
+        let mut write_paths: Vec<PathBuf> = system_write.iter().map(PathBuf::from).collect();
+        if let Some(home) = &home {
+            read_paths.extend(home_read.iter().map(|path| home.join(path)));
+            write_paths.extend(home_write.iter().map(|path| home.join(path)));
+        }
+
+        // The detected Node.js installation and the server binary itself must stay executable
+        let node_binaries = [
+            &node_info.node_path,
+            &node_info.npm_path,
+            &node_info.npx_path,
+        ];
+        read_paths.extend(
+            node_binaries
+                .into_iter()
+                .flatten()
+                .filter_map(|path| node_prefix(path)),
+        );
+        read_paths.extend(executable_dir(command));
+
+        read_paths.extend(
+            config
+                .read_paths
+                .iter()
+                .map(|path| expand_home(path, &home)),
+        );
+        write_paths.extend(
+            config
+                .write_paths
+                .iter()
+                .map(|path| expand_home(path, &home)),
+        );
+
+        read_paths.sort();
+        read_paths.dedup();
+        write_paths.sort();
+        write_paths.dedup();
+
+        Self {
+            profile: config.profile,
+            read_paths,
+            write_paths,
+            allow_network: config.allow_network,
+        }
+    }
+
+    fn denied_syscalls(&self) -> Vec<libc::c_long> {
+        let mut syscalls = DENIED_SYSCALLS.to_vec();
+        if self.profile == SandboxProfile::Restrictive {
+            syscalls.extend_from_slice(RESTRICTIVE_DENIED_SYSCALLS);
+        }
+        if self.profile == SandboxProfile::Restrictive || !self.allow_network {
+            syscalls.extend_from_slice(IO_URING_SYSCALLS);
+        }
+        syscalls
+    }
+}
+
+/// Whether the running kernel supports Landlock at all
+pub fn is_landlock_available() -> bool {
+    landlock_abi_version() > 0
+}
+
+fn landlock_abi_version() -> libc::c_long {
+    unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<libc::c_void>(),
+            0usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    }
+}
+
+/// Whether unprivileged processes may enter user and network namespaces of their own,
+/// probed once in a throwaway child
+pub fn is_network_namespace_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let (uid_map, gid_map) = id_maps();
+        unsafe {
+            match libc::fork() {
+                -1 => false,
+                0 => {
+                    let code = match enter_network_namespace(&uid_map, &gid_map) {
+                        Ok(()) => 0,
+                        Err(_) => 1,
+                    };
+                    libc::_exit(code)
+                }
+                pid => {
+                    let mut status = 0;
+                    libc::waitpid(pid, &mut status, 0) == pid
+                        && libc::WIFEXITED(status)
+                        && libc::WEXITSTATUS(status) == 0
+                }
+            }
+        }
+    })
+}
+
+/// Configure a command so the process it spawns is confined by the given policy,
+/// returning what the kernel doesn't support and is therefore left unconfined
+pub fn apply(cmd: &mut Command, policy: &SandboxPolicy) -> Result<Vec<String>, String> {
+    let mut warnings = Vec::new();
+    if !is_landlock_available() {
+        warnings.push(
+            "Landlock is not supported by this kernel, filesystem access is not restricted"
+                .to_string(),
+        );
+    }
+    let isolate_network = !policy.allow_network && is_network_namespace_available();
+    if !policy.allow_network && !isolate_network {
+        warnings.push(
+            "Network namespaces are not available, network access is only restricted by the seccomp socket filter"
+                .to_string(),
+        );
+    }
+
+    let ruleset = build_landlock_ruleset(policy)?;
+    let seccomp_filter = build_seccomp_filter(policy)?;
+
+    // Everything the child needs is prepared here, the pre_exec hook only issues syscalls
+    let (uid_map, gid_map) = id_maps();
+    let mut ruleset = Some(ruleset);
+
+    unsafe {
+        cmd.pre_exec(move || {
+            if isolate_network {
+                enter_network_namespace(&uid_map, &gid_map)?;
+            }
+
+            if let Some(ruleset) = ruleset.take() {
+                ruleset.restrict_self().map_err(io::Error::other)?;
+            }
+
+            seccompiler::apply_filter(&seccomp_filter).map_err(io::Error::other)?;
+            Ok(())
+        });
+    }
+
+    Ok(warnings)
+}
+
+/// Map the current user and group to themselves inside a new user namespace
+fn id_maps() -> (Vec<u8>, Vec<u8>) {
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    (
+        format!("{uid} {uid} 1\n").into_bytes(),
+        format!("{gid} {gid} 1\n").into_bytes(),
+    )
+}
+
+fn build_landlock_ruleset(policy: &SandboxPolicy) -> Result<RulesetCreated, String> {
+    let read_access = AccessFs::from_read(LANDLOCK_ABI);
+    let write_access = AccessFs::from_all(LANDLOCK_ABI);
+
+    let rules = existing_paths(&policy.read_paths)
+        .map(|path| (path, read_access))
+        .chain(existing_paths(&policy.write_paths).map(|path| (path, write_access)))
+        .filter_map(|(path, access)| {
+            PathFd::new(path)
+                .ok()
+                .map(|fd| PathBeneath::new(fd, access))
+        });
+
+    Ruleset::default()
+        .handle_access(write_access)
+        .and_then(|ruleset| ruleset.create())
+        .and_then(|ruleset| ruleset.add_rules(rules.map(Ok)))
+        .map_err(|e| format!("Failed to build Landlock ruleset: {e}"))
+}
+
+fn build_seccomp_filter(policy: &SandboxPolicy) -> Result<BpfProgram, String> {
+    let mut rules: BTreeMap<i64, Vec<SeccompRule>> = policy
+        .denied_syscalls()
+        .into_iter()
+        .map(|syscall| (syscall, Vec::new()))
+        .collect();
+
+    if !policy.allow_network {
+        let socket_rules = NETWORK_SOCKET_FAMILIES
+            .iter()
+            .map(|family| {
+                SeccompCondition::new(0, SeccompCmpArgLen::Dword, SeccompCmpOp::Eq, *family as u64)
+                    .and_then(|condition| SeccompRule::new(vec![condition]))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to build seccomp rule: {e}"))?;
+        rules.insert(libc::SYS_socket, socket_rules);
+    }
+
+    let target_arch = std::env::consts::ARCH
+        .try_into()
+        .map_err(|e| format!("Seccomp is not supported on this architecture: {e}"))?;
+
+    SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        target_arch,
+    )
+    .and_then(BpfProgram::try_from)
+    .map_err(|e| format!("Failed to build seccomp filter: {e}"))
+}
+
+/// Move the calling (forked) process into fresh user and network namespaces
+fn enter_network_namespace(uid_map: &[u8], gid_map: &[u8]) -> io::Result<()> {
+    if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    write_proc_file(c"/proc/self/setgroups", b"deny")?;
+    write_proc_file(c"/proc/self/uid_map", uid_map)?;
+    write_proc_file(c"/proc/self/gid_map", gid_map)
+}
+
+fn write_proc_file(path: &CStr, contents: &[u8]) -> io::Result<()> {
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        let result = if written < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        libc::close(fd);
+        result
+    }
+}
+
+fn existing_paths(paths: &[PathBuf]) -> impl Iterator<Item = &PathBuf> {
+    paths.iter().filter(|path| path.exists())
+}
+
+/// Installation prefix of a Node.js binary, e.g. `~/.nvm/versions/node/v20.0.0` for its `bin/node`
+fn node_prefix(binary: &Path) -> Option<PathBuf> {
+    let binary = binary.canonicalize().ok()?;
+    binary.parent()?.parent().map(Path::to_path_buf)
+}
+
+/// Directory holding the executable a command resolves to
+fn executable_dir(command: &str) -> Option<PathBuf> {
+    let command_path = Path::new(command);
+    let resolved = if command_path.components().count() > 1 {
+        command_path.to_path_buf()
+    } else {
+        std::env::split_paths(&std::env::var_os("PATH")?)
+            .map(|dir| dir.join(command))
+            .find(|candidate| candidate.is_file())?
+    };
+    resolved
+        .canonicalize()
+        .ok()?
+        .parent()
+        .map(Path::to_path_buf)
+}
+
+fn expand_home(path: &str, home: &Option<PathBuf>) -> PathBuf {
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    fn no_node() -> NodeInfo {
+        NodeInfo {
+            node_path: None,
+            npm_path: None,
+            npx_path: None,
+        }
+    }
+
+    #[test]
+    fn test_restrictive_profile_is_narrower_than_permissive() {
+        let permissive = SandboxPolicy::new(&SandboxConfig::default(), &no_node(), "sh");
+        let restrictive = SandboxPolicy::new(
+            &SandboxConfig {
+                profile: SandboxProfile::Restrictive,
+                ..Default::default()
+            },
+            &no_node(),
+            "sh",
+        );
+
+        assert!(restrictive.read_paths.len() < permissive.read_paths.len());
+        assert!(restrictive.write_paths.len() < permissive.write_paths.len());
+        assert!(permissive.read_paths.contains(&PathBuf::from("/run")));
+        assert!(!restrictive.read_paths.contains(&PathBuf::from("/run")));
+        assert!(restrictive
+            .denied_syscalls()
+            .contains(&libc::SYS_io_uring_setup));
+        assert!(!permissive
+            .denied_syscalls()
+            .contains(&libc::SYS_io_uring_setup));
+
+        // io_uring would get around the socket filter
+        let offline = SandboxPolicy {
+            allow_network: false,
+            ..permissive
+        };
+        assert!(offline
+            .denied_syscalls()
+            .contains(&libc::SYS_io_uring_setup));
+    }
+
+    #[test]
+    fn test_policy_includes_configured_paths() {
+        let config = SandboxConfig {
+            read_paths: vec!["/srv/data".to_string()],
+            write_paths: vec!["/srv/output".to_string()],
+            allow_network: false,
+            ..Default::default()
+        };
+        let policy = SandboxPolicy::new(&config, &no_node(), "/bin/sh");
+
+        assert!(policy.read_paths.contains(&PathBuf::from("/srv/data")));
+        assert!(policy.write_paths.contains(&PathBuf::from("/srv/output")));
+        assert!(!policy.allow_network);
+        assert!(build_seccomp_filter(&policy).is_ok());
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = Some(PathBuf::from("/home/test"));
+        assert_eq!(
+            expand_home("~/projects", &home),
+            PathBuf::from("/home/test/projects")
+        );
+        assert_eq!(expand_home("/etc", &home), PathBuf::from("/etc"));
+        assert_eq!(
+            expand_home("~/projects", &None),
+            PathBuf::from("~/projects")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sandboxed_process_cannot_write_outside_allowed_paths() {
+        if !is_landlock_available() {
+            eprintln!("Landlock not supported by this kernel, skipping");
+            return;
+        }
+
+        let allowed = tempfile::tempdir().unwrap();
+        let denied = tempfile::tempdir().unwrap();
+        let config = SandboxConfig {
+            write_paths: vec![allowed.path().display().to_string()],
+            ..Default::default()
+        };
+        let mut policy = SandboxPolicy::new(&config, &no_node(), "sh");
+        // The temp dir is writable by default, which would let the denied write through
+        policy
+            .write_paths
+            .retain(|path| !denied.path().starts_with(path));
+
+        let script = format!(
+            "echo ok > {}/file && echo nope > {}/file",
+            allowed.path().display(),
+            denied.path().display()
+        );
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script).stderr(Stdio::null());
+        assert!(apply(&mut cmd, &policy).unwrap().is_empty());
+
+        let status = cmd.status().await.unwrap();
+        assert!(!status.success());
+        assert!(allowed.path().join("file").exists());
+        assert!(!denied.path().join("file").exists());
+    }
+}
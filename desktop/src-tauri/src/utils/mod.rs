@@ -1 +1,8 @@
+#[cfg(target_os = "linux")]
+pub mod linux_sandbox;
+pub mod names;
 pub mod node;
+pub mod python;
+#[cfg(target_os = "linux")]
+pub mod resource_limits;
+pub mod runtime;
@@ -0,0 +1,44 @@
+//! Server names come from users and catalogs. These turn them into names that are safe
+//! to use in paths, container names and tool names.
+
+/// Replace everything but ASCII letters, digits, `-` and `_` with `_`
+pub fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A short, stable hash of a name, in hex
+pub fn short_hash(name: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, name.as_bytes());
+    digest.as_ref()[..4]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// The sanitized name, with a hash of the name itself telling apart the names that only
+/// differ in the characters that were replaced
+pub fn unique(name: &str) -> String {
+    format!("{}-{}", sanitize(name), short_hash(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_names() {
+        assert_eq!(sanitize("../GitHub (work)"), "___GitHub__work_");
+        assert!(unique("My Server").starts_with("My_Server-"));
+        assert_ne!(unique("My Server"), unique("My_Server"));
+        assert_eq!(unique("My Server"), unique("My Server"));
+        assert_eq!(short_hash("github").len(), 8);
+    }
+}
@@ -1,6 +1,25 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use utoipa::ToSchema;
+
+use super::runtime;
+
+/// File in an npm prefix recording the package installed there
+const PIN_FILE_NAME: &str = "archestra-pin.json";
+
+/// A prefix a server's npm package is installed into, and how npm installs it there
+#[derive(Debug, Clone, Default)]
+pub struct NpmPrefix {
+    pub path: PathBuf,
+    /// Environment npm runs with, e.g. the server's own HOME and npm cache
+    pub env: HashMap<String, String>,
+    /// Let npm run the install scripts of the package and its dependencies. They run
+    /// outside of any sandbox, but some packages need them to build native code.
+    pub run_install_scripts: bool,
+}
 
 #[derive(Debug, Clone)]
 pub struct NodeInfo {
@@ -19,178 +38,306 @@ impl NodeInfo {
     }
 }
 
-/// Detect Node.js installation on the system
-pub fn detect_node_installation() -> NodeInfo {
-    let mut info = NodeInfo {
-        node_path: None,
-        npm_path: None,
-        npx_path: None,
-    };
+/// The version of an npm package installed for a server, with the integrity hash npm
+/// recorded for it. Reinstalling the package has to give the same hash, so a tampered
+/// tarball is never run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = MCPServerNpmPackage)]
+pub struct NpmPackagePin {
+    pub name: String,
+    pub version: String,
+    /// Subresource integrity hash of the package tarball, missing for packages that
+    /// don't come from a registry
+    pub integrity: Option<String>,
+}
 
-    // First check if commands are in PATH
-    if let Some(path) = find_in_path("node") {
-        info.node_path = Some(path);
-    }
-    if let Some(path) = find_in_path("npm") {
-        info.npm_path = Some(path);
-    }
-    if let Some(path) = find_in_path("npx") {
-        info.npx_path = Some(path);
+/// Detect Node.js installation on the system. A node the user picked in the runtime
+/// settings wins, then PATH, then the installs of version managers and the usual
+/// system directories. npm and npx are taken from node's directory where possible, so
+/// the three belong to the same installation.
+pub fn detect_node_installation() -> NodeInfo {
+    if let Some(node_path) = runtime::preferred_node_path() {
+        return node_info_for(node_path);
     }
 
-    // If not found in PATH, check common installation directories
-    if info.node_path.is_none() {
-        info.node_path = find_node_in_common_locations();
-    }
-    if info.npm_path.is_none() {
-        info.npm_path = find_npm_in_common_locations();
-    }
-    if info.npx_path.is_none() {
-        info.npx_path = find_npx_in_common_locations();
+    // First check if commands are in PATH
+    if let Some(node_path) = find_in_path("node") {
+        let mut info = node_info_for(node_path);
+        if info.npm_path.is_none() {
+            info.npm_path = find_in_path("npm");
+        }
+        if info.npx_path.is_none() {
+            info.npx_path = find_in_path("npx");
+        }
+        return info;
     }
 
-    info
+    // A GUI app often doesn't get the user's shell PATH, look where installs usually are
+    get_common_node_locations()
+        .into_iter()
+        .find_map(|dir| find_in_dirs("node", [dir]))
+        .map(node_info_for)
+        .unwrap_or(NodeInfo {
+            node_path: None,
+            npm_path: None,
+            npx_path: None,
+        })
 }
 
-/// Find a command in PATH
-fn find_in_path(command: &str) -> Option<PathBuf> {
-    if let Ok(output) = Command::new("which").arg(command).output() {
-        if output.status.success() {
-            let path_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !path_str.is_empty() {
-                return Some(PathBuf::from(path_str));
-            }
-        }
+/// A node binary with the npm and npx next to it
+fn node_info_for(node_path: PathBuf) -> NodeInfo {
+    let bin_dir = node_path.parent().map(Path::to_path_buf);
+    NodeInfo {
+        npm_path: bin_dir.clone().and_then(|dir| find_in_dirs("npm", [dir])),
+        npx_path: bin_dir.and_then(|dir| find_in_dirs("npx", [dir])),
+        node_path: Some(node_path),
     }
+}
 
-    // Fallback to Windows where command
-    if cfg!(target_os = "windows") {
-        if let Ok(output) = Command::new("where").arg(command).output() {
-            if output.status.success() {
-                let path_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if let Some(first_line) = path_str.lines().next() {
-                    return Some(PathBuf::from(first_line));
-                }
-            }
-        }
-    }
+/// PATH with node's directory in front. npm and npx are scripts that run whatever
+/// `node` is in PATH, which a GUI app may not have.
+pub fn path_with_node_dir(node_info: &NodeInfo) -> Option<std::ffi::OsString> {
+    let node_dir = node_info.node_path.as_ref()?.parent()?.to_path_buf();
+    let path = env::var_os("PATH").unwrap_or_default();
+    env::join_paths(std::iter::once(node_dir).chain(env::split_paths(&path))).ok()
+}
 
-    None
+/// Find a command in PATH, the way `which` and `where` do
+pub fn find_in_path(command: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    find_in_dirs(command, env::split_paths(&path))
 }
 
-/// Common locations where Node.js might be installed
-fn get_common_node_locations() -> Vec<PathBuf> {
-    let mut locations = vec![
-        PathBuf::from("/usr/local/bin/node"),
-        PathBuf::from("/usr/bin/node"),
-        PathBuf::from("/opt/homebrew/bin/node"),
-    ];
+/// Find an executable in the first of the directories that has it. On Windows the
+/// extensions in PATHEXT are tried, so `npm` finds `npm.cmd`.
+pub fn find_in_dirs(command: &str, dirs: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    let file_names = executable_file_names(command);
+    dirs.into_iter().find_map(|dir| {
+        file_names
+            .iter()
+            .map(|file_name| dir.join(file_name))
+            .find(|path| is_executable(path))
+    })
+}
 
-    // Add NVM paths
-    if let Ok(home) = env::var("HOME") {
-        let home_path = PathBuf::from(&home);
-
-        // Check for .nvm directory
-        let nvm_path = home_path.join(".nvm/versions/node");
-        if nvm_path.exists() {
-            // Look for the latest version
-            if let Ok(entries) = std::fs::read_dir(&nvm_path) {
-                for entry in entries.flatten() {
-                    let version_path = entry.path().join("bin/node");
-                    if version_path.exists() {
-                        locations.push(version_path);
-                    }
-                }
-            }
-        }
+fn executable_file_names(command: &str) -> Vec<String> {
+    if !cfg!(target_os = "windows") {
+        return vec![command.to_string()];
     }
+    let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    // A command that already has its extension is taken as it is
+    std::iter::once(command.to_string())
+        .filter(|command| Path::new(command).extension().is_some())
+        .chain(
+            extensions
+                .split(';')
+                .filter(|extension| !extension.is_empty())
+                .map(|extension| format!("{command}{}", extension.to_ascii_lowercase())),
+        )
+        .collect()
+}
 
-    // Windows locations
-    if cfg!(target_os = "windows") {
-        locations.extend(vec![
-            PathBuf::from("C:\\Program Files\\nodejs\\node.exe"),
-            PathBuf::from("C:\\Program Files (x86)\\nodejs\\node.exe"),
-        ]);
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    // Follows symlinks, version manager shims usually are ones
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
 
-        if let Ok(appdata) = env::var("APPDATA") {
-            locations.push(PathBuf::from(&appdata).join("npm\\node.exe"));
-        }
-    }
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
 
-    locations
+/// The user's home directory
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
 }
 
-/// Find Node.js in common installation locations
-fn find_node_in_common_locations() -> Option<PathBuf> {
-    get_common_node_locations()
-        .into_iter()
-        .find(|path| path.exists() && path.is_file())
+/// A directory from an environment variable, or else the default
+fn env_dir(variable: &str, default: Option<PathBuf>) -> Option<PathBuf> {
+    env::var_os(variable).map(PathBuf::from).or(default)
 }
 
-/// Find npm in common installation locations
-fn find_npm_in_common_locations() -> Option<PathBuf> {
-    let locations = vec![
-        PathBuf::from("/usr/local/bin/npm"),
-        PathBuf::from("/usr/bin/npm"),
-        PathBuf::from("/opt/homebrew/bin/npm"),
-    ];
+/// Where data goes on this platform, for managers that follow it
+fn data_home() -> Option<PathBuf> {
+    env_dir(
+        "XDG_DATA_HOME",
+        home_dir().map(|home| home.join(".local").join("share")),
+    )
+}
 
-    for path in locations {
-        if path.exists() && path.is_file() {
-            return Some(path);
+/// Directories holding one install per version, newest version first. Directory names
+/// are versions, with or without a leading `v`.
+fn version_dirs(versions_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(versions_dir) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<(Vec<u64>, PathBuf)> = entries
+        .flatten()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let version = name
+                .trim_start_matches('v')
+                .split('.')
+                .map(|part| part.parse().unwrap_or(0))
+                .collect();
+            (version, entry.path())
+        })
+        .collect();
+    versions.sort_by(|(a, _), (b, _)| b.cmp(a));
+    versions.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Directories version managers put node in, most preferred first: shims that follow
+/// the version the user selected, then each manager's default, then the newest
+/// version each manager has installed
+fn get_version_manager_dirs() -> Vec<PathBuf> {
+    let home = home_dir();
+    let is_windows = cfg!(target_os = "windows");
+    let mut dirs = Vec::new();
+
+    let volta_home = env_dir(
+        "VOLTA_HOME",
+        if is_windows {
+            env::var_os("LOCALAPPDATA").map(|appdata| PathBuf::from(appdata).join("Volta"))
+        } else {
+            home.as_ref().map(|home| home.join(".volta"))
+        },
+    );
+    let asdf_dir = env_dir(
+        "ASDF_DATA_DIR",
+        home.as_ref().map(|home| home.join(".asdf")),
+    );
+    let mise_dir = env_dir("MISE_DATA_DIR", data_home().map(|data| data.join("mise")));
+    let nvm_dir = env_dir("NVM_DIR", home.as_ref().map(|home| home.join(".nvm")));
+    let fnm_dir = env_dir(
+        "FNM_DIR",
+        if is_windows {
+            env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join("fnm"))
+        } else if cfg!(target_os = "macos") {
+            home.as_ref()
+                .map(|home| home.join("Library/Application Support/fnm"))
+        } else {
+            data_home().map(|data| data.join("fnm"))
+        },
+    );
+    // fnm keeps node in the root of an install on Windows, and in bin elsewhere
+    let fnm_bin = |install: PathBuf| {
+        if is_windows {
+            install
+        } else {
+            install.join("bin")
         }
+    };
+
+    // Shims run the version the user selected
+    if let Some(volta_home) = &volta_home {
+        dirs.push(volta_home.join("bin"));
+    }
+    if let Some(asdf_dir) = &asdf_dir {
+        dirs.push(asdf_dir.join("shims"));
+    }
+    if let Some(mise_dir) = &mise_dir {
+        dirs.push(mise_dir.join("shims"));
     }
 
-    // If we found node, npm is usually in the same directory
-    if let Some(node_path) = find_node_in_common_locations() {
-        if let Some(parent) = node_path.parent() {
-            let npm_path = parent.join("npm");
-            if npm_path.exists() {
-                return Some(npm_path);
-            }
-        }
+    // Defaults: fnm's alias and the version nvm for Windows has switched to
+    if let Some(fnm_dir) = &fnm_dir {
+        dirs.push(fnm_bin(fnm_dir.join("aliases").join("default")));
+    }
+    if let Some(nvm_symlink) = env::var_os("NVM_SYMLINK") {
+        dirs.push(PathBuf::from(nvm_symlink));
     }
 
-    None
+    // Newest installs
+    if let Some(nvm_dir) = &nvm_dir {
+        dirs.extend(
+            version_dirs(&nvm_dir.join("versions").join("node"))
+                .into_iter()
+                .map(|install| install.join("bin")),
+        );
+    }
+    if let Some(nvm_home) = env::var_os("NVM_HOME") {
+        dirs.extend(version_dirs(Path::new(&nvm_home)));
+    }
+    if let Some(fnm_dir) = &fnm_dir {
+        dirs.extend(
+            version_dirs(&fnm_dir.join("node-versions"))
+                .into_iter()
+                .map(|install| fnm_bin(install.join("installation"))),
+        );
+    }
+    if let Some(asdf_dir) = &asdf_dir {
+        dirs.extend(
+            version_dirs(&asdf_dir.join("installs").join("nodejs"))
+                .into_iter()
+                .map(|install| install.join("bin")),
+        );
+    }
+    if let Some(mise_dir) = &mise_dir {
+        dirs.extend(
+            version_dirs(&mise_dir.join("installs").join("node"))
+                .into_iter()
+                .map(|install| install.join("bin")),
+        );
+    }
+
+    dirs
 }
 
-/// Find npx in common installation locations
-fn find_npx_in_common_locations() -> Option<PathBuf> {
-    let locations = vec![
-        PathBuf::from("/usr/local/bin/npx"),
-        PathBuf::from("/usr/bin/npx"),
-        PathBuf::from("/opt/homebrew/bin/npx"),
-    ];
+/// Directories where Node.js might be installed, version managers first
+fn get_common_node_locations() -> Vec<PathBuf> {
+    let mut locations = get_version_manager_dirs();
 
-    for path in locations {
-        if path.exists() && path.is_file() {
-            return Some(path);
-        }
-    }
+    locations.extend([
+        PathBuf::from("/usr/local/bin"),
+        PathBuf::from("/usr/bin"),
+        PathBuf::from("/opt/homebrew/bin"),
+    ]);
 
-    // If we found node, npx is usually in the same directory
-    if let Some(node_path) = find_node_in_common_locations() {
-        if let Some(parent) = node_path.parent() {
-            let npx_path = parent.join("npx");
-            if npx_path.exists() {
-                return Some(npx_path);
-            }
+    // Windows locations
+    if cfg!(target_os = "windows") {
+        locations.extend([
+            PathBuf::from("C:\\Program Files\\nodejs"),
+            PathBuf::from("C:\\Program Files (x86)\\nodejs"),
+        ]);
+
+        if let Ok(appdata) = env::var("APPDATA") {
+            locations.push(PathBuf::from(&appdata).join("npm"));
         }
     }
 
-    None
+    locations
 }
 
-/// Get the command to execute an npm package
+/// Get the command to execute an npm package. Given a prefix, the package is installed
+/// there (see `ensure_npm_package`) and its binary is run by node directly.
 pub fn get_npm_execution_command(
     package_name: &str,
     node_info: &NodeInfo,
+    prefix: Option<&NpmPrefix>,
 ) -> Result<(String, Vec<String>), String> {
+    if let Some(prefix) = prefix {
+        let Some(node_path) = &node_info.node_path else {
+            return Err("Node.js is not available on the system".to_string());
+        };
+        let pin = ensure_npm_package(package_name, node_info, prefix)?;
+        let binary = find_package_binary(&prefix.path, &pin.name)?;
+        return Ok((
+            node_path.to_string_lossy().to_string(),
+            vec![binary.to_string_lossy().to_string()],
+        ));
+    }
+
     // First try npx if available
     if let Some(npx_path) = &node_info.npx_path {
         return Ok((
             npx_path.to_string_lossy().to_string(),
-            vec![package_name.to_string()],
+            vec!["-y".to_string(), package_name.to_string()],
         ));
     }
 
@@ -220,7 +367,7 @@ pub fn get_npm_execution_command(
             vec![
                 "-e".to_string(),
                 format!(
-                    "require('child_process').execFileSync('npx', ['{}'], {{stdio: 'inherit'}})",
+                    "require('child_process').execFileSync('npx', ['-y', '{}'], {{stdio: 'inherit'}})",
                     package_name
                 ),
             ],
@@ -230,6 +377,220 @@ pub fn get_npm_execution_command(
     Err("Neither npx nor node.js is available on the system".to_string())
 }
 
+/// Split the arguments of an `npx` command into the package to run and the arguments
+/// for it, skipping npx's own flags such as `-y`
+pub fn split_npx_args(args: &[String]) -> Option<(String, Vec<String>)> {
+    let position = args.iter().position(|arg| !arg.starts_with('-'))?;
+    Some((args[position].clone(), args[position + 1..].to_vec()))
+}
+
+/// Split a package spec like `@scope/name@1.2.3` into its name and version
+pub fn parse_package_spec(spec: &str) -> (&str, Option<&str>) {
+    match spec.rfind('@') {
+        Some(at) if at > 0 => (&spec[..at], Some(&spec[at + 1..])),
+        _ => (spec, None),
+    }
+}
+
+/// Whether a version from a package spec names one exact version, rather than a range
+/// or a tag like `latest`
+pub fn is_exact_version(version: &str) -> bool {
+    version.starts_with(|c: char| c.is_ascii_digit())
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+}
+
+/// The package pinned in a prefix, if one was installed there
+pub fn read_pin(prefix: &Path) -> Option<NpmPackagePin> {
+    let pin = std::fs::read_to_string(prefix.join(PIN_FILE_NAME)).ok()?;
+    serde_json::from_str(&pin).ok()
+}
+
+/// What npm's lockfile in a prefix says is installed for a package
+fn locked_package(prefix: &Path, name: &str) -> Option<NpmPackagePin> {
+    let lockfile = std::fs::read_to_string(prefix.join("package-lock.json")).ok()?;
+    let lockfile: serde_json::Value = serde_json::from_str(&lockfile).ok()?;
+    let entry = lockfile
+        .get("packages")?
+        .get(format!("node_modules/{name}"))?;
+    Some(NpmPackagePin {
+        name: name.to_string(),
+        version: entry.get("version")?.as_str()?.to_string(),
+        integrity: entry
+            .get("integrity")
+            .and_then(|integrity| integrity.as_str())
+            .map(str::to_string),
+    })
+}
+
+/// Make sure a package is installed in a prefix and return its pin. The first install
+/// takes the newest version matching the spec and pins it, later launches run the
+/// pinned version without going to the registry, until the package is upgraded with
+/// `install_npm_package` or the spec asks for another exact version.
+pub fn ensure_npm_package(
+    spec: &str,
+    node_info: &NodeInfo,
+    prefix: &NpmPrefix,
+) -> Result<NpmPackagePin, String> {
+    let (name, version) = parse_package_spec(spec);
+    let pin = read_pin(&prefix.path);
+
+    // A prefix holds a single package, start over if the server now runs another one
+    if pin.as_ref().is_some_and(|pin| pin.name != name) {
+        std::fs::remove_dir_all(&prefix.path)
+            .map_err(|e| format!("Failed to remove {}: {e}", prefix.path.display()))?;
+    }
+    let pin = pin.filter(|pin| {
+        pin.name == name
+            && version.is_none_or(|version| !is_exact_version(version) || version == pin.version)
+    });
+    let Some(pin) = pin else {
+        return install_npm_package(spec, node_info, prefix);
+    };
+
+    let package_json = prefix
+        .path
+        .join("node_modules")
+        .join(name)
+        .join("package.json");
+    if locked_package(&prefix.path, name).as_ref() == Some(&pin) && package_json.is_file() {
+        return Ok(pin);
+    }
+
+    // The package went missing, or the lockfile no longer records the pinned version,
+    // put the pinned version back. The installed files themselves aren't checked.
+    let installed = run_npm_install(&format!("{name}@{}", pin.version), node_info, prefix)?;
+    if installed.integrity != pin.integrity {
+        return Err(format!(
+            "Integrity of {name}@{} does not match the one recorded when it was first installed, refusing to run it",
+            pin.version
+        ));
+    }
+    Ok(pin)
+}
+
+/// Install the newest version of a package matching its spec into a prefix and pin it,
+/// replacing the version pinned before
+pub fn install_npm_package(
+    spec: &str,
+    node_info: &NodeInfo,
+    prefix: &NpmPrefix,
+) -> Result<NpmPackagePin, String> {
+    let pin = run_npm_install(spec, node_info, prefix)?;
+    let pin_json = serde_json::to_string_pretty(&pin)
+        .map_err(|e| format!("Failed to serialize the pin of {}: {e}", pin.name))?;
+    let pin_path = prefix.path.join(PIN_FILE_NAME);
+    std::fs::write(&pin_path, pin_json)
+        .map_err(|e| format!("Failed to write {}: {e}", pin_path.display()))?;
+    println!("📦 Installed {}@{}", pin.name, pin.version);
+    Ok(pin)
+}
+
+/// Run `npm install` in a prefix and return what it installed for the package
+fn run_npm_install(
+    spec: &str,
+    node_info: &NodeInfo,
+    prefix: &NpmPrefix,
+) -> Result<NpmPackagePin, String> {
+    let Some(npm_path) = &node_info.npm_path else {
+        return Err(
+            "npm is needed to install MCP server packages, but it was not found".to_string(),
+        );
+    };
+    std::fs::create_dir_all(&prefix.path)
+        .map_err(|e| format!("Failed to create {}: {e}", prefix.path.display()))?;
+
+    let mut npm = Command::new(npm_path);
+    npm.envs(&prefix.env);
+    if let Some(path) = path_with_node_dir(node_info) {
+        npm.env("PATH", path);
+    }
+    npm.arg("install").arg("--prefix").arg(&prefix.path).args([
+        "--save-exact",
+        "--no-audit",
+        "--no-fund",
+        "--no-update-notifier",
+    ]);
+    if !prefix.run_install_scripts {
+        // Install scripts would run code from the package outside of any sandbox
+        npm.arg("--ignore-scripts");
+    }
+    let output = npm
+        .arg(spec)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run npm: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "npm install {spec} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    if !prefix.run_install_scripts {
+        let skipped = packages_with_install_scripts(&prefix.path);
+        if !skipped.is_empty() {
+            eprintln!(
+                "⚠️ Skipped the install scripts of {} for {spec}, the server may need them to run",
+                skipped.join(", ")
+            );
+        }
+    }
+
+    let (name, _) = parse_package_spec(spec);
+    locked_package(&prefix.path, name)
+        .ok_or_else(|| format!("npm did not record {name} in {}", prefix.path.display()))
+}
+
+/// Packages in a prefix's lockfile that come with install scripts
+fn packages_with_install_scripts(prefix: &Path) -> Vec<String> {
+    let Ok(lockfile) = std::fs::read_to_string(prefix.join("package-lock.json")) else {
+        return Vec::new();
+    };
+    let Ok(lockfile) = serde_json::from_str::<serde_json::Value>(&lockfile) else {
+        return Vec::new();
+    };
+    let Some(packages) = lockfile
+        .get("packages")
+        .and_then(|packages| packages.as_object())
+    else {
+        return Vec::new();
+    };
+    packages
+        .iter()
+        .filter(|(_, entry)| entry.get("hasInstallScript") == Some(&serde_json::Value::Bool(true)))
+        .filter_map(|(path, _)| path.rsplit_once("node_modules/"))
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Find the script an installed package runs as, picked the way npx does: its only
+/// binary, or else the one named after the package
+fn find_package_binary(prefix: &Path, name: &str) -> Result<PathBuf, String> {
+    let package_dir = prefix.join("node_modules").join(name);
+    let manifest_path = package_dir.join("package.json");
+    let manifest = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read {}: {e}", manifest_path.display()))?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest)
+        .map_err(|e| format!("Failed to parse {}: {e}", manifest_path.display()))?;
+
+    let unscoped_name = name.rsplit('/').next().unwrap_or(name);
+    let script = match manifest.get("bin") {
+        Some(serde_json::Value::String(script)) => Some(script.as_str()),
+        Some(serde_json::Value::Object(bins)) if bins.len() == 1 => {
+            bins.values().next().and_then(|script| script.as_str())
+        }
+        Some(serde_json::Value::Object(bins)) => {
+            bins.get(unscoped_name).and_then(|script| script.as_str())
+        }
+        _ => None,
+    };
+    script
+        .map(|script| package_dir.join(script))
+        .ok_or_else(|| format!("Package {name} has no binary to run"))
+}
+
 /// Find a globally installed npm package binary
 fn find_global_npm_binary(package_name: &str) -> Option<PathBuf> {
     // Common global npm binary locations
@@ -391,3 +752,272 @@ fn generate_restrictive_sandbox_profile(node_info: &NodeInfo) -> String {
 
     profile
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lay out a prefix the way `install_npm_package` leaves it
+    fn install_fake_package(prefix: &Path, integrity: &str) -> NpmPackagePin {
+        let package_dir = prefix.join("node_modules/@modelcontextprotocol/server-github");
+        std::fs::create_dir_all(package_dir.join("dist")).unwrap();
+        std::fs::write(
+            package_dir.join("package.json"),
+            r#"{"name":"@modelcontextprotocol/server-github","version":"1.2.3","bin":{"mcp-server-github":"dist/index.js"}}"#,
+        )
+        .unwrap();
+        let lockfile = serde_json::json!({
+            "lockfileVersion": 3,
+            "packages": {
+                "node_modules/@modelcontextprotocol/server-github": {
+                    "version": "1.2.3",
+                    "integrity": integrity,
+                }
+            }
+        });
+        std::fs::write(prefix.join("package-lock.json"), lockfile.to_string()).unwrap();
+
+        let pin = NpmPackagePin {
+            name: "@modelcontextprotocol/server-github".to_string(),
+            version: "1.2.3".to_string(),
+            integrity: Some("sha512-abc".to_string()),
+        };
+        std::fs::write(
+            prefix.join(PIN_FILE_NAME),
+            serde_json::to_string(&pin).unwrap(),
+        )
+        .unwrap();
+        pin
+    }
+
+    fn npm_prefix(path: &Path) -> NpmPrefix {
+        NpmPrefix {
+            path: path.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    fn node_without_npm() -> NodeInfo {
+        NodeInfo {
+            node_path: Some(PathBuf::from("node")),
+            npm_path: None,
+            npx_path: None,
+        }
+    }
+
+    /// Create an executable the way PATH lookups expect it on this platform
+    fn create_executable(dir: &Path, name: &str) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let path = if cfg!(target_os = "windows") {
+            dir.join(format!("{name}.exe"))
+        } else {
+            dir.join(name)
+        };
+        std::fs::write(&path, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_find_in_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let first = root.path().join("first");
+        let second = root.path().join("second");
+        let node = create_executable(&second, "node");
+        std::fs::create_dir_all(&first).unwrap();
+
+        assert_eq!(
+            find_in_dirs("node", [first.clone(), second.clone()]),
+            Some(node)
+        );
+        assert_eq!(find_in_dirs("npm", [first.clone(), second]), None);
+
+        // Files that can't be executed are skipped
+        #[cfg(unix)]
+        {
+            std::fs::write(first.join("npm"), "").unwrap();
+            assert_eq!(find_in_dirs("npm", [first]), None);
+        }
+    }
+
+    #[test]
+    fn test_newest_version_comes_first() {
+        let versions = tempfile::tempdir().unwrap();
+        for version in ["v18.19.0", "v20.9.0", "v20.11.1", "v9.0.0"] {
+            std::fs::create_dir(versions.path().join(version)).unwrap();
+        }
+
+        let names: Vec<String> = version_dirs(versions.path())
+            .iter()
+            .map(|dir| dir.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["v20.11.1", "v20.9.0", "v18.19.0", "v9.0.0"]);
+    }
+
+    #[test]
+    fn test_npm_and_npx_come_from_nodes_directory() {
+        let bin = tempfile::tempdir().unwrap();
+        let node = create_executable(bin.path(), "node");
+        let npm = create_executable(bin.path(), "npm");
+
+        let info = node_info_for(node.clone());
+        assert_eq!(info.node_path, Some(node));
+        assert_eq!(info.npm_path, Some(npm));
+        assert_eq!(info.npx_path, None);
+    }
+
+    #[test]
+    fn test_npx_args_and_package_specs() {
+        let args: Vec<String> = ["-y", "@scope/server@1.2.3", "./"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(
+            split_npx_args(&args),
+            Some(("@scope/server@1.2.3".to_string(), vec!["./".to_string()]))
+        );
+        assert_eq!(split_npx_args(&["-y".to_string()]), None);
+
+        assert_eq!(
+            parse_package_spec("@scope/server@1.2.3"),
+            ("@scope/server", Some("1.2.3"))
+        );
+        assert_eq!(parse_package_spec("@scope/server"), ("@scope/server", None));
+        assert_eq!(
+            parse_package_spec("server@latest"),
+            ("server", Some("latest"))
+        );
+        assert!(is_exact_version("1.2.3-beta.1"));
+        assert!(!is_exact_version("latest"));
+        assert!(!is_exact_version("^1.2.0"));
+    }
+
+    #[test]
+    fn test_pinned_package_runs_without_npm() {
+        let prefix = tempfile::tempdir().unwrap();
+        let pin = install_fake_package(prefix.path(), "sha512-abc");
+        let node_info = node_without_npm();
+
+        // Installed and matching its pin, so npm isn't needed
+        assert_eq!(
+            ensure_npm_package(
+                "@modelcontextprotocol/server-github",
+                &node_info,
+                &npm_prefix(prefix.path())
+            ),
+            Ok(pin.clone())
+        );
+        assert_eq!(
+            ensure_npm_package(
+                "@modelcontextprotocol/server-github@1.2.3",
+                &node_info,
+                &npm_prefix(prefix.path())
+            ),
+            Ok(pin)
+        );
+        let (command, args) = get_npm_execution_command(
+            "@modelcontextprotocol/server-github",
+            &node_info,
+            Some(&npm_prefix(prefix.path())),
+        )
+        .unwrap();
+        assert_eq!(command, "node");
+        assert_eq!(
+            PathBuf::from(&args[0]),
+            prefix
+                .path()
+                .join("node_modules/@modelcontextprotocol/server-github")
+                .join("dist/index.js")
+        );
+
+        // Another exact version has to be installed
+        assert!(ensure_npm_package(
+            "@modelcontextprotocol/server-github@2.0.0",
+            &node_info,
+            &npm_prefix(prefix.path())
+        )
+        .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_npm_install_skips_install_scripts() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let args_file = dir.path().join("args");
+        let npm = dir.path().join("npm");
+        std::fs::write(
+            &npm,
+            format!(
+                "#!/bin/sh\necho \"$HOME $@\" > {}\nexit 1\n",
+                args_file.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&npm, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let node_info = NodeInfo {
+            npm_path: Some(npm),
+            ..node_without_npm()
+        };
+        let mut prefix = NpmPrefix {
+            path: dir.path().join("prefix"),
+            env: HashMap::from([("HOME".to_string(), "/server-home".to_string())]),
+            run_install_scripts: false,
+        };
+
+        // npm runs with the server's environment, and without scripts unless allowed
+        assert!(run_npm_install("some-server", &node_info, &prefix).is_err());
+        let args = std::fs::read_to_string(&args_file).unwrap();
+        assert!(args.starts_with("/server-home install "), "{args}");
+        assert!(args.contains(" --ignore-scripts "), "{args}");
+
+        prefix.run_install_scripts = true;
+        assert!(run_npm_install("some-server", &node_info, &prefix).is_err());
+        let args = std::fs::read_to_string(&args_file).unwrap();
+        assert!(!args.contains("--ignore-scripts"), "{args}");
+    }
+
+    #[test]
+    fn test_packages_with_install_scripts() {
+        let prefix = tempfile::tempdir().unwrap();
+        let lockfile = serde_json::json!({
+            "lockfileVersion": 3,
+            "packages": {
+                "": {},
+                "node_modules/some-server": { "version": "1.0.0" },
+                "node_modules/@img/sharp": { "version": "0.33.0", "hasInstallScript": true },
+                "node_modules/a/node_modules/puppeteer": { "hasInstallScript": true },
+            }
+        });
+        std::fs::write(
+            prefix.path().join("package-lock.json"),
+            lockfile.to_string(),
+        )
+        .unwrap();
+
+        let mut skipped = packages_with_install_scripts(prefix.path());
+        skipped.sort();
+        assert_eq!(skipped, vec!["@img/sharp", "puppeteer"]);
+    }
+
+    #[test]
+    fn test_changed_package_is_reinstalled() {
+        let prefix = tempfile::tempdir().unwrap();
+        install_fake_package(prefix.path(), "sha512-tampered");
+
+        // The lockfile doesn't match the pin anymore, so the pinned version is installed
+        // again, which needs npm
+        let error = ensure_npm_package(
+            "@modelcontextprotocol/server-github",
+            &node_without_npm(),
+            &npm_prefix(prefix.path()),
+        )
+        .unwrap_err();
+        assert!(error.contains("npm is needed"));
+    }
+}
@@ -0,0 +1,396 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::node::{find_in_dirs, find_in_path, home_dir};
+
+#[derive(Debug, Clone)]
+pub struct PythonInfo {
+    pub python_path: Option<PathBuf>,
+    pub uv_path: Option<PathBuf>,
+    pub uvx_path: Option<PathBuf>,
+    pub pipx_path: Option<PathBuf>,
+}
+
+impl PythonInfo {
+    /// Whether Python packages can be run at all, uv brings its own Python if needed
+    pub fn is_available(&self) -> bool {
+        self.python_path.is_some() || self.uv_path.is_some() || self.uvx_path.is_some()
+    }
+
+    pub fn has_uvx(&self) -> bool {
+        self.uvx_path.is_some()
+    }
+}
+
+/// Detect Python, uv and pipx installations on the system
+pub fn detect_python_installation() -> PythonInfo {
+    let mut info = PythonInfo {
+        python_path: None,
+        uv_path: None,
+        uvx_path: None,
+        pipx_path: None,
+    };
+
+    // First check if commands are in PATH, Windows installs Python as `python`
+    info.python_path = find_in_path("python3").or_else(|| find_in_path("python"));
+    info.uv_path = find_in_path("uv");
+    info.uvx_path = find_in_path("uvx");
+    info.pipx_path = find_in_path("pipx");
+
+    // If not found in PATH, check common installation directories
+    if info.python_path.is_none() {
+        info.python_path = find_in_common_locations(&["python3", "python"]);
+    }
+    if info.uv_path.is_none() {
+        info.uv_path = find_in_common_locations(&["uv"]);
+    }
+    if info.uvx_path.is_none() {
+        info.uvx_path = find_in_common_locations(&["uvx"]);
+    }
+    if info.pipx_path.is_none() {
+        info.pipx_path = find_in_common_locations(&["pipx"]);
+    }
+
+    info
+}
+
+/// Common directories Python and its tools are installed in
+fn get_common_python_locations() -> Vec<PathBuf> {
+    let mut locations = vec![
+        PathBuf::from("/usr/local/bin"),
+        PathBuf::from("/usr/bin"),
+        PathBuf::from("/opt/homebrew/bin"),
+    ];
+
+    // The uv and pipx installers put their binaries in ~/.local/bin, cargo in ~/.cargo/bin
+    if let Some(home_path) = home_dir() {
+        locations.push(home_path.join(".local").join("bin"));
+        locations.push(home_path.join(".cargo").join("bin"));
+    }
+
+    // Windows locations
+    if cfg!(target_os = "windows") {
+        if let Ok(local_appdata) = env::var("LOCALAPPDATA") {
+            let python_root = PathBuf::from(&local_appdata).join("Programs\\Python");
+            if let Ok(entries) = std::fs::read_dir(&python_root) {
+                for entry in entries.flatten() {
+                    locations.push(entry.path());
+                    locations.push(entry.path().join("Scripts"));
+                }
+            }
+        }
+    }
+
+    locations
+}
+
+/// Find the first of the binaries in common installation locations
+fn find_in_common_locations(binaries: &[&str]) -> Option<PathBuf> {
+    let locations = get_common_python_locations();
+    binaries
+        .iter()
+        .find_map(|binary| find_in_dirs(binary, locations.iter().cloned()))
+}
+
+/// Python interpreter of a virtual environment
+pub fn venv_python(venv_dir: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        venv_dir.join("Scripts").join("python.exe")
+    } else {
+        venv_dir.join("bin").join("python")
+    }
+}
+
+/// Script a package installed into a virtual environment is run with
+fn venv_script(venv_dir: &Path, name: &str) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        venv_dir.join("Scripts").join(format!("{name}.exe"))
+    } else {
+        venv_dir.join("bin").join(name)
+    }
+}
+
+/// Create the virtual environment unless it already exists. It sees the system's site
+/// packages, so modules installed for the whole system keep working, while whatever a
+/// server installs stays in its own environment.
+pub fn ensure_venv(venv_dir: &Path, python_info: &PythonInfo) -> Result<PathBuf, String> {
+    let python = venv_python(venv_dir);
+    if python.is_file() {
+        return Ok(python);
+    }
+
+    let venv_arg = venv_dir.to_string_lossy().to_string();
+    let mut command = if let Some(uv_path) = &python_info.uv_path {
+        let mut command = Command::new(uv_path);
+        command.args(["venv", "--system-site-packages", &venv_arg]);
+        command
+    } else if let Some(python_path) = &python_info.python_path {
+        let mut command = Command::new(python_path);
+        command.args(["-m", "venv", "--system-site-packages", &venv_arg]);
+        command
+    } else {
+        return Err("Neither uv nor Python is available to create a virtual environment".into());
+    };
+
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to create virtual environment: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to create virtual environment: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(python)
+}
+
+/// Name of the script a package installs, e.g. `mcp-server-fetch` for `mcp-server-fetch[cli]==1.0`
+fn package_binary_name(package: &str) -> &str {
+    let end = package
+        .find(['[', '=', '<', '>', '~', '!', '@', ' '])
+        .unwrap_or(package.len());
+    &package[..end]
+}
+
+/// Get the command to execute a Python MCP server configured as `uvx`, `uv`, `pipx`,
+/// `python` or `python3`. `python` runs in the server's virtual environment when it has one.
+pub fn get_python_execution_command(
+    command: &str,
+    args: Vec<String>,
+    python_info: &PythonInfo,
+    venv_dir: Option<&Path>,
+) -> Result<(String, Vec<String>), String> {
+    let path_string = |path: &PathBuf| path.to_string_lossy().to_string();
+
+    match command {
+        "uvx" => {
+            let Some(package) = args.first() else {
+                return Err("No package specified for uvx command".to_string());
+            };
+
+            // First try uvx itself, then the tools that run a package the same way
+            if let Some(uvx_path) = &python_info.uvx_path {
+                return Ok((path_string(uvx_path), args));
+            }
+            if let Some(uv_path) = &python_info.uv_path {
+                let mut all_args = vec!["tool".to_string(), "run".to_string()];
+                all_args.extend(args);
+                return Ok((path_string(uv_path), all_args));
+            }
+            if let Some(pipx_path) = &python_info.pipx_path {
+                let mut all_args = vec!["run".to_string()];
+                all_args.extend(args);
+                return Ok((path_string(pipx_path), all_args));
+            }
+
+            // As a last resort, install the package into the server's virtual environment
+            if package.starts_with('-') {
+                return Err(format!(
+                    "uvx options like '{package}' need uv, which is not available"
+                ));
+            }
+            let Some(venv_dir) = venv_dir else {
+                return Err("Neither uvx, uv nor pipx is available on the system".to_string());
+            };
+            // Installed by an earlier start, which is what `uvx` would reuse too
+            let binary = venv_script(venv_dir, package_binary_name(package));
+            if !binary.is_file() {
+                let python = ensure_venv(venv_dir, python_info)?;
+                let output = Command::new(&python)
+                    .args(["-m", "pip", "install", "--quiet", package])
+                    .output()
+                    .map_err(|e| format!("Failed to install '{package}': {e}"))?;
+                if !output.status.success() {
+                    return Err(format!(
+                        "Failed to install '{package}': {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+            }
+            Ok((path_string(&binary), args[1..].to_vec()))
+        }
+        "uv" => match &python_info.uv_path {
+            Some(uv_path) => Ok((path_string(uv_path), args)),
+            None => Err("uv is not available on the system".to_string()),
+        },
+        "pipx" => match &python_info.pipx_path {
+            Some(pipx_path) => Ok((path_string(pipx_path), args)),
+            None => Err("pipx is not available on the system".to_string()),
+        },
+        "python" | "python3" => {
+            if let Some(venv_dir) = venv_dir {
+                let python = ensure_venv(venv_dir, python_info)?;
+                return Ok((path_string(&python), args));
+            }
+            match &python_info.python_path {
+                Some(python_path) => Ok((path_string(python_path), args)),
+                None => Err("Python is not available on the system".to_string()),
+            }
+        }
+        _ => Ok((command.to_string(), args)),
+    }
+}
+
+/// Whether a command runs a Python MCP server
+pub fn is_python_command(command: &str) -> bool {
+    matches!(command, "uvx" | "uv" | "pipx" | "python" | "python3")
+}
+
+/// Get a user-friendly error message for missing Python tooling
+pub fn get_python_installation_instructions() -> String {
+    let os = env::consts::OS;
+
+    match os {
+        "macos" => {
+            "Neither uv nor Python is installed on your system. Please install uv, which also \
+             takes care of Python, using one of these methods:\n\n\
+             1. Using Homebrew: brew install uv\n\
+             2. Using the installer: curl -LsSf https://astral.sh/uv/install.sh | sh\n\
+             3. Or install Python from: https://www.python.org/downloads/".to_string()
+        }
+        "linux" => {
+            "Neither uv nor Python is installed on your system. Please install uv, which also \
+             takes care of Python:\n\n\
+             curl -LsSf https://astral.sh/uv/install.sh | sh\n\n\
+             Or install Python using your package manager:\n\
+             Ubuntu/Debian: sudo apt-get install python3 python3-venv\n\
+             Fedora: sudo dnf install python3\n\
+             Arch: sudo pacman -S python".to_string()
+        }
+        "windows" => {
+            "Neither uv nor Python is installed on your system. Please:\n\n\
+             1. Install uv: powershell -ExecutionPolicy ByPass -c \"irm https://astral.sh/uv/install.ps1 | iex\"\n\
+             2. Or download Python from: https://www.python.org/downloads/\n\
+             3. Restart this application after installation".to_string()
+        }
+        _ => {
+            "Neither uv nor Python is installed on your system. Please install uv from \
+             https://docs.astral.sh/uv/ or Python from https://www.python.org/downloads/".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn python_info(uvx: bool, uv: bool, pipx: bool) -> PythonInfo {
+        PythonInfo {
+            python_path: None,
+            uv_path: uv.then(|| PathBuf::from("/opt/uv/uv")),
+            uvx_path: uvx.then(|| PathBuf::from("/opt/uv/uvx")),
+            pipx_path: pipx.then(|| PathBuf::from("/opt/pipx/pipx")),
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_uvx_falls_back_to_uv_and_pipx() {
+        let uvx_args = args(&["mcp-server-fetch", "--ignore-robots-txt"]);
+
+        let (command, command_args) = get_python_execution_command(
+            "uvx",
+            uvx_args.clone(),
+            &python_info(true, true, true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(PathBuf::from(command), PathBuf::from("/opt/uv/uvx"));
+        assert_eq!(command_args, uvx_args);
+
+        let (command, command_args) = get_python_execution_command(
+            "uvx",
+            uvx_args.clone(),
+            &python_info(false, true, true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(PathBuf::from(command), PathBuf::from("/opt/uv/uv"));
+        assert_eq!(
+            command_args,
+            args(&["tool", "run", "mcp-server-fetch", "--ignore-robots-txt"])
+        );
+
+        let (command, command_args) = get_python_execution_command(
+            "uvx",
+            uvx_args.clone(),
+            &python_info(false, false, true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(PathBuf::from(command), PathBuf::from("/opt/pipx/pipx"));
+        assert_eq!(
+            command_args,
+            args(&["run", "mcp-server-fetch", "--ignore-robots-txt"])
+        );
+
+        let nothing = python_info(false, false, false);
+        assert!(!nothing.is_available());
+        assert!(get_python_execution_command("uvx", uvx_args, &nothing, None).is_err());
+        assert!(
+            get_python_execution_command("uvx", vec![], &python_info(true, true, true), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_python_runs_in_existing_venv() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let venv_dir = data_dir.path().join("venv");
+        let python = venv_python(&venv_dir);
+        std::fs::create_dir_all(python.parent().unwrap()).unwrap();
+        std::fs::write(&python, "").unwrap();
+
+        // The environment is reused, nothing has to be available to create it
+        let (command, command_args) = get_python_execution_command(
+            "python",
+            args(&["-m", "mcp_server_time"]),
+            &python_info(false, false, false),
+            Some(&venv_dir),
+        )
+        .unwrap();
+        assert_eq!(PathBuf::from(command), python);
+        assert_eq!(command_args, args(&["-m", "mcp_server_time"]));
+    }
+
+    #[test]
+    fn test_uvx_reuses_package_installed_in_venv() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let venv_dir = data_dir.path().join("venv");
+        let binary = venv_script(&venv_dir, "mcp-server-fetch");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, "").unwrap();
+
+        // Nothing is installed again, so not even Python has to be available
+        let (command, command_args) = get_python_execution_command(
+            "uvx",
+            args(&["mcp-server-fetch==1.0", "--ignore-robots-txt"]),
+            &python_info(false, false, false),
+            Some(&venv_dir),
+        )
+        .unwrap();
+        assert_eq!(PathBuf::from(command), binary);
+        assert_eq!(command_args, args(&["--ignore-robots-txt"]));
+    }
+
+    #[test]
+    fn test_package_binary_name() {
+        assert_eq!(package_binary_name("mcp-server-fetch"), "mcp-server-fetch");
+        assert_eq!(
+            package_binary_name("mcp-server-fetch==1.0"),
+            "mcp-server-fetch"
+        );
+        assert_eq!(
+            package_binary_name("mcp-server-git[cli]>=2"),
+            "mcp-server-git"
+        );
+        assert_eq!(
+            package_binary_name("mcp-server-time@latest"),
+            "mcp-server-time"
+        );
+    }
+}
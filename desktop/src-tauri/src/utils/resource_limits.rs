@@ -0,0 +1,383 @@
+//! Resource limits for spawned MCP server processes.
+//!
+//! Where the app's cgroup is part of a cgroup v2 hierarchy it may manage, every
+//! server process gets a cgroup of its own capping the memory and the number of
+//! processes of the server and everything it spawns. The kernel counts in that
+//! cgroup when a cap was hit, which is how a process killed by one is told apart
+//! from one that crashed. Without cgroup v2 memory falls back to an rlimit and the
+//! process count isn't limited. CPU time and open files are rlimits either way, set
+//! in the forked child right before `exec`.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::process::Command;
+
+use super::names;
+use crate::models::mcp_server::{sandbox::TerminationReason, ResourceLimits};
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// Where the app moves itself when its own cgroup has to make room for the servers'
+const APP_CGROUP: &str = "archestra";
+const CGROUP_CONTROLLERS: &[&str] = &["memory", "pids"];
+// Killed processes leave their cgroup once they are reaped
+const CGROUP_REMOVE_ATTEMPTS: u32 = 50;
+const CGROUP_REMOVE_INTERVAL: Duration = Duration::from_millis(20);
+
+#[cfg(target_env = "gnu")]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(target_env = "gnu"))]
+type Resource = libc::c_int;
+
+/// How a process's limits are enforced, kept to tell which one it ran into
+pub struct AppliedLimits {
+    limits: ResourceLimits,
+    cgroup: Option<ServerCgroup>,
+    /// Limits this system can't enforce
+    pub warnings: Vec<String>,
+}
+
+impl AppliedLimits {
+    /// Work out whether the process exited because it ran into one of its limits
+    pub fn exceeded_limit(&self, status: &ExitStatus) -> Option<TerminationReason> {
+        if self.limits.max_cpu_seconds.is_some() && status.signal() == Some(libc::SIGXCPU) {
+            return Some(TerminationReason::CpuLimit);
+        }
+
+        let cgroup = self.cgroup.as_ref()?;
+        if event_count(&cgroup.path, "memory.events", "oom_kill") > 0 {
+            return Some(TerminationReason::MemoryLimit);
+        }
+        // Refused forks alone don't kill, but a server that failed after one likely died of it
+        if !status.success() && event_count(&cgroup.path, "pids.events", "max") > 0 {
+            return Some(TerminationReason::ProcessLimit);
+        }
+        None
+    }
+}
+
+/// Configure a command so the process it spawns runs under the given limits
+pub fn apply(
+    cmd: &mut Command,
+    server_name: &str,
+    limits: &ResourceLimits,
+) -> Result<AppliedLimits, String> {
+    let needs_cgroup = limits.max_memory_mb.is_some() || limits.max_processes.is_some();
+    let cgroup =
+        match servers_cgroup().filter(|_| needs_cgroup) {
+            Some(parent) => Some(ServerCgroup::create(parent, server_name, limits).map_err(
+                |e| format!("Failed to create a cgroup for MCP server '{server_name}': {e}"),
+            )?),
+            None => None,
+        };
+
+    let mut warnings = Vec::new();
+    if cgroup.is_none() && limits.max_processes.is_some() {
+        warnings.push(
+            "cgroup v2 is not available, the process count limit is not enforced".to_string(),
+        );
+    }
+
+    let rlimits = rlimits(limits, cgroup.is_some());
+    let cgroup_procs = cgroup.as_ref().map(|cgroup| cgroup.procs.as_raw_fd());
+    if !rlimits.is_empty() || cgroup_procs.is_some() {
+        unsafe {
+            cmd.pre_exec(move || {
+                // Writing 0 moves the writing process, before it can spawn anything
+                if let Some(fd) = cgroup_procs {
+                    if libc::write(fd, b"0".as_ptr().cast(), 1) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                for (resource, soft, hard) in &rlimits {
+                    let limit = libc::rlimit {
+                        rlim_cur: *soft,
+                        rlim_max: *hard,
+                    };
+                    if libc::setrlimit(*resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
+    Ok(AppliedLimits {
+        limits: limits.clone(),
+        cgroup,
+        warnings,
+    })
+}
+
+fn rlimits(limits: &ResourceLimits, in_cgroup: bool) -> Vec<(Resource, u64, u64)> {
+    let mut rlimits = Vec::new();
+
+    // RLIMIT_DATA rather than RLIMIT_AS, V8 reserves far more address space than it ever uses
+    if let Some(mb) = limits.max_memory_mb.filter(|_| !in_cgroup) {
+        let bytes = mb.saturating_mul(BYTES_PER_MB);
+        rlimits.push((libc::RLIMIT_DATA as Resource, bytes, bytes));
+    }
+    // The soft limit delivers SIGXCPU, which lets us tell this limit apart from other kills
+    if let Some(seconds) = limits.max_cpu_seconds {
+        rlimits.push((
+            libc::RLIMIT_CPU as Resource,
+            seconds,
+            seconds.saturating_add(1),
+        ));
+    }
+    if let Some(files) = limits.max_open_files {
+        rlimits.push((libc::RLIMIT_NOFILE as Resource, files, files));
+    }
+
+    rlimits
+}
+
+/// The cgroup server cgroups are created in, set up on first use. None if there is no
+/// cgroup v2 hierarchy the app may manage.
+fn servers_cgroup() -> Option<&'static Path> {
+    static SERVERS_CGROUP: OnceLock<Option<PathBuf>> = OnceLock::new();
+    SERVERS_CGROUP
+        .get_or_init(|| match set_up_servers_cgroup() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                println!("ℹ️ cgroup v2 is not available ({e}), limiting MCP servers with rlimits");
+                None
+            }
+        })
+        .as_deref()
+}
+
+fn set_up_servers_cgroup() -> io::Result<PathBuf> {
+    let own = std::fs::read_to_string("/proc/self/cgroup")?
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| Path::new(CGROUP_ROOT).join(path.trim().trim_start_matches('/')))
+        .ok_or_else(|| io::Error::other("not in a cgroup v2 hierarchy"))?;
+
+    let available = std::fs::read_to_string(own.join("cgroup.controllers"))?;
+    if !CGROUP_CONTROLLERS
+        .iter()
+        .all(|controller| available.split_whitespace().any(|c| c == *controller))
+    {
+        return Err(io::Error::other(
+            "memory and pids controllers are not available",
+        ));
+    }
+
+    // Only a cgroup without processes of its own may hand controllers down to children
+    if enable_controllers(&own).is_err() {
+        let app = own.join(APP_CGROUP);
+        create_cgroup(&app)?;
+        std::fs::write(app.join("cgroup.procs"), std::process::id().to_string())?;
+        enable_controllers(&own)?;
+    }
+    Ok(own)
+}
+
+fn enable_controllers(cgroup: &Path) -> io::Result<()> {
+    let controllers: Vec<String> = CGROUP_CONTROLLERS.iter().map(|c| format!("+{c}")).collect();
+    std::fs::write(cgroup.join("cgroup.subtree_control"), controllers.join(" "))
+}
+
+fn create_cgroup(path: &Path) -> io::Result<()> {
+    match std::fs::create_dir(path) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// The cgroup of one server process, everything left in it is killed when dropped
+struct ServerCgroup {
+    path: PathBuf,
+    // Opened up front, the forked child only has to write to it
+    procs: File,
+}
+
+impl ServerCgroup {
+    fn create(parent: &Path, server_name: &str, limits: &ResourceLimits) -> io::Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let name = names::sanitize(server_name);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = parent.join(format!("mcp-{name}-{id}"));
+        create_cgroup(&path)?;
+
+        let configure = || -> io::Result<File> {
+            if let Some(mb) = limits.max_memory_mb {
+                let bytes = mb.saturating_mul(BYTES_PER_MB);
+                std::fs::write(path.join("memory.max"), bytes.to_string())?;
+                // Missing without swap accounting, in which case there is no swap to escape to
+                let _ = std::fs::write(path.join("memory.swap.max"), "0");
+            }
+            if let Some(processes) = limits.max_processes {
+                std::fs::write(path.join("pids.max"), processes.to_string())?;
+            }
+            File::options().write(true).open(path.join("cgroup.procs"))
+        };
+        match configure() {
+            Ok(procs) => Ok(Self { path, procs }),
+            Err(e) => {
+                let _ = std::fs::remove_dir(&path);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for ServerCgroup {
+    fn drop(&mut self) {
+        // Needs Linux 5.14, on older kernels stray children keep the cgroup around
+        if let Ok(mut kill) = File::options()
+            .write(true)
+            .open(self.path.join("cgroup.kill"))
+        {
+            let _ = kill.write_all(b"1");
+        }
+        let path = self.path.clone();
+        std::thread::spawn(move || {
+            for _ in 0..CGROUP_REMOVE_ATTEMPTS {
+                if std::fs::remove_dir(&path).is_ok() {
+                    return;
+                }
+                std::thread::sleep(CGROUP_REMOVE_INTERVAL);
+            }
+        });
+    }
+}
+
+/// A counter from a cgroup's flat keyed events file, e.g. `oom_kill` in `memory.events`
+fn event_count(cgroup: &Path, file: &str, key: &str) -> u64 {
+    std::fs::read_to_string(cgroup.join(file))
+        .ok()
+        .and_then(|events| {
+            events.lines().find_map(|line| {
+                let (name, count) = line.split_once(' ')?;
+                (name == key).then(|| count.trim().parse().ok())?
+            })
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    #[test]
+    fn test_rlimits_only_for_configured_limits() {
+        assert!(rlimits(&ResourceLimits::default(), false).is_empty());
+
+        let limits = ResourceLimits {
+            max_memory_mb: Some(256),
+            max_cpu_seconds: Some(10),
+            max_processes: Some(8),
+            max_lifetime_seconds: Some(60),
+            ..Default::default()
+        };
+        assert_eq!(
+            rlimits(&limits, false),
+            vec![
+                (
+                    libc::RLIMIT_DATA as Resource,
+                    256 * BYTES_PER_MB,
+                    256 * BYTES_PER_MB
+                ),
+                (libc::RLIMIT_CPU as Resource, 10, 11),
+            ]
+        );
+        // The cgroup takes care of memory
+        assert_eq!(
+            rlimits(&limits, true),
+            vec![(libc::RLIMIT_CPU as Resource, 10, 11)]
+        );
+    }
+
+    #[test]
+    fn test_event_count() {
+        let cgroup = tempfile::tempdir().unwrap();
+        std::fs::write(
+            cgroup.path().join("memory.events"),
+            "low 0\nhigh 0\nmax 3\noom 1\noom_kill 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(event_count(cgroup.path(), "memory.events", "oom_kill"), 1);
+        assert_eq!(event_count(cgroup.path(), "memory.events", "max"), 3);
+        assert_eq!(event_count(cgroup.path(), "memory.events", "missing"), 0);
+        assert_eq!(event_count(cgroup.path(), "pids.events", "max"), 0);
+    }
+
+    #[test]
+    fn test_exceeded_limit_is_read_from_the_exit_status() {
+        let applied = AppliedLimits {
+            limits: ResourceLimits {
+                max_cpu_seconds: Some(1),
+                ..Default::default()
+            },
+            cgroup: None,
+            warnings: Vec::new(),
+        };
+        assert_eq!(
+            applied.exceeded_limit(&ExitStatus::from_raw(libc::SIGXCPU)),
+            Some(TerminationReason::CpuLimit)
+        );
+        assert_eq!(
+            applied.exceeded_limit(&ExitStatus::from_raw(libc::SIGABRT)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cpu_limit_is_reported() {
+        let limits = ResourceLimits {
+            max_cpu_seconds: Some(1),
+            ..Default::default()
+        };
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("while :; do :; done")
+            .stderr(Stdio::null());
+        let applied = apply(&mut cmd, "test", &limits).unwrap();
+
+        let status = cmd.status().await.unwrap();
+        assert_eq!(
+            applied.exceeded_limit(&status),
+            Some(TerminationReason::CpuLimit)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit_is_reported() {
+        if servers_cgroup().is_none() {
+            eprintln!("cgroup v2 is not available, skipping");
+            return;
+        }
+
+        let limits = ResourceLimits {
+            max_memory_mb: Some(16),
+            ..Default::default()
+        };
+        // tail keeps the whole line in memory, and there is no end of line coming
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("head -c 256M /dev/zero | tail -n 1")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let applied = apply(&mut cmd, "test", &limits).unwrap();
+
+        let status = cmd.status().await.unwrap();
+        assert_eq!(
+            applied.exceeded_limit(&status),
+            Some(TerminationReason::MemoryLimit)
+        );
+    }
+}
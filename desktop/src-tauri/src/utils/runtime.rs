@@ -0,0 +1,166 @@
+//! The interpreters servers are run with: the user's preferences, kept in a settings file
+//! in the app config dir, and a report of what was found on the system.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::RwLock;
+use tauri::Manager;
+use utoipa::ToSchema;
+
+use super::node::{self, NodeInfo};
+
+const SETTINGS_FILE_NAME: &str = "runtime.json";
+
+static SETTINGS: RwLock<RuntimeSettings> = RwLock::new(RuntimeSettings {
+    preferred_node_path: None,
+});
+static SETTINGS_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+/// Runtime preferences of the user
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = RuntimeSettings)]
+pub struct RuntimeSettings {
+    /// Node.js binary to run servers with instead of the one found on the system, npm
+    /// and npx are taken from the same directory
+    #[serde(default)]
+    pub preferred_node_path: Option<String>,
+}
+
+/// An executable that was found, and the version it reported
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(as = RuntimeExecutable)]
+pub struct ExecutableInfo {
+    pub path: String,
+    /// Missing if running it with `--version` failed
+    pub version: Option<String>,
+}
+
+/// The Node.js installation servers are run with
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(as = RuntimeInfo)]
+pub struct RuntimeInfo {
+    pub node: Option<ExecutableInfo>,
+    pub npm: Option<ExecutableInfo>,
+    pub npx: Option<ExecutableInfo>,
+    pub settings: RuntimeSettings,
+}
+
+/// Load the runtime settings from the app config dir, a missing file means defaults
+pub fn init_runtime_settings(app: &tauri::AppHandle) -> Result<(), String> {
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get app config directory: {e}"))?;
+    let path = config_dir.join(SETTINGS_FILE_NAME);
+
+    if path.exists() {
+        let settings = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let settings: RuntimeSettings = serde_json::from_str(&settings)
+            .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+        *SETTINGS.write().unwrap() = settings;
+    }
+    SETTINGS_PATH
+        .set(path)
+        .map_err(|_| "Runtime settings are already initialized".to_string())
+}
+
+pub fn settings() -> RuntimeSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// Check that the preferred interpreters exist
+pub fn validate_settings(settings: &RuntimeSettings) -> Result<(), String> {
+    if let Some(node_path) = &settings.preferred_node_path {
+        if !Path::new(node_path).is_file() {
+            return Err(format!("{node_path} is not a file"));
+        }
+    }
+    Ok(())
+}
+
+/// Replace the runtime settings, saving them to the settings file once it's known
+pub fn update_settings(settings: RuntimeSettings) -> Result<(), String> {
+    validate_settings(&settings)?;
+
+    if let Some(path) = SETTINGS_PATH.get() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize runtime settings: {e}"))?;
+        std::fs::write(path, json)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    }
+    *SETTINGS.write().unwrap() = settings;
+    Ok(())
+}
+
+/// The node the user picked, if it still exists
+pub fn preferred_node_path() -> Option<PathBuf> {
+    SETTINGS
+        .read()
+        .unwrap()
+        .preferred_node_path
+        .as_ref()
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+}
+
+/// Detect the Node.js installation and ask each of its executables for its version
+pub fn detect_runtime_info() -> RuntimeInfo {
+    let node_info = node::detect_node_installation();
+    let describe = |path: &Option<PathBuf>| {
+        path.as_ref().map(|path| ExecutableInfo {
+            path: path.to_string_lossy().to_string(),
+            version: executable_version(path, &node_info),
+        })
+    };
+
+    RuntimeInfo {
+        node: describe(&node_info.node_path),
+        npm: describe(&node_info.npm_path),
+        npx: describe(&node_info.npx_path),
+        settings: settings(),
+    }
+}
+
+fn executable_version(path: &Path, node_info: &NodeInfo) -> Option<String> {
+    let mut command = Command::new(path);
+    // npm and npx run the node in PATH
+    if let Some(path) = node::path_with_node_dir(node_info) {
+        command.env("PATH", path);
+    }
+    let output = command
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferred_node_must_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = RuntimeSettings {
+            preferred_node_path: Some(dir.path().join("node").to_string_lossy().to_string()),
+        };
+        assert!(validate_settings(&missing).is_err());
+
+        std::fs::write(dir.path().join("node"), "").unwrap();
+        assert!(validate_settings(&missing).is_ok());
+        assert!(validate_settings(&RuntimeSettings::default()).is_ok());
+    }
+}
@@ -8,6 +8,9 @@ import type {
   ConnectExternalMcpClientData,
   ConnectExternalMcpClientErrors,
   ConnectExternalMcpClientResponses,
+  DeleteSecretData,
+  DeleteSecretErrors,
+  DeleteSecretResponses,
   DisconnectExternalMcpClientData,
   DisconnectExternalMcpClientErrors,
   DisconnectExternalMcpClientResponses,
@@ -29,18 +32,51 @@ import type {
   GetMcpRequestLogsData,
   GetMcpRequestLogsErrors,
   GetMcpRequestLogsResponses,
+  GetMcpServerLogsData,
+  GetMcpServerLogsErrors,
+  GetMcpServerLogsResponses,
+  GetMcpServerStatusData,
+  GetMcpServerStatusErrors,
+  GetMcpServerStatusResponses,
+  GetRuntimeInfoData,
+  GetRuntimeInfoErrors,
+  GetRuntimeInfoResponses,
+  GetSecretsData,
+  GetSecretsErrors,
+  GetSecretsResponses,
   GetSupportedExternalMcpClientsData,
   GetSupportedExternalMcpClientsErrors,
   GetSupportedExternalMcpClientsResponses,
   InstallMcpServerFromCatalogData,
   InstallMcpServerFromCatalogErrors,
   InstallMcpServerFromCatalogResponses,
+  RestartMcpServerData,
+  RestartMcpServerErrors,
+  RestartMcpServerResponses,
+  SetSecretData,
+  SetSecretErrors,
+  SetSecretResponses,
+  StartMcpServerData,
+  StartMcpServerErrors,
   StartMcpServerOauthData,
   StartMcpServerOauthErrors,
   StartMcpServerOauthResponses,
+  StartMcpServerResponses,
+  StopMcpServerData,
+  StopMcpServerErrors,
+  StopMcpServerResponses,
+  StreamMcpServerLogsData,
+  StreamMcpServerLogsErrors,
+  StreamMcpServerLogsResponses,
   UninstallMcpServerData,
   UninstallMcpServerErrors,
   UninstallMcpServerResponses,
+  UpdateRuntimeSettingsData,
+  UpdateRuntimeSettingsErrors,
+  UpdateRuntimeSettingsResponses,
+  UpgradeMcpServerPackageData,
+  UpgradeMcpServerPackageErrors,
+  UpgradeMcpServerPackageResponses,
 } from './types.gen';
 
 export type Options<TData extends TDataShape = TDataShape, ThrowOnError extends boolean = boolean> = ClientOptions<
@@ -226,3 +262,123 @@ export const uninstallMcpServer = <ThrowOnError extends boolean = false>(
     ...options,
   });
 };
+
+export const getMcpServerLogs = <ThrowOnError extends boolean = false>(
+  options: Options<GetMcpServerLogsData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).get<GetMcpServerLogsResponses, GetMcpServerLogsErrors, ThrowOnError>({
+    url: '/api/mcp_server/{mcp_server_name}/logs',
+    ...options,
+  });
+};
+
+export const streamMcpServerLogs = <ThrowOnError extends boolean = false>(
+  options: Options<StreamMcpServerLogsData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).get<StreamMcpServerLogsResponses, StreamMcpServerLogsErrors, ThrowOnError>({
+    url: '/api/mcp_server/{mcp_server_name}/logs/stream',
+    ...options,
+  });
+};
+
+export const restartMcpServer = <ThrowOnError extends boolean = false>(
+  options: Options<RestartMcpServerData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).post<RestartMcpServerResponses, RestartMcpServerErrors, ThrowOnError>({
+    url: '/api/mcp_server/{mcp_server_name}/restart',
+    ...options,
+  });
+};
+
+export const startMcpServer = <ThrowOnError extends boolean = false>(
+  options: Options<StartMcpServerData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).post<StartMcpServerResponses, StartMcpServerErrors, ThrowOnError>({
+    url: '/api/mcp_server/{mcp_server_name}/start',
+    ...options,
+  });
+};
+
+export const getMcpServerStatus = <ThrowOnError extends boolean = false>(
+  options: Options<GetMcpServerStatusData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).get<GetMcpServerStatusResponses, GetMcpServerStatusErrors, ThrowOnError>({
+    url: '/api/mcp_server/{mcp_server_name}/status',
+    ...options,
+  });
+};
+
+export const stopMcpServer = <ThrowOnError extends boolean = false>(
+  options: Options<StopMcpServerData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).post<StopMcpServerResponses, StopMcpServerErrors, ThrowOnError>({
+    url: '/api/mcp_server/{mcp_server_name}/stop',
+    ...options,
+  });
+};
+
+export const upgradeMcpServerPackage = <ThrowOnError extends boolean = false>(
+  options: Options<UpgradeMcpServerPackageData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).post<
+    UpgradeMcpServerPackageResponses,
+    UpgradeMcpServerPackageErrors,
+    ThrowOnError
+  >({
+    url: '/api/mcp_server/{mcp_server_name}/upgrade',
+    ...options,
+  });
+};
+
+export const getRuntimeInfo = <ThrowOnError extends boolean = false>(
+  options?: Options<GetRuntimeInfoData, ThrowOnError>
+) => {
+  return (options?.client ?? _heyApiClient).get<GetRuntimeInfoResponses, GetRuntimeInfoErrors, ThrowOnError>({
+    url: '/api/runtime',
+    ...options,
+  });
+};
+
+export const updateRuntimeSettings = <ThrowOnError extends boolean = false>(
+  options: Options<UpdateRuntimeSettingsData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).put<
+    UpdateRuntimeSettingsResponses,
+    UpdateRuntimeSettingsErrors,
+    ThrowOnError
+  >({
+    url: '/api/runtime',
+    ...options,
+    headers: {
+      'Content-Type': 'application/json',
+      ...options.headers,
+    },
+  });
+};
+
+export const getSecrets = <ThrowOnError extends boolean = false>(options?: Options<GetSecretsData, ThrowOnError>) => {
+  return (options?.client ?? _heyApiClient).get<GetSecretsResponses, GetSecretsErrors, ThrowOnError>({
+    url: '/api/secret',
+    ...options,
+  });
+};
+
+export const deleteSecret = <ThrowOnError extends boolean = false>(
+  options: Options<DeleteSecretData, ThrowOnError>
+) => {
+  return (options.client ?? _heyApiClient).delete<DeleteSecretResponses, DeleteSecretErrors, ThrowOnError>({
+    url: '/api/secret/{name}',
+    ...options,
+  });
+};
+
+export const setSecret = <ThrowOnError extends boolean = false>(options: Options<SetSecretData, ThrowOnError>) => {
+  return (options.client ?? _heyApiClient).put<SetSecretResponses, SetSecretErrors, ThrowOnError>({
+    url: '/api/secret/{name}',
+    ...options,
+    headers: {
+      'Content-Type': 'application/json',
+      ...options.headers,
+    },
+  });
+};
@@ -5,6 +5,7 @@ export type ConnectExternalMcpClientRequest = {
 };
 
 export type CreateMcpRequestLog = {
+  batch_id?: string | null;
   client_info?: null | McpClientInfo;
   duration_ms?: number | null;
   error_message?: string | null;
@@ -34,6 +35,10 @@ export type InstallMcpServerRequest = {
   mcp_connector_id: string;
 };
 
+export type InstalledMcpServer = McpServer & {
+  status: McpServerStatus;
+};
+
 export type McpClientInfo = {
   client_name?: string | null;
   client_platform?: string | null;
@@ -66,6 +71,10 @@ export type McpoAuthResponse = {
 };
 
 export type McpRequestLog = {
+  /**
+   * Shared by the requests that were sent together in one JSON-RPC batch
+   */
+  batch_id?: string | null;
   client_info?: string | null;
   duration_ms?: number | null;
   error_message?: string | null;
@@ -84,6 +93,7 @@ export type McpRequestLog = {
 };
 
 export type McpRequestLogFilters = {
+  batch_id?: string | null;
   end_time?: string | null;
   mcp_session_id?: string | null;
   method?: string | null;
@@ -95,6 +105,7 @@ export type McpRequestLogFilters = {
 
 export type McpRequestLogStats = {
   avg_duration_ms: number;
+  cancelled_count: number;
   error_count: number;
   requests_per_server: {
     [key: string]: number;
@@ -105,29 +116,298 @@ export type McpRequestLogStats = {
 
 export type McpServer = {
   created_at: string;
+  /**
+   * Whether the server is started with the app, false once it was stopped explicitly
+   */
+  enabled: boolean;
   id: number;
   meta?: string | null;
   name: string;
   server_config: string;
 };
 
+/**
+ * When a server is started: with the app, or when a client first sends it a request
+ */
+export type McpServerActivation = 'eager' | 'lazy';
+
 export type McpServerConfig = {
+  activation?: McpServerActivation;
+  /**
+   * May refer to stored secrets as `secret://<name>`, resolved when the server starts
+   */
   args: Array<string>;
   command: string;
+  /**
+   * Image and container settings of a server with the "docker" transport
+   */
+  container?: null | McpServerContainerConfig;
+  /**
+   * Directory the server runs in, relative paths are taken from its own HOME under
+   * the app data dir, which is also where it runs if this isn't set
+   */
+  cwd?: string | null;
+  /**
+   * Environment of the process, or headers of a remote server. Values may refer to
+   * stored secrets like args do.
+   */
   env: {
     [key: string]: string;
   };
+  health_check?: null | McpServerHealthCheckConfig;
+  /**
+   * Stop a lazily activated server after this many minutes without requests, 10 by default
+   */
+  idle_timeout_minutes?: number | null;
+  /**
+   * Persist the server's output to the database, keeping it this many days
+   */
+  log_retention_days?: number | null;
+  /**
+   * Run this many identical processes of a stdio server and spread requests across them
+   */
+  replicas?: number | null;
+  resource_limits?: null | McpServerResourceLimits;
+  restart_policy?: null | McpServerRestartPolicy;
+  /**
+   * Let npm run install scripts when it installs the package of an `npx` server. Some
+   * packages need them to build native code, but they run outside of any sandbox.
+   */
+  run_install_scripts?: boolean;
+  /**
+   * Confine the server process, currently enforced on Linux only. Sandboxing is
+   * opt-in: a server without this setting runs with the user's full permissions
+   */
+  sandbox?: null | McpServerSandboxConfig;
+  timeouts?: null | McpServerTimeoutConfig;
   transport: string;
 };
 
+/**
+ * A server run from an OCI image, talking stdio through `docker run -i`. It's run with
+ * podman if that's the server's command, otherwise with docker, or podman if docker is
+ * missing. The container gets the server's env and its args follow the image.
+ */
+export type McpServerContainerConfig = {
+  image: string;
+  mounts?: Array<McpServerContainerMount>;
+  /**
+   * Network mode, e.g. "none" or "host", the runtime's default if not set
+   */
+  network?: string | null;
+};
+
+export type McpServerContainerMount = {
+  read_only?: boolean;
+  /**
+   * Directory on the host, relative paths are taken from the server's own HOME under
+   * the app data dir
+   */
+  source: string;
+  /**
+   * Where the directory shows up in the container
+   */
+  target: string;
+};
+
 export type McpServerDefinition = {
   meta?: unknown;
   name: string;
   server_config: McpServerConfig;
 };
 
+/**
+ * How a server process last exited
+ */
+export type McpServerExitInfo = {
+  exit_code?: number | null;
+  exited_at: string;
+  /**
+   * The last lines the process wrote to stderr before exiting
+   */
+  stderr_tail: Array<string>;
+  termination_reason: McpServerTerminationReason;
+};
+
+export type McpServerHealth = {
+  consecutive_failures: number;
+  last_checked_at?: string | null;
+  /**
+   * Round trip of the last answered ping
+   */
+  latency_ms?: number | null;
+  state: McpServerHealthState;
+};
+
+/**
+ * How a running MCP server is pinged to tell whether it still responds
+ */
+export type McpServerHealthCheckConfig = {
+  /**
+   * Answers slower than this mark the server as degraded
+   */
+  degraded_latency_ms?: number;
+  /**
+   * Seconds between two pings
+   */
+  interval_seconds?: number;
+  /**
+   * How long to wait for an answer before the ping counts as failed
+   */
+  timeout_ms?: number;
+  /**
+   * Consecutive failed pings after which the server is unresponsive
+   */
+  unresponsive_after?: number;
+};
+
+/**
+ * Whether a server answers its health check pings
+ */
+export type McpServerHealthState = 'starting' | 'healthy' | 'degraded' | 'unresponsive' | 'stopped';
+
+export type McpServerLog = {
+  id: number;
+  line: string;
+  server_name: string;
+  stream: McpServerLogStream;
+  timestamp: string;
+};
+
+/**
+ * Where a log line came from. `system` lines are written by the manager itself,
+ * e.g. when the process exits or fails to spawn.
+ */
+export type McpServerLogStream = 'stdout' | 'stderr' | 'system';
+
+/**
+ * The version of an npm package installed for a server, with the integrity hash npm
+ * recorded for it. Reinstalling the package has to give the same hash, so a tampered
+ * tarball is never run.
+ */
+export type McpServerNpmPackage = {
+  /**
+   * Subresource integrity hash of the package tarball, missing for packages that
+   * don't come from a registry
+   */
+  integrity?: string | null;
+  name: string;
+  version: string;
+};
+
+/**
+ * Caps on what a server process may consume. Everything but the lifetime is only
+ * enforced on Linux: memory and the process count through a cgroup of the server's
+ * own that covers everything it spawns, where cgroup v2 is available, CPU time and
+ * open files as rlimits of the server process. Without cgroup v2 memory is limited
+ * by an rlimit too and the process count isn't limited.
+ */
+export type McpServerResourceLimits = {
+  max_cpu_seconds?: number | null;
+  /**
+   * Kill (and restart) the process after it has been running this long
+   */
+  max_lifetime_seconds?: number | null;
+  /**
+   * Maximum memory, in megabytes
+   */
+  max_memory_mb?: number | null;
+  max_open_files?: number | null;
+  /**
+   * Maximum number of processes the server may run at once, itself included
+   */
+  max_processes?: number | null;
+};
+
+/**
+ * How a crashed MCP server process gets restarted
+ */
+export type McpServerRestartPolicy = {
+  /**
+   * Delay before the first restart, doubled after every consecutive crash
+   */
+  initial_backoff_ms?: number;
+  /**
+   * Consecutive restarts before the server is given up on and marked as crashed
+   */
+  max_restarts?: number;
+};
+
+export type McpServerSandboxConfig = {
+  allow_network?: boolean;
+  profile?: McpServerSandboxProfile;
+  /**
+   * Extra paths the server may read, on top of the profile's defaults
+   */
+  read_paths?: Array<string>;
+  /**
+   * Extra paths the server may write, on top of the profile's defaults
+   */
+  write_paths?: Array<string>;
+};
+
+/**
+ * Baseline set of paths and syscalls a sandboxed server gets, mirroring the
+ * tiers of `utils::node::generate_sandbox_profile`
+ */
+export type McpServerSandboxProfile = 'permissive' | 'restrictive';
+
+/**
+ * Lifecycle state of a managed MCP server
+ */
+export type McpServerState = 'running' | 'restarting' | 'crashed' | 'stopped' | 'idle';
+
+export type McpServerStatus = {
+  health: McpServerHealth;
+  last_exit?: null | McpServerExitInfo;
+  restart_count: number;
+  /**
+   * Parts of the configured sandbox this system can't enforce, the server runs less
+   * isolated than configured unless this is empty
+   */
+  sandbox_warnings: Array<string>;
+  state: McpServerState;
+};
+
+/**
+ * Why a server process stopped running
+ */
+export type McpServerTerminationReason =
+  | 'exited'
+  | 'spawn_failed'
+  | 'memory_limit'
+  | 'cpu_limit'
+  | 'process_limit'
+  | 'lifetime_limit';
+
+/**
+ * How long requests forwarded to a server may take before they are cancelled
+ */
+export type McpServerTimeoutConfig = {
+  /**
+   * Milliseconds any request may take, the gateway's 30 seconds if not set
+   */
+  default_ms?: number | null;
+  /**
+   * Milliseconds by JSON-RPC method, e.g. a short limit for "tools/list"
+   */
+  methods?: {
+    [key: string]: number;
+  };
+  /**
+   * Milliseconds by tool name, taking precedence over a "tools/call" method limit
+   */
+  tools?: {
+    [key: string]: number;
+  };
+};
+
 export type PaginatedMcpRequestLogResponseMcpRequestLog = {
   data: Array<{
+    /**
+     * Shared by the requests that were sent together in one JSON-RPC batch
+     */
+    batch_id?: string | null;
     client_info?: string | null;
     duration_ms?: number | null;
     error_message?: string | null;
@@ -149,6 +429,62 @@ export type PaginatedMcpRequestLogResponseMcpRequestLog = {
   total: number;
 };
 
+export type PaginatedMcpServerLogResponse = {
+  data: Array<McpServerLog>;
+  page: number;
+  page_size: number;
+  total: number;
+};
+
+/**
+ * An executable that was found, and the version it reported
+ */
+export type RuntimeExecutable = {
+  path: string;
+  /**
+   * Missing if running it with `--version` failed
+   */
+  version?: string | null;
+};
+
+/**
+ * The Node.js installation servers are run with
+ */
+export type RuntimeInfo = {
+  node?: null | RuntimeExecutable;
+  npm?: null | RuntimeExecutable;
+  npx?: null | RuntimeExecutable;
+  settings: RuntimeSettings;
+};
+
+/**
+ * Runtime preferences of the user
+ */
+export type RuntimeSettings = {
+  /**
+   * Node.js binary to run servers with instead of the one found on the system, npm
+   * and npx are taken from the same directory
+   */
+  preferred_node_path?: string | null;
+};
+
+/**
+ * A stored secret, its value is never returned
+ */
+export type Secret = {
+  created_at: string;
+  name: string;
+  /**
+   * What to put in a server's env values or args to use the secret
+   */
+  reference: string;
+  updated_at: string;
+};
+
+export type SetSecretRequest = {
+  value: string;
+};
+
 export type StartMcpServerOAuthRequest = {
   mcp_connector_id: string;
 };
@@ -282,6 +618,7 @@ export type GetMcpRequestLogsData = {
     mcp_session_id?: string | null;
     status_code?: number | null;
     method?: string | null;
+    batch_id?: string | null;
     start_time?: string | null;
     end_time?: string | null;
     page?: number | null;
@@ -315,6 +652,7 @@ export type GetMcpRequestLogStatsData = {
     mcp_session_id?: string | null;
     status_code?: number | null;
     method?: string | null;
+    batch_id?: string | null;
     start_time?: string | null;
     end_time?: string | null;
     page?: number | null;
@@ -389,7 +727,7 @@ export type GetInstalledMcpServersResponses = {
   /**
    * List of installed MCP servers
    */
-  200: Array<McpServer>;
+  200: Array<InstalledMcpServer>;
 };
 
 export type GetInstalledMcpServersResponse = GetInstalledMcpServersResponses[keyof GetInstalledMcpServersResponses];
@@ -469,7 +807,12 @@ export type UninstallMcpServerData = {
      */
     mcp_server_name: string;
   };
-  query?: never;
+  query?: {
+    /**
+     * Keep the server's data directory instead of deleting it
+     */
+    keep_data?: boolean | null;
+  };
   url: '/api/mcp_server/{mcp_server_name}';
 };
 
@@ -487,6 +830,373 @@ export type UninstallMcpServerResponses = {
   200: unknown;
 };
 
+export type GetMcpServerLogsData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the MCP server
+     */
+    mcp_server_name: string;
+  };
+  query?: {
+    stream?: null | McpServerLogStream;
+    page?: number | null;
+    page_size?: number | null;
+  };
+  url: '/api/mcp_server/{mcp_server_name}/logs';
+};
+
+export type GetMcpServerLogsErrors = {
+  /**
+   * MCP server not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type GetMcpServerLogsResponses = {
+  /**
+   * Paginated stdout/stderr lines of the MCP server, newest first
+   */
+  200: PaginatedMcpServerLogResponse;
+};
+
+export type GetMcpServerLogsResponse = GetMcpServerLogsResponses[keyof GetMcpServerLogsResponses];
+
+export type StreamMcpServerLogsData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the MCP server
+     */
+    mcp_server_name: string;
+  };
+  query?: never;
+  url: '/api/mcp_server/{mcp_server_name}/logs/stream';
+};
+
+export type StreamMcpServerLogsErrors = {
+  /**
+   * MCP server not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type StreamMcpServerLogsResponses = {
+  /**
+   * Server-sent `log` events, one per new stdout/stderr line
+   */
+  200: McpServerLog;
+};
+
+export type StreamMcpServerLogsResponse = StreamMcpServerLogsResponses[keyof StreamMcpServerLogsResponses];
+
+export type RestartMcpServerData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the MCP server to restart
+     */
+    mcp_server_name: string;
+  };
+  query?: never;
+  url: '/api/mcp_server/{mcp_server_name}/restart';
+};
+
+export type RestartMcpServerErrors = {
+  /**
+   * MCP server not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type RestartMcpServerResponses = {
+  /**
+   * MCP server restarted
+   */
+  200: McpServerStatus;
+};
+
+export type RestartMcpServerResponse = RestartMcpServerResponses[keyof RestartMcpServerResponses];
+
+export type StartMcpServerData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the MCP server to start
+     */
+    mcp_server_name: string;
+  };
+  query?: never;
+  url: '/api/mcp_server/{mcp_server_name}/start';
+};
+
+export type StartMcpServerErrors = {
+  /**
+   * MCP server not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type StartMcpServerResponses = {
+  /**
+   * MCP server started, or already running
+   */
+  200: McpServerStatus;
+};
+
+export type StartMcpServerResponse = StartMcpServerResponses[keyof StartMcpServerResponses];
+
+export type GetMcpServerStatusData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the MCP server
+     */
+    mcp_server_name: string;
+  };
+  query?: never;
+  url: '/api/mcp_server/{mcp_server_name}/status';
+};
+
+export type GetMcpServerStatusErrors = {
+  /**
+   * MCP server not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type GetMcpServerStatusResponses = {
+  /**
+   * Process state and latest health check of the MCP server
+   */
+  200: McpServerStatus;
+};
+
+export type GetMcpServerStatusResponse = GetMcpServerStatusResponses[keyof GetMcpServerStatusResponses];
+
+export type StopMcpServerData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the MCP server to stop
+     */
+    mcp_server_name: string;
+  };
+  query?: never;
+  url: '/api/mcp_server/{mcp_server_name}/stop';
+};
+
+export type StopMcpServerErrors = {
+  /**
+   * MCP server not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type StopMcpServerResponses = {
+  /**
+   * MCP server stopped, it stays stopped until started again
+   */
+  200: McpServerStatus;
+};
+
+export type StopMcpServerResponse = StopMcpServerResponses[keyof StopMcpServerResponses];
+
+export type UpgradeMcpServerPackageData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the MCP server whose npm package to upgrade
+     */
+    mcp_server_name: string;
+  };
+  query?: never;
+  url: '/api/mcp_server/{mcp_server_name}/upgrade';
+};
+
+export type UpgradeMcpServerPackageErrors = {
+  /**
+   * MCP server doesn't run an npm package, or pins it to an exact version
+   */
+  400: unknown;
+  /**
+   * MCP server not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type UpgradeMcpServerPackageResponses = {
+  /**
+   * Package upgraded and pinned to the new version, the server is restarted if it was running
+   */
+  200: McpServerNpmPackage;
+};
+
+export type UpgradeMcpServerPackageResponse = UpgradeMcpServerPackageResponses[keyof UpgradeMcpServerPackageResponses];
+
+export type GetRuntimeInfoData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: '/api/runtime';
+};
+
+export type GetRuntimeInfoErrors = {
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type GetRuntimeInfoResponses = {
+  /**
+   * Node.js, npm and npx found on the system, with their versions
+   */
+  200: RuntimeInfo;
+};
+
+export type GetRuntimeInfoResponse = GetRuntimeInfoResponses[keyof GetRuntimeInfoResponses];
+
+export type UpdateRuntimeSettingsData = {
+  body: RuntimeSettings;
+  path?: never;
+  query?: never;
+  url: '/api/runtime';
+};
+
+export type UpdateRuntimeSettingsErrors = {
+  /**
+   * Preferred interpreter doesn't exist
+   */
+  400: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type UpdateRuntimeSettingsResponses = {
+  /**
+   * Settings saved, with what is used from now on
+   */
+  200: RuntimeInfo;
+};
+
+export type UpdateRuntimeSettingsResponse = UpdateRuntimeSettingsResponses[keyof UpdateRuntimeSettingsResponses];
+
+export type GetSecretsData = {
+  body?: never;
+  path?: never;
+  query?: never;
+  url: '/api/secret';
+};
+
+export type GetSecretsErrors = {
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type GetSecretsResponses = {
+  /**
+   * Stored secrets, without their values
+   */
+  200: Array<Secret>;
+};
+
+export type GetSecretsResponse = GetSecretsResponses[keyof GetSecretsResponses];
+
+export type DeleteSecretData = {
+  body?: never;
+  path: {
+    /**
+     * Name of the secret to delete
+     */
+    name: string;
+  };
+  query?: never;
+  url: '/api/secret/{name}';
+};
+
+export type DeleteSecretErrors = {
+  /**
+   * Secret not found
+   */
+  404: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type DeleteSecretResponses = {
+  /**
+   * Secret deleted
+   */
+  200: unknown;
+};
+
+export type SetSecretData = {
+  body: SetSecretRequest;
+  path: {
+    /**
+     * Name of the secret, referenced as secret://<name>
+     */
+    name: string;
+  };
+  query?: never;
+  url: '/api/secret/{name}';
+};
+
+export type SetSecretErrors = {
+  /**
+   * Invalid secret name
+   */
+  400: unknown;
+  /**
+   * Internal server error
+   */
+  500: unknown;
+};
+
+export type SetSecretResponses = {
+  /**
+   * Secret created or replaced
+   */
+  200: Secret;
+};
+
+export type SetSecretResponse = SetSecretResponses[keyof SetSecretResponses];
+
 export type ClientOptions = {
   baseUrl: `${string}://openapi.json` | (string & {});
 };
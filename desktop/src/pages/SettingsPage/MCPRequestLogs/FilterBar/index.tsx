@@ -75,6 +75,7 @@ export default function FilterBar({ filters, onFiltersChange, onReset }: FilterB
                 <SelectItem value="all">All Status Codes</SelectItem>
                 <SelectItem value="200">200 - Success</SelectItem>
                 <SelectItem value="400">400 - Bad Request</SelectItem>
+                <SelectItem value="499">499 - Cancelled</SelectItem>
                 <SelectItem value="500">500 - Server Error</SelectItem>
               </SelectContent>
             </Select>
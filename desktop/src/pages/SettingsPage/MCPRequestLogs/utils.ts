@@ -17,6 +17,7 @@ export const getStatusColor = (statusCode: number): string => {
 
 export const getStatusLabel = (statusCode: number): string => {
   if (statusCode >= 200 && statusCode < 300) return 'Success';
+  if (statusCode === 499) return 'Cancelled';
   if (statusCode >= 400 && statusCode < 500) return 'Client Error';
   if (statusCode >= 500) return 'Server Error';
   return 'Unknown';
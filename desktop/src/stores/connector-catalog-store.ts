@@ -25,7 +25,7 @@ interface ConnectorCatalogState {
 
 interface ConnectorCatalogActions {
   installMCPServerFromConnectorCatalog: (mcpServer: ConnectorCatalogEntry) => Promise<void>;
-  uninstallMCPServer: (mcpServerName: string) => Promise<void>;
+  uninstallMCPServer: (mcpServerName: string, keepData?: boolean) => Promise<void>;
   loadConnectorCatalog: () => Promise<void>;
 }
 
@@ -113,7 +113,7 @@ export const useConnectorCatalogStore = create<ConnectorCatalogStore>((set) => (
     }
   },
 
-  uninstallMCPServer: async (mcpServerName: string) => {
+  uninstallMCPServer: async (mcpServerName: string, keepData = false) => {
     try {
       set({
         uninstallingMCPServerName: mcpServerName,
@@ -122,6 +122,7 @@ export const useConnectorCatalogStore = create<ConnectorCatalogStore>((set) => (
 
       const response = await uninstallMcpServer({
         path: { mcp_server_name: mcpServerName },
+        query: { keep_data: keepData },
       });
 
       if ('error' in response) {